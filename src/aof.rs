@@ -0,0 +1,309 @@
+//! Append-only file persistence: every write is logged as the RESP command
+//! that made it, and the file is replayed on startup so the keyspace
+//! survives a restart.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// When the log is flushed to disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fsync {
+    /// After every write, so none is lost.
+    Always,
+    /// Once a second, so at most a second of writes is lost.
+    #[default]
+    EverySec,
+    /// Whenever the operating system gets round to it.
+    No,
+}
+
+impl FromStr for Fsync {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "always" => Ok(Fsync::Always),
+            "everysec" => Ok(Fsync::EverySec),
+            "no" => Ok(Fsync::No),
+            _ => Err(format!("unknown fsync policy '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for Fsync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Fsync::Always => "always",
+            Fsync::EverySec => "everysec",
+            Fsync::No => "no",
+        })
+    }
+}
+
+#[cfg(feature = "server")]
+pub use self::file::{command, replay, Aof, Write};
+
+#[cfg(feature = "server")]
+mod file {
+    use bytes::{Bytes, BytesMut};
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, Write as _};
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use super::Fsync;
+    use crate::client::Client;
+    use crate::cmd::Command;
+    use crate::config::Config;
+    use crate::connection::Connection;
+    use crate::db::Databases;
+    use crate::pubsub::Subscriptions;
+    use crate::resp::{Frame, ParseError};
+    use crate::shared::Shared;
+
+    /// A logged write: the database it ran against and the command.
+    pub type Write = (usize, Frame);
+
+    /// An append-only file being written to.
+    #[derive(Debug)]
+    pub struct Aof {
+        fsync: Fsync,
+        log: Mutex<Log>,
+        /// A second handle on the file, so syncing it does not hold up
+        /// writes.
+        syncer: File,
+    }
+
+    #[derive(Debug)]
+    struct Log {
+        file: File,
+        /// The database the last logged write ran against, `None` until
+        /// the first write, which always selects its database.
+        db: Option<usize>,
+        /// How long the file is, counting only whole writes.
+        len: u64,
+        /// Why a write failed, once one has: from then on the file no
+        /// longer matches the keyspace, so no more writes are made.
+        failed: Option<String>,
+    }
+
+    impl Aof {
+        /// Opens `path` for appending, creating it if it does not exist.
+        pub fn open(path: &Path, fsync: Fsync) -> io::Result<Aof> {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let len = file.metadata()?.len();
+            Ok(Aof {
+                fsync,
+                syncer: file.try_clone()?,
+                log: Mutex::new(Log {
+                    file,
+                    db: None,
+                    len,
+                    failed: None,
+                }),
+            })
+        }
+
+        pub fn fsync(&self) -> Fsync {
+            self.fsync
+        }
+
+        /// Runs `f` and logs the writes it collects, with no other client's
+        /// writes in between, so that the file holds them in the order
+        /// they were made. All of them go to the file in a single write,
+        /// and one that fails is cut back off it, so a transaction is
+        /// logged whole or not at all unless the machine fails partway
+        /// through the write.
+        ///
+        /// Once a write has failed, `f` is not run and every call fails:
+        /// the writes `f` made were kept in memory, and more on top of them
+        /// would be lost on restart too.
+        pub fn record<T>(&self, f: impl FnOnce(&mut Vec<Write>) -> T) -> io::Result<T> {
+            let mut log = self.log.lock().unwrap();
+            if let Some(err) = &log.failed {
+                return Err(io::Error::other(err.clone()));
+            }
+            let mut writes = Vec::new();
+            let out = f(&mut writes);
+            if writes.is_empty() {
+                return Ok(out);
+            }
+
+            let mut buf = BytesMut::new();
+            for (db, frame) in writes {
+                if log.db != Some(db) {
+                    command(&[b"select", db.to_string().as_bytes()]).encode(&mut buf);
+                    log.db = Some(db);
+                }
+                frame.encode(&mut buf);
+            }
+            let written = log.file.write_all(&buf).and_then(|()| match self.fsync {
+                Fsync::Always => log.file.sync_data(),
+                _ => Ok(()),
+            });
+            match written {
+                Ok(()) => {
+                    log.len += buf.len() as u64;
+                    Ok(out)
+                }
+                Err(err) => {
+                    println!("error: writing to the append-only file: {err}");
+                    let len = log.len;
+                    if let Err(err) = log.file.set_len(len) {
+                        println!("error: truncating the append-only file: {err}");
+                    }
+                    // The next write cannot count on the select having made
+                    // it to the file.
+                    log.db = None;
+                    log.failed = Some(err.to_string());
+                    Err(err)
+                }
+            }
+        }
+
+        /// Flushes everything logged so far to disk.
+        pub fn sync(&self) -> io::Result<()> {
+            self.syncer.sync_data()
+        }
+    }
+
+    /// A command as a client would send it.
+    pub fn command(args: &[&[u8]]) -> Frame {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::BulkString(Bytes::copy_from_slice(arg)))
+                .collect(),
+        )
+    }
+
+    /// Runs the commands logged in the file at `path` against
+    /// `databases`, returning how many there were. A missing file holds
+    /// none. A command cut short at the end of the file, as by a crash
+    /// partway through writing it, is dropped from the file.
+    pub fn replay(path: &Path, databases: &Databases) -> io::Result<usize> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        // Limits put in place since must not drop what was already stored,
+        // and replies have nowhere to go.
+        let shared = Shared::new(Config::default());
+        let (discarded, _) = tokio::io::duplex(64);
+        let subscriptions = Subscriptions::new(shared.pubsub.clone());
+        let mut client = Client::new(0, Connection::new(discarded), subscriptions);
+
+        let mut pos = 0;
+        let mut count = 0;
+        while pos < data.len() {
+            let (frame, len) = match Frame::parse(&data[pos..]) {
+                Ok(parsed) => parsed,
+                Err(ParseError::Incomplete) => {
+                    println!(
+                        "warning: dropping the incomplete command at the end of {}",
+                        path.display()
+                    );
+                    OpenOptions::new()
+                        .write(true)
+                        .open(path)?
+                        .set_len(pos as u64)?;
+                    break;
+                }
+                Err(err) => return Err(corrupt(path, pos, err)),
+            };
+            let cmd = Command::from_frame(frame).map_err(|err| corrupt(path, pos, err))?;
+            // A write that failed was not logged, so any error here is
+            // one the original client saw too.
+            cmd.execute(databases, &shared, &mut client);
+            pos += len;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn corrupt(path: &Path, pos: usize, err: impl std::fmt::Display) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is corrupt at byte {pos}: {err}", path.display()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fsync() {
+        assert_eq!("always".parse(), Ok(Fsync::Always));
+        assert_eq!("EverySec".parse(), Ok(Fsync::EverySec));
+        assert_eq!("no".parse(), Ok(Fsync::No));
+        assert!("sometimes".parse::<Fsync>().is_err());
+        assert_eq!(Fsync::default().to_string(), "everysec");
+    }
+
+    #[cfg(feature = "server")]
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("redis-lite-{name}-{}.aof", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_replay_restores_writes_in_each_database() {
+        use bytes::Bytes;
+
+        let path = temp_path("replay");
+        let aof = Aof::open(&path, Fsync::Always).unwrap();
+        aof.record(|writes| {
+            writes.push((0, command(&[b"set", b"a", b"1"])));
+            writes.push((3, command(&[b"rpush", b"list", b"x", b"y"])));
+        })
+        .unwrap();
+        aof.record(|writes| writes.push((3, command(&[b"lpop", b"list"]))))
+            .unwrap();
+        drop(aof);
+
+        let databases = crate::db::DbHandle::with_databases(4, Default::default()).databases();
+        assert_eq!(replay(&path, &databases).unwrap(), 5);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(databases[0].get("a"), Some(Bytes::from("1")));
+        assert_eq!(
+            databases[3].lrange("list", 0, -1).unwrap(),
+            [Bytes::from("y")]
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_replay_drops_incomplete_command() {
+        let path = temp_path("truncated");
+        let whole = command(&[b"set", b"a", b"1"]).to_bytes();
+        let mut data = whole.to_vec();
+        data.extend_from_slice(&whole[..whole.len() - 3]);
+        std::fs::write(&path, data).unwrap();
+
+        let databases = crate::db::DbHandle::with_databases(1, Default::default()).databases();
+        assert_eq!(replay(&path, &databases).unwrap(), 1);
+        let left = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(left, whole);
+
+        assert!(replay(&path, &databases).is_ok());
+    }
+
+    #[cfg(all(feature = "server", target_os = "linux"))]
+    #[test]
+    fn test_failed_write_refuses_later_ones() {
+        let aof = Aof::open(std::path::Path::new("/dev/full"), Fsync::No).unwrap();
+        assert!(aof.record(|_| {}).is_ok());
+        let failed = aof.record(|writes| writes.push((0, command(&[b"set", b"a", b"1"]))));
+        assert!(failed.is_err());
+
+        let mut ran = false;
+        assert!(aof.record(|_| ran = true).is_err());
+        assert!(!ran);
+    }
+}
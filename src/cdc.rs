@@ -0,0 +1,325 @@
+//! Change data capture: streams every write to the keyspace, as one JSON
+//! object per line, to a file or TCP endpoint so other systems can mirror
+//! it. Embedders can instead receive the changes over a channel with
+//! [`forward`].
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::db::Change;
+use crate::export::{json_string, json_value};
+
+/// How many recent writes are kept for a sink that falls behind, or that
+/// reconnects after losing its connection.
+pub const BACKLOG: usize = 100_000;
+
+/// Where changes are streamed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Appended to a file, `file:PATH`.
+    File(PathBuf),
+    /// Written to a TCP connection, `tcp:HOST:PORT`, which is reopened
+    /// whenever it is lost.
+    Tcp(String),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("file", path)) if !path.is_empty() => Ok(Target::File(path.into())),
+            Some(("tcp", addr))
+                if addr.rsplit_once(':').is_some_and(|(host, port)| {
+                    !host.is_empty() && port.parse::<u16>().is_ok()
+                }) =>
+            {
+                Ok(Target::Tcp(addr.to_string()))
+            }
+            _ => Err(format!("unknown change data capture sink '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::File(path) => write!(f, "file:{}", path.display()),
+            Target::Tcp(addr) => write!(f, "tcp:{addr}"),
+        }
+    }
+}
+
+/// One change as a line of JSON: `{"offset":..,"op":"set","key":..,
+/// "type":..,"ttl":..,"value":..}` for a key that holds a value, with the
+/// TTL in milliseconds or -1 for none, and `{"offset":..,"op":"del",
+/// "key":..}` for one that was deleted or has expired, or
+/// `{"offset":..,"op":"flush"}` once every key was deleted. Values are
+/// written as EXPORT writes them.
+pub fn to_json(change: &Change) -> String {
+    let Some(key) = &change.key else {
+        return format!("{{\"offset\":{},\"op\":\"flush\"}}\n", change.offset);
+    };
+    let key = json_string(key);
+    match &change.value {
+        Some(value) => format!(
+            "{{\"offset\":{},\"op\":\"set\",\"key\":{key},\"type\":\"{}\",\"ttl\":{},\"value\":{}}}\n",
+            change.offset,
+            value.type_name(),
+            change.ttl.map_or(-1, |ttl| ttl.as_millis() as i64),
+            json_value(value)
+        ),
+        None => format!(
+            "{{\"offset\":{},\"op\":\"del\",\"key\":{key}}}\n",
+            change.offset
+        ),
+    }
+}
+
+/// The offset of the last complete change in `text`, as written by
+/// [`to_json`], so that a server writing to an existing file carries on
+/// numbering from there.
+pub fn last_offset(text: &str) -> Option<u64> {
+    let line = text
+        .strip_suffix('\n')?
+        .rsplit('\n')
+        .next()?
+        .strip_prefix("{\"offset\":")?;
+    let end = line.find(',')?;
+    line[..end].parse().ok()
+}
+
+#[cfg(feature = "server")]
+pub use self::stream::{forward, stream};
+
+#[cfg(feature = "server")]
+mod stream {
+    use std::future::Future;
+    use std::io;
+    use std::time::Duration;
+    use tokio::fs::OpenOptions;
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::sync::mpsc;
+
+    use super::{to_json, Target};
+    use crate::db::{Change, Db};
+
+    /// Changes written to the sink at a time.
+    const BATCH: usize = 512;
+
+    /// How long to wait before reconnecting to a sink that could not be
+    /// written to.
+    const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Streams the changes recorded in `db`, starting at `offset`, to
+    /// `target` until `stop` completes, then writes those still pending and
+    /// returns. Changes the sink could not take are sent again once it is
+    /// reopened, so a TCP sink may see a change more than once; offsets
+    /// tell the copies apart. A sink that falls more than
+    /// [`BACKLOG`](super::BACKLOG) changes behind skips to the oldest
+    /// change still held.
+    pub async fn stream(db: Db, target: Target, mut offset: u64, stop: impl Future<Output = ()>) {
+        tokio::pin!(stop);
+        let mut sink = None;
+        let mut stopping = false;
+        loop {
+            if !stopping {
+                tokio::select! {
+                    _ = db.wait_for_change(offset) => {}
+                    _ = &mut stop => stopping = true,
+                }
+            }
+
+            let changes = match db.changes_since(offset, BATCH) {
+                Ok(changes) => changes,
+                Err(lost) => {
+                    println!(
+                        "change data capture to {target} fell behind, skipping {} changes",
+                        lost.oldest - offset
+                    );
+                    offset = lost.oldest;
+                    continue;
+                }
+            };
+            if changes.is_empty() {
+                if stopping {
+                    return;
+                }
+                continue;
+            }
+
+            match write_changes(&mut sink, &target, &changes).await {
+                Ok(()) => offset = changes.last().unwrap().offset + 1,
+                Err(err) => {
+                    println!("error: change data capture to {target}: {err}");
+                    sink = None;
+                    if stopping {
+                        return;
+                    }
+                    tokio::select! {
+                        _ = tokio::time::sleep(RETRY_INTERVAL) => {}
+                        _ = &mut stop => stopping = true,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends the changes recorded in `db`, starting at `offset`, to
+    /// `changes` until the receiver is dropped. Returns the offset of the
+    /// first change not sent, so a new receiver can resume from it.
+    pub async fn forward(db: Db, changes: mpsc::Sender<Change>, mut offset: u64) -> u64 {
+        loop {
+            tokio::select! {
+                _ = db.wait_for_change(offset) => {}
+                _ = changes.closed() => return offset,
+            }
+            let batch = match db.changes_since(offset, BATCH) {
+                Ok(batch) => batch,
+                Err(lost) => {
+                    offset = lost.oldest;
+                    continue;
+                }
+            };
+            for change in batch {
+                let next = change.offset + 1;
+                if changes.send(change).await.is_err() {
+                    return offset;
+                }
+                offset = next;
+            }
+        }
+    }
+
+    async fn write_changes(
+        sink: &mut Option<Box<dyn AsyncWrite + Send + Unpin>>,
+        target: &Target,
+        changes: &[Change],
+    ) -> io::Result<()> {
+        let out = match sink {
+            Some(out) => out,
+            None => sink.insert(open(target).await?),
+        };
+        let text: String = changes.iter().map(to_json).collect();
+        out.write_all(text.as_bytes()).await?;
+        out.flush().await
+    }
+
+    async fn open(target: &Target) -> io::Result<Box<dyn AsyncWrite + Send + Unpin>> {
+        Ok(match target {
+            Target::File(path) => Box::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?,
+            ),
+            Target::Tcp(addr) => Box::new(TcpStream::connect(addr.as_str()).await?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Value;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            "file:/tmp/changes.jsonl".parse(),
+            Ok(Target::File("/tmp/changes.jsonl".into()))
+        );
+        assert_eq!(
+            "tcp:[::1]:9000".parse(),
+            Ok(Target::Tcp("[::1]:9000".to_string()))
+        );
+        assert_eq!(
+            Target::Tcp("host:9000".to_string()).to_string(),
+            "tcp:host:9000"
+        );
+        for bad in [
+            "",
+            "file:",
+            "tcp:host",
+            "tcp::9000",
+            "tcp:host:port",
+            "udp:host:1",
+        ] {
+            assert!(bad.parse::<Target>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let set = Change {
+            offset: 3,
+            key: Some("a\"b".to_string()),
+            value: Some(Value::from("v")),
+            ttl: Some(Duration::from_millis(1500)),
+        };
+        let del = Change {
+            offset: 4,
+            key: Some("gone".to_string()),
+            value: None,
+            ttl: None,
+        };
+
+        assert_eq!(
+            to_json(&set),
+            "{\"offset\":3,\"op\":\"set\",\"key\":\"a\\\"b\",\"type\":\"string\",\"ttl\":1500,\"value\":\"v\"}\n"
+        );
+        assert_eq!(
+            to_json(&del),
+            "{\"offset\":4,\"op\":\"del\",\"key\":\"gone\"}\n"
+        );
+        assert_eq!(last_offset(&(to_json(&set) + &to_json(&del))), Some(4));
+    }
+
+    #[test]
+    fn test_last_offset_ignores_partial_lines() {
+        assert_eq!(last_offset(""), None);
+        assert_eq!(last_offset("{\"offset\":7,\"op\":\"del\""), None);
+        assert_eq!(last_offset("garbage\n"), None);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_forward_resumes_from_offset() {
+        let db = crate::db::Db::new();
+        db.record_changes(BACKLOG, 0);
+        db.set("a".to_string(), bytes::Bytes::from("1"), None);
+        db.set("b".to_string(), bytes::Bytes::from("2"), None);
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let task = tokio::spawn(forward(db.clone(), tx, 1));
+        assert_eq!(rx.recv().await.unwrap().key.as_deref(), Some("b"));
+        db.set("c".to_string(), bytes::Bytes::from("3"), None);
+        assert_eq!(rx.recv().await.unwrap().offset, 2);
+        drop(rx);
+        assert_eq!(task.await.unwrap(), 3);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_stream_to_file_writes_pending_changes_on_stop() {
+        let path =
+            std::env::temp_dir().join(format!("redis-lite-cdc-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db = crate::db::Db::new();
+        db.record_changes(BACKLOG, 10);
+        db.set("a".to_string(), bytes::Bytes::from("1"), None);
+        db.del(&["a".to_string()]);
+
+        stream(db, Target::File(path.clone()), 10, async {}).await;
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.starts_with("{\"offset\":10,\"op\":\"del\""));
+        assert_eq!(last_offset(&text), Some(11));
+    }
+}
@@ -0,0 +1,60 @@
+//! The state of one connected client.
+
+use crate::cmd::Command;
+use crate::connection::Connection;
+use crate::db::KeyWatch;
+use crate::pubsub::Subscriptions;
+use crate::resp::Frame;
+use tokio::sync::watch;
+
+/// A client's connection, along with what its commands keep between
+/// requests.
+#[derive(Debug)]
+pub struct Client {
+    /// The client's id in the server's [`ClientList`](crate::stats::ClientList).
+    pub id: u64,
+    pub conn: Connection,
+    pub subscriptions: Subscriptions,
+    /// The database chosen with SELECT, which commands run against.
+    pub db: usize,
+    /// The commands queued since MULTI, `None` outside a transaction.
+    pub transaction: Option<Transaction>,
+    /// The keys WATCH was given, which make the next EXEC fail if they are
+    /// written first.
+    pub watched: Vec<KeyWatch>,
+    /// Set to `true` once the server starts shutting down, so that
+    /// commands waiting on something can stop. `None` without a server.
+    pub shutdown: Option<watch::Receiver<bool>>,
+}
+
+/// A transaction started by MULTI, run by EXEC.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    /// Each command with its request, kept if writes are being logged.
+    pub queued: Vec<(Command, Option<Frame>)>,
+    /// Set once a command could not be queued, so that EXEC refuses to
+    /// run the rest.
+    pub aborted: bool,
+}
+
+impl Client {
+    pub fn new(id: u64, conn: Connection, subscriptions: Subscriptions) -> Client {
+        Client {
+            id,
+            conn,
+            subscriptions,
+            db: 0,
+            transaction: None,
+            watched: Vec::new(),
+            shutdown: None,
+        }
+    }
+
+    /// Makes EXEC fail if a transaction is being queued, because a
+    /// command in it was rejected.
+    pub fn abort_transaction(&mut self) {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.aborted = true;
+        }
+    }
+}
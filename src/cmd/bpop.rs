@@ -0,0 +1,139 @@
+use bytes::Bytes;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::aof::{self, Aof};
+use crate::cmd::{aof_failed, CommandError, Parse};
+use crate::connection::Connection;
+use crate::db::{Db, ListEnd, WrongType};
+use crate::resp::Frame;
+
+/// `BLPOP key [key ...] timeout` and `BRPOP key [key ...] timeout` pop from
+/// the first of the keys holding a non-empty list, like LPOP and RPOP.
+/// When all of them are empty the connection waits for an element to be
+/// pushed, for up to `timeout` seconds or forever if it is 0. Replies with
+/// the key and the element, or nil on timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BPop {
+    end: ListEnd,
+    keys: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl BPop {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<BPop, CommandError> {
+        let end = match parse.name() {
+            "blpop" => ListEnd::Left,
+            _ => ListEnd::Right,
+        };
+        let mut keys = Vec::new();
+        while parse.remaining() > 1 {
+            keys.push(parse.next_string()?);
+        }
+        let timeout = parse_timeout(parse)?;
+        Ok(BPop { end, keys, timeout })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self.end {
+            ListEnd::Left => "blpop",
+            ListEnd::Right => "brpop",
+        }
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Waits for an element, giving up if the client on `conn` disconnects
+    /// first so that nothing is popped for a reply no one will read, or
+    /// with an error if the server starts shutting down. With
+    /// an append-only file, the pop is logged, as the plain pop it comes
+    /// to, under the same lock as it is made.
+    pub(crate) async fn apply(
+        self,
+        db: &Db,
+        conn: &mut Connection,
+        shutdown: Option<watch::Receiver<bool>>,
+        aof: Option<(&Aof, usize)>,
+    ) -> Frame {
+        let pop = || {
+            let popped = match aof {
+                None => db.pop_first(&self.keys, self.end),
+                Some((aof, index)) => aof
+                    .record(|writes| {
+                        let popped = db.pop_first(&self.keys, self.end);
+                        if let Ok(Some((key, _))) = &popped {
+                            let name = &self.name()[1..];
+                            writes.push((index, aof::command(&[name.as_bytes(), key.as_bytes()])));
+                        }
+                        popped
+                    })
+                    .map_err(aof_failed)?,
+            };
+            popped.map_err(|err| Frame::Error(err.to_string()))
+        };
+        let popped = async {
+            let popped = db.blocking_pop_with(pop);
+            match self.timeout {
+                None => popped.await.map(Some),
+                Some(timeout) => match tokio::time::timeout(timeout, popped).await {
+                    Ok(popped) => popped.map(Some),
+                    Err(_) => Ok(None),
+                },
+            }
+        };
+        tokio::select! {
+            // Checked first, so a pop that became possible just as the
+            // client left is not made.
+            biased;
+            _ = conn.closed() => Frame::Null,
+            _ = shutting_down(shutdown) => Frame::Error("SHUTDOWN in progress".into()),
+            popped = popped => popped.map_or_else(|err| err, |popped| reply(Ok(popped))),
+        }
+    }
+
+    /// Pops without waiting, as inside a transaction: replies with nil if
+    /// every list is empty.
+    pub(crate) fn apply_now(self, db: &Db) -> Frame {
+        reply(db.pop_first(&self.keys, self.end))
+    }
+}
+
+/// Completes once the server starts shutting down, and never for a client
+/// that has no server.
+async fn shutting_down(shutdown: Option<watch::Receiver<bool>>) {
+    if let Some(mut shutdown) = shutdown {
+        if shutdown.wait_for(|&shutdown| shutdown).await.is_ok() {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+fn reply(popped: Result<Option<(String, Bytes)>, WrongType>) -> Frame {
+    match popped {
+        Ok(Some((key, value))) => Frame::Array(vec![
+            Frame::BulkString(Bytes::from(key)),
+            Frame::BulkString(value),
+        ]),
+        Ok(None) => Frame::Null,
+        Err(err) => Frame::Error(err.to_string()),
+    }
+}
+
+/// Reads a blocking command's timeout in seconds, where 0 means forever.
+pub(crate) fn parse_timeout(parse: &mut Parse) -> Result<Option<Duration>, CommandError> {
+    let secs = parse
+        .next_float()
+        .map_err(|_| CommandError::Other("timeout is not a float or out of range".into()))?;
+    if secs < 0.0 {
+        return Err(CommandError::Other("timeout is negative".into()));
+    }
+    match secs {
+        0.0 => Ok(None),
+        secs => Duration::try_from_secs_f64(secs)
+            .map(Some)
+            .map_err(|_| CommandError::Other("timeout is not a float or out of range".into())),
+    }
+}
@@ -0,0 +1,58 @@
+use bytes::Bytes;
+use std::time::Duration;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `CAS key expected new [PX milliseconds]` replaces the value of `key` with
+/// `new` if it currently equals `expected`, replying with the value held
+/// before the call. This is a RedisLite extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cas {
+    key: String,
+    expected: Bytes,
+    value: Bytes,
+    expire: Option<Duration>,
+}
+
+impl Cas {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Cas, CommandError> {
+        let key = parse.next_string()?;
+        let expected = parse.next_bytes()?;
+        let value = parse.next_bytes()?;
+
+        let mut expire = None;
+        if parse.remaining() > 0 {
+            let opt = parse.next_string()?;
+            if !opt.eq_ignore_ascii_case("px") || parse.remaining() != 1 {
+                return Err(CommandError::Syntax);
+            }
+            expire = Some(Duration::from_millis(parse.next_int()?));
+        }
+
+        Ok(Cas {
+            key,
+            expected,
+            value,
+            expire,
+        })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The size of the value stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.value.len()
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.compare_and_set(&self.key, &self.expected, self.value, self.expire) {
+            Ok(None) => Frame::Null,
+            Ok(Some(old)) => Frame::BulkString(old),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+use crate::stats::ClientList;
+
+use std::time::Instant;
+
+/// `CLIENT LIST` replies with a line describing each connected client,
+/// and `CLIENT ID` with the id of the client asking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientCommand {
+    List,
+    Id,
+}
+
+impl ClientCommand {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ClientCommand, CommandError> {
+        let subcommand = parse.next_string()?.to_lowercase();
+        let cmd = match subcommand.as_str() {
+            "list" => ClientCommand::List,
+            "id" => ClientCommand::Id,
+            _ => {
+                return Err(CommandError::Other(format!(
+                    "unknown CLIENT subcommand '{subcommand}'"
+                )))
+            }
+        };
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub(crate) fn apply(self, clients: &ClientList, id: u64) -> Frame {
+        match self {
+            ClientCommand::List => {
+                let now = Instant::now();
+                let mut list = String::new();
+                for client in clients.list() {
+                    list.push_str(&client.line(now));
+                    list.push('\n');
+                }
+                Frame::BulkString(list.into())
+            }
+            ClientCommand::Id => Frame::Integer(id as i64),
+        }
+    }
+}
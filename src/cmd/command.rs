@@ -0,0 +1,72 @@
+use bytes::Bytes;
+
+use crate::cmd::{Command, CommandError, CommandSpec, Parse};
+use crate::resp::Frame;
+
+/// `COMMAND`, `COMMAND COUNT` and `COMMAND INFO name [name ...]` describe
+/// the commands the server supports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandInfo {
+    All,
+    Count,
+    Info(Vec<String>),
+}
+
+impl CommandInfo {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<CommandInfo, CommandError> {
+        if parse.remaining() == 0 {
+            return Ok(CommandInfo::All);
+        }
+
+        let subcommand = parse.next_string()?.to_lowercase();
+        match subcommand.as_str() {
+            "count" => {
+                parse.finish()?;
+                Ok(CommandInfo::Count)
+            }
+            "info" => {
+                let mut names = Vec::new();
+                while parse.remaining() > 0 {
+                    names.push(parse.next_string()?.to_lowercase());
+                }
+                Ok(CommandInfo::Info(names))
+            }
+            _ => Err(CommandError::Other(format!(
+                "unknown subcommand '{subcommand}'"
+            ))),
+        }
+    }
+
+    pub(crate) fn apply(self) -> Frame {
+        let table = Command::table();
+        match self {
+            CommandInfo::All => Frame::Array(table.iter().map(describe).collect()),
+            CommandInfo::Count => Frame::Integer(table.len() as i64),
+            CommandInfo::Info(names) => Frame::Array(
+                names
+                    .iter()
+                    .map(|name| match table.iter().find(|spec| spec.name == name) {
+                        Some(spec) => describe(spec),
+                        None => Frame::Null,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn describe(spec: &CommandSpec) -> Frame {
+    Frame::Array(vec![
+        Frame::BulkString(Bytes::from_static(spec.name.as_bytes())),
+        Frame::Integer(spec.arity.into()),
+        Frame::Array(
+            spec.flags
+                .iter()
+                .map(|flag| Frame::SimpleString(flag.to_string()))
+                .collect(),
+        ),
+        Frame::Integer(spec.first_key.into()),
+        Frame::Integer(spec.last_key.into()),
+        Frame::Integer(spec.step.into()),
+    ])
+}
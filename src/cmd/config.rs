@@ -0,0 +1,91 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::config;
+use crate::glob;
+use crate::resp::Frame;
+use crate::shared::Shared;
+
+/// `CONFIG GET pattern [pattern ...]` replies with the name and value of
+/// every setting matching a glob pattern. `CONFIG SET name value [name
+/// value ...]` changes settings that may be changed at runtime, all or
+/// none of them, and `CONFIG REWRITE` saves the settings to the config file
+/// the server was started with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigCommand {
+    Get(Vec<Bytes>),
+    /// Each setting's value, split into words as it would be on the command
+    /// line.
+    Set(Vec<(String, Vec<String>)>),
+    Rewrite,
+}
+
+impl ConfigCommand {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ConfigCommand, CommandError> {
+        let subcommand = parse.next_string()?.to_lowercase();
+        let wrong_arity = || CommandError::WrongArity(format!("config|{subcommand}"));
+        let cmd = match subcommand.as_str() {
+            "get" => {
+                let mut patterns = vec![parse.next_bytes().map_err(|_| wrong_arity())?];
+                while parse.remaining() > 0 {
+                    patterns.push(parse.next_bytes()?);
+                }
+                ConfigCommand::Get(patterns)
+            }
+            "set" => {
+                if parse.remaining() == 0 || !parse.remaining().is_multiple_of(2) {
+                    return Err(wrong_arity());
+                }
+                let mut settings = Vec::new();
+                while parse.remaining() > 0 {
+                    let name = parse.next_string()?;
+                    let value = parse.next_string()?;
+                    let values = match value.as_str() {
+                        "" => vec![String::new()],
+                        _ => value.split_whitespace().map(String::from).collect(),
+                    };
+                    settings.push((name, values));
+                }
+                ConfigCommand::Set(settings)
+            }
+            "rewrite" => ConfigCommand::Rewrite,
+            _ => {
+                return Err(CommandError::Other(format!(
+                    "unknown CONFIG subcommand '{subcommand}'"
+                )))
+            }
+        };
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub(crate) fn apply(self, shared: &Shared) -> Frame {
+        match self {
+            ConfigCommand::Get(patterns) => {
+                let current = shared.config.get();
+                let mut reply = Vec::new();
+                for param in config::PARAMS {
+                    if patterns
+                        .iter()
+                        .any(|pattern| glob::matches(pattern, param.name.as_bytes()))
+                    {
+                        reply.push(Frame::BulkString(Bytes::from_static(param.name.as_bytes())));
+                        reply.push(Frame::BulkString(param.get(&current).into()));
+                    }
+                }
+                Frame::Array(reply)
+            }
+            ConfigCommand::Set(settings) => match shared.config.set(&settings) {
+                Ok(config) => {
+                    shared.reconfigure(&config);
+                    Frame::SimpleString("OK".into())
+                }
+                Err(err) => Frame::Error(format!("ERR CONFIG SET failed: {err}")),
+            },
+            ConfigCommand::Rewrite => match shared.config.rewrite() {
+                Ok(()) => Frame::SimpleString("OK".into()),
+                Err(err) => Frame::Error(format!("ERR CONFIG REWRITE failed: {err}")),
+            },
+        }
+    }
+}
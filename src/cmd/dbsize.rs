@@ -0,0 +1,18 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `DBSIZE` replies with the number of keys that have not expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbSize;
+
+impl DbSize {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<DbSize, CommandError> {
+        parse.finish()?;
+        Ok(DbSize)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        Frame::Integer(db.len() as i64)
+    }
+}
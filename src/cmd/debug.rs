@@ -0,0 +1,269 @@
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+use crate::cmd::{CommandError, Parse};
+use crate::connection::Connection;
+use crate::db::{Db, KeySnapshot};
+use crate::net::normalize_peer_addr;
+use crate::resp::Frame;
+use crate::stats::Stats;
+use crate::value::Value;
+
+/// How long `DEBUG TRACEKEY key on` traces for unless told otherwise.
+const DEFAULT_TRACE_DURATION: Duration = Duration::from_secs(60);
+
+/// How many bytes of a value are shown in a trace line.
+const TRACE_VALUE_PREVIEW: usize = 32;
+
+/// The DEBUG subcommands used by benchmarking scripts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Debug {
+    /// `DEBUG POPULATE count [prefix] [size]`
+    Populate {
+        count: u64,
+        prefix: String,
+        size: Option<usize>,
+    },
+    /// `DEBUG SLEEP seconds`
+    Sleep(Duration),
+    /// `DEBUG QUICKACK 0|1`
+    QuickAck(bool),
+    /// `DEBUG TRACEKEY key ON [seconds]|OFF` logs every command that touches
+    /// `key` for the given time, 60 seconds by default.
+    TraceKey {
+        key: String,
+        /// `None` turns tracing off.
+        duration: Option<Duration>,
+    },
+    /// `DEBUG BADCLIENTS` lists the client addresses that have sent
+    /// malformed requests or unknown commands.
+    BadClients,
+}
+
+impl Debug {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Debug, CommandError> {
+        let subcommand = parse.next_string()?.to_lowercase();
+        let debug = match subcommand.as_str() {
+            "populate" => {
+                let count = parse.next_int()?;
+                let prefix = match parse.remaining() {
+                    0 => "key".to_string(),
+                    _ => parse.next_string()?,
+                };
+                let size = match parse.remaining() {
+                    0 => None,
+                    _ => Some(parse.next_int()?),
+                };
+                Debug::Populate {
+                    count,
+                    prefix,
+                    size,
+                }
+            }
+            "sleep" => {
+                let secs = parse.next_float()?;
+                Debug::Sleep(Duration::try_from_secs_f64(secs).map_err(|_| CommandError::NotFloat)?)
+            }
+            "tracekey" => {
+                let key = parse.next_string()?;
+                let duration = match parse.next_string()?.to_lowercase().as_str() {
+                    "on" if parse.remaining() == 0 => Some(DEFAULT_TRACE_DURATION),
+                    "on" => {
+                        let secs = parse.next_float()?;
+                        Some(
+                            Duration::try_from_secs_f64(secs)
+                                .map_err(|_| CommandError::NotFloat)?,
+                        )
+                    }
+                    "off" => None,
+                    _ => return Err(CommandError::Syntax),
+                };
+                Debug::TraceKey { key, duration }
+            }
+            "badclients" => Debug::BadClients,
+            "quickack" => match parse.next_string()?.as_str() {
+                "0" => Debug::QuickAck(false),
+                "1" => Debug::QuickAck(true),
+                _ => return Err(CommandError::Other("QUICKACK expects 0 or 1".into())),
+            },
+            _ => {
+                return Err(CommandError::Other(format!(
+                    "unknown DEBUG subcommand '{subcommand}'"
+                )))
+            }
+        };
+        parse.finish()?;
+        Ok(debug)
+    }
+
+    pub(crate) async fn apply(self, db: &Db, stats: &Stats, conn: &Connection) -> Frame {
+        match self {
+            Debug::Sleep(duration) => {
+                // Only this connection waits; other clients keep being served.
+                tokio::time::sleep(duration).await;
+                Frame::SimpleString("OK".into())
+            }
+            debug => debug.apply_now(db, stats, conn),
+        }
+    }
+
+    /// Runs the command without yielding, as inside a transaction, where
+    /// SLEEP holds up every other client too.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    pub(crate) fn apply_now(self, db: &Db, stats: &Stats, conn: &Connection) -> Frame {
+        match self {
+            Debug::Populate {
+                count,
+                prefix,
+                size,
+            } => {
+                db.populate(count, &prefix, size);
+            }
+            Debug::Sleep(duration) => std::thread::sleep(duration),
+            Debug::TraceKey { key, duration } => {
+                db.trace_key(&key, duration);
+            }
+            Debug::BadClients => return bad_clients(stats),
+            #[cfg(target_os = "linux")]
+            Debug::QuickAck(enable) => {
+                let Some(socket) = conn.socket() else {
+                    return Frame::Error("ERR QUICKACK needs a TCP connection".into());
+                };
+                if let Err(err) = socket2::SockRef::from(socket).set_tcp_quickack(enable) {
+                    return Frame::Error(format!("ERR {err}"));
+                }
+            }
+            #[cfg(not(target_os = "linux"))]
+            Debug::QuickAck(_) => {
+                return Frame::Error("ERR QUICKACK is only supported on Linux".into());
+            }
+        }
+        Frame::SimpleString("OK".into())
+    }
+}
+
+/// One entry per address, alternating `ip` with `[protocol_errors, n,
+/// unknown_commands, n, banned_ms, n]`, where `banned_ms` is 0 unless the
+/// address is banned.
+fn bad_clients(stats: &Stats) -> Frame {
+    let now = Instant::now();
+    let mut frames = Vec::new();
+    for (ip, client) in stats.bad_clients.report() {
+        let banned = client.banned_for(now).unwrap_or_default();
+        frames.push(Frame::BulkString(Bytes::from(ip.to_string())));
+        frames.push(Frame::Array(vec![
+            Frame::BulkString(Bytes::from_static(b"protocol_errors")),
+            Frame::Integer(client.protocol_errors as i64),
+            Frame::BulkString(Bytes::from_static(b"unknown_commands")),
+            Frame::Integer(client.unknown_commands as i64),
+            Frame::BulkString(Bytes::from_static(b"banned_ms")),
+            Frame::Integer(banned.as_millis() as i64),
+        ]));
+    }
+    Frame::Array(frames)
+}
+
+/// Logs a command that touched a traced key, with the key's state before
+/// and after it ran.
+pub(crate) fn trace(
+    command: &str,
+    conn: &Connection,
+    key: &str,
+    before: Option<KeySnapshot>,
+    after: Option<KeySnapshot>,
+) {
+    let client = match conn.socket().map(TcpStream::peer_addr) {
+        Some(Ok(addr)) => normalize_peer_addr(addr).to_string(),
+        _ => "unknown".to_string(),
+    };
+    println!(
+        "trace {key}: {command} from {client}: {} -> {}",
+        describe(before.as_ref()),
+        describe(after.as_ref())
+    );
+}
+
+fn describe(snapshot: Option<&KeySnapshot>) -> String {
+    let Some(snapshot) = snapshot else {
+        return "(none)".to_string();
+    };
+    let ttl = match snapshot.ttl {
+        None => "no ttl".to_string(),
+        Some(ttl) => format!("ttl {}ms", ttl.as_millis()),
+    };
+    match &snapshot.value {
+        Value::String(data) => {
+            let preview = &data[..data.len().min(TRACE_VALUE_PREVIEW)];
+            let ellipsis = if data.len() > preview.len() {
+                "..."
+            } else {
+                ""
+            };
+            format!(
+                "string({}) \"{}{ellipsis}\", {ttl}",
+                data.len(),
+                preview.escape_ascii()
+            )
+        }
+        Value::List(list) => format!("list({} elements), {ttl}", list.len()),
+        Value::Hash(hash) => format!("hash({} fields), {ttl}", hash.len()),
+        Value::Set(set) => format!("set({} members), {ttl}", set.len()),
+        Value::SortedSet(set) => format!("zset({} members), {ttl}", set.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Offence;
+
+    #[test]
+    fn test_bad_clients_report() {
+        let stats = Stats::default();
+        let ip = "10.0.0.1".parse().unwrap();
+        stats.bad_clients.record(ip, Offence::Protocol);
+        stats.bad_clients.record(ip, Offence::Protocol);
+
+        assert_eq!(
+            bad_clients(&stats),
+            Frame::Array(vec![
+                Frame::BulkString(Bytes::from("10.0.0.1")),
+                Frame::Array(vec![
+                    Frame::BulkString(Bytes::from("protocol_errors")),
+                    Frame::Integer(2),
+                    Frame::BulkString(Bytes::from("unknown_commands")),
+                    Frame::Integer(0),
+                    Frame::BulkString(Bytes::from("banned_ms")),
+                    Frame::Integer(0),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_describe_snapshot() {
+        let snapshot = KeySnapshot {
+            key: "Foo".into(),
+            value: Value::from("a\nb"),
+            ttl: Some(Duration::from_millis(1500)),
+        };
+
+        assert_eq!(describe(None), "(none)");
+        assert_eq!(describe(Some(&snapshot)), "string(3) \"a\\nb\", ttl 1500ms");
+    }
+
+    #[test]
+    fn test_describe_truncates_long_values() {
+        let snapshot = KeySnapshot {
+            key: "Foo".into(),
+            value: Value::from("x".repeat(100)),
+            ttl: None,
+        };
+
+        assert_eq!(
+            describe(Some(&snapshot)),
+            format!("string(100) \"{}...\", no ttl", "x".repeat(32))
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `DEL key [key ...]` removes the given keys and replies with how many
+/// existed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Del {
+    keys: Vec<String>,
+}
+
+impl Del {
+    pub fn new(keys: Vec<String>) -> Del {
+        Del { keys }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Del, CommandError> {
+        let mut keys = vec![parse.next_string()?];
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+        }
+        Ok(Del { keys })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        Frame::Integer(db.del(&self.keys) as i64)
+    }
+}
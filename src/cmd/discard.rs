@@ -0,0 +1,23 @@
+use crate::client::Client;
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+
+/// `DISCARD` ends a transaction without running the queued commands, and
+/// unwatches every key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discard;
+
+impl Discard {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Discard, CommandError> {
+        parse.finish()?;
+        Ok(Discard)
+    }
+
+    pub(crate) fn apply(self, client: &mut Client) -> Frame {
+        client.watched.clear();
+        match client.transaction.take() {
+            Some(_) => Frame::SimpleString("OK".into()),
+            None => Frame::Error("ERR DISCARD without MULTI".into()),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+
+/// `ECHO message` replies with `message`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Echo {
+    msg: Bytes,
+}
+
+impl Echo {
+    pub fn new(msg: impl Into<Bytes>) -> Echo {
+        Echo { msg: msg.into() }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Echo, CommandError> {
+        let msg = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Echo { msg })
+    }
+
+    pub(crate) fn apply(self) -> Frame {
+        Frame::BulkString(self.msg)
+    }
+}
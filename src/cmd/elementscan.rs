@@ -0,0 +1,90 @@
+use bytes::Bytes;
+
+use crate::cmd::scan::{self, ScanOptions};
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::float;
+use crate::resp::Frame;
+
+/// `HSCAN`, `SSCAN` and `ZSCAN key cursor [MATCH pattern] [COUNT count]`
+/// walk the fields of a hash with their values, the members of a set, or
+/// the members of a sorted set with their scores, the way SCAN walks the
+/// keyspace. `MATCH` is matched against fields and members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementScan {
+    name: &'static str,
+    key: String,
+    cursor: u64,
+    options: ScanOptions,
+}
+
+impl ElementScan {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ElementScan, CommandError> {
+        let name = match parse.name() {
+            "hscan" => "hscan",
+            "sscan" => "sscan",
+            _ => "zscan",
+        };
+        let key = parse.next_string()?;
+        let cursor = scan::parse_cursor(parse)?;
+        let options = ScanOptions::parse(parse, |_, _| Err(CommandError::Syntax))?;
+        Ok(ElementScan {
+            name,
+            key,
+            cursor,
+            options,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let (key, cursor, count) = (&self.key, self.cursor, self.options.count);
+        let pattern = self.options.pattern.as_deref();
+        let step = match self.name {
+            "hscan" => db
+                .hscan(key, cursor, count, pattern)
+                .map(|(cursor, fields)| {
+                    let found = fields
+                        .into_iter()
+                        .flat_map(|(field, value)| {
+                            [
+                                Frame::BulkString(Bytes::from(field)),
+                                Frame::BulkString(value),
+                            ]
+                        })
+                        .collect();
+                    (cursor, found)
+                }),
+            "sscan" => db
+                .sscan(key, cursor, count, pattern)
+                .map(|(cursor, members)| {
+                    (cursor, members.into_iter().map(Frame::BulkString).collect())
+                }),
+            _ => db
+                .zscan(key, cursor, count, pattern)
+                .map(|(cursor, members)| {
+                    let found = members
+                        .into_iter()
+                        .flat_map(|(member, score)| {
+                            [
+                                Frame::BulkString(member),
+                                Frame::BulkString(Bytes::from(float::format(score))),
+                            ]
+                        })
+                        .collect();
+                    (cursor, found)
+                }),
+        };
+        match step {
+            Ok((cursor, found)) => scan::reply(cursor, found),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::aof::Write;
+use crate::client::Client;
+use crate::cmd::{aof_failed, CommandError, Parse};
+use crate::db::{Databases, KeyWatch};
+use crate::resp::Frame;
+use crate::shared::Shared;
+
+/// `EXEC` runs the commands queued since MULTI, with no other client's
+/// commands in between, and replies with an array of their replies. If
+/// any command could not be queued, or a key given to WATCH has been
+/// written since, nothing is run; the latter replies with nil. Either way
+/// every key is unwatched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exec;
+
+impl Exec {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Exec, CommandError> {
+        parse.finish()?;
+        Ok(Exec)
+    }
+
+    pub(crate) fn apply(
+        self,
+        databases: &Databases,
+        shared: &Shared,
+        client: &mut Client,
+    ) -> Frame {
+        let Some(transaction) = client.transaction.take() else {
+            return Frame::Error("ERR EXEC without MULTI".into());
+        };
+        let watched = std::mem::take(&mut client.watched);
+        let writes = transaction.queued.iter().any(|(cmd, _)| cmd.is_write());
+        if transaction.aborted {
+            return Frame::Error(
+                "EXECABORT Transaction discarded because of previous errors.".into(),
+            );
+        }
+        let run = |writes: &mut Vec<Write>| {
+            databases.exclusive(|| {
+                if watched.iter().any(KeyWatch::is_modified) {
+                    return Frame::Null;
+                }
+                let replies = transaction
+                    .queued
+                    .into_iter()
+                    .map(|(cmd, request)| cmd.run(request, databases, shared, client, writes))
+                    .collect();
+                Frame::Array(replies)
+            })
+        };
+        match &shared.aof {
+            Some(aof) if writes => aof.record(run).unwrap_or_else(aof_failed),
+            _ => run(&mut Vec::new()),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `EXISTS key [key ...]` replies with how many of the given keys exist.
+/// A key named more than once is counted each time, as in Redis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exists {
+    keys: Vec<String>,
+}
+
+impl Exists {
+    pub fn new(keys: Vec<String>) -> Exists {
+        Exists { keys }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Exists, CommandError> {
+        let mut keys = vec![parse.next_string()?];
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+        }
+        Ok(Exists { keys })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let count = self.keys.iter().filter(|key| db.exists(key)).count();
+        Frame::Integer(count as i64)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, ExpireCondition};
+use crate::resp::Frame;
+use crate::time;
+
+/// `EXPIRE key seconds`, `PEXPIRE key milliseconds`, `EXPIREAT key
+/// unix-seconds` and `PEXPIREAT key unix-milliseconds`, each optionally
+/// followed by `NX`, `XX`, `GT` or `LT`. Replies with 1 if the TTL was set
+/// and 0 if the key does not exist or the condition was not met. A time in
+/// the past deletes the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expire {
+    name: &'static str,
+    key: String,
+    /// Milliseconds from now, or since the UNIX epoch if `absolute`.
+    millis: i64,
+    absolute: bool,
+    condition: ExpireCondition,
+}
+
+impl Expire {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Expire, CommandError> {
+        let (name, scale, absolute) = match parse.name() {
+            "expire" => ("expire", 1000, false),
+            "pexpire" => ("pexpire", 1, false),
+            "expireat" => ("expireat", 1000, true),
+            _ => ("pexpireat", 1, true),
+        };
+        let key = parse.next_string()?;
+        let millis = parse
+            .next_int::<i64>()?
+            .checked_mul(scale)
+            .ok_or_else(|| invalid_time(name))?;
+
+        let mut condition = ExpireCondition::default();
+        while parse.remaining() > 0 {
+            let opt = parse.next_string()?.to_lowercase();
+            match opt.as_str() {
+                "nx" => condition.nx = true,
+                "xx" => condition.xx = true,
+                "gt" => condition.gt = true,
+                "lt" => condition.lt = true,
+                _ => return Err(CommandError::Other(format!("Unsupported option {opt}"))),
+            }
+        }
+        if condition.nx && (condition.xx || condition.gt || condition.lt) {
+            return Err(CommandError::Other(
+                "NX and XX, GT or LT options at the same time are not compatible".into(),
+            ));
+        }
+        if condition.gt && condition.lt {
+            return Err(CommandError::Other(
+                "GT and LT options at the same time are not compatible".into(),
+            ));
+        }
+
+        Ok(Expire {
+            name,
+            key,
+            millis,
+            absolute,
+            condition,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let expires_at = if self.absolute {
+            time::instant_from_unix_millis(self.millis)
+        } else {
+            time::instant_after_millis(self.millis)
+        };
+        match expires_at {
+            None => Frame::Error(invalid_time(self.name).to_string()),
+            Some(at) => Frame::Integer(db.expire(&self.key, at, self.condition) as i64),
+        }
+    }
+}
+
+fn invalid_time(name: &str) -> CommandError {
+    CommandError::Other(format!("invalid expire time in '{name}' command"))
+}
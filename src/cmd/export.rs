@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, KeySnapshot};
+use crate::export::{self, Format};
+use crate::resp::Frame;
+
+/// `EXPORT path [JSON|CSV]` writes every key to `path` on the server and
+/// replies with the number of keys written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    path: String,
+    format: Format,
+}
+
+impl Export {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Export, CommandError> {
+        let path = parse.next_string()?;
+        let format = match parse.remaining() {
+            0 => Format::Json,
+            _ => parse.next_string()?.parse().map_err(CommandError::Other)?,
+        };
+        parse.finish()?;
+        Ok(Export { path, format })
+    }
+
+    pub(crate) async fn apply(self, db: &Db) -> Frame {
+        // Copy the entries a batch at a time, then write them out without
+        // the lock.
+        let entries = db.snapshot();
+        tokio::task::spawn_blocking(move || self.write(&entries))
+            .await
+            .unwrap()
+    }
+
+    /// Writes the file without leaving the calling thread, as inside a
+    /// transaction.
+    pub(crate) fn apply_now(self, db: &Db) -> Frame {
+        self.write(&db.snapshot())
+    }
+
+    fn write(self, entries: &[KeySnapshot]) -> Frame {
+        let written = File::create(self.path)
+            .and_then(|file| export::write_entries(BufWriter::new(file), entries, self.format));
+        match written {
+            Ok(count) => Frame::Integer(count as i64),
+            Err(err) => Frame::Error(format!("ERR export failed: {err}")),
+        }
+    }
+}
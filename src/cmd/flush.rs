@@ -0,0 +1,49 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Databases, Db};
+use crate::resp::Frame;
+
+/// `FLUSHDB [ASYNC|SYNC]` deletes every key in the selected database, and
+/// `FLUSHALL [ASYNC|SYNC]` every key in every database. With `ASYNC` the
+/// keyspace is emptied at once but its memory is freed in the background,
+/// so a large one does not hold up the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flush {
+    all: bool,
+    lazy: bool,
+}
+
+impl Flush {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Flush, CommandError> {
+        let all = parse.name() == "flushall";
+        let lazy = match parse.remaining() {
+            0 => false,
+            _ => match parse.next_string()?.to_lowercase().as_str() {
+                "async" => true,
+                "sync" => false,
+                _ => return Err(CommandError::Syntax),
+            },
+        };
+        parse.finish()?;
+        Ok(Flush { all, lazy })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        if self.all {
+            "flushall"
+        } else {
+            "flushdb"
+        }
+    }
+
+    pub(crate) fn apply(self, databases: &Databases, db: &Db) -> Frame {
+        let flushed: Vec<_> = if self.all {
+            databases.iter().map(Db::flush).collect()
+        } else {
+            vec![db.flush()]
+        };
+        if self.lazy {
+            tokio::task::spawn_blocking(move || drop(flushed));
+        }
+        Frame::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::db::WrongType;
+use crate::resp::Frame;
+use crate::value::Value;
+
+/// `GET key` replies with the value of `key`, or nil if it does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Get {
+    key: String,
+}
+
+impl Get {
+    pub fn new(key: impl Into<String>) -> Get {
+        Get { key: key.into() }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Get, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(Get { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.get_value(&self.key) {
+            None => Frame::Null,
+            Some(Value::String(value)) => Frame::BulkString(value),
+            Some(_) => Frame::Error(WrongType.to_string()),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, WrongType};
+use crate::range;
+use crate::resp::Frame;
+use crate::value::Value;
+
+/// `GETRANGE key start end` replies with the substring between the
+/// inclusive byte offsets `start` and `end`. Negative offsets count from the
+/// end of the string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetRange {
+    key: String,
+    start: i64,
+    end: i64,
+}
+
+impl GetRange {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<GetRange, CommandError> {
+        let key = parse.next_string()?;
+        let start = parse.next_int()?;
+        let end = parse.next_int()?;
+        parse.finish()?;
+        Ok(GetRange { key, start, end })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let value = match db.get_value(&self.key) {
+            None => Bytes::new(),
+            Some(Value::String(value)) => value,
+            Some(_) => return Frame::Error(WrongType.to_string()),
+        };
+        match range::normalize(self.start, self.end, value.len()) {
+            None => Frame::BulkString(Bytes::new()),
+            Some((from, to)) => Frame::BulkString(value.slice(from..=to)),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `HDEL key field [field ...]` removes fields from the hash at `key` and
+/// replies with how many existed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HDel {
+    key: String,
+    fields: Vec<String>,
+}
+
+impl HDel {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HDel, CommandError> {
+        let key = parse.next_string()?;
+        let mut fields = Vec::new();
+        while parse.remaining() > 0 {
+            fields.push(parse.next_string()?);
+        }
+        Ok(HDel { key, fields })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hdel(&self.key, &self.fields) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
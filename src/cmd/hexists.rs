@@ -0,0 +1,31 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `HEXISTS key field` replies with 1 if the hash at `key` has `field`,
+/// otherwise 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HExists {
+    key: String,
+    field: String,
+}
+
+impl HExists {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HExists, CommandError> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+        parse.finish()?;
+        Ok(HExists { key, field })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hexists(&self.key, &self.field) {
+            Ok(exists) => Frame::Integer(exists as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
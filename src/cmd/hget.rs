@@ -0,0 +1,32 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `HGET key field` replies with the value of `field` in the hash at `key`,
+/// or nil if either does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HGet {
+    key: String,
+    field: String,
+}
+
+impl HGet {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HGet, CommandError> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+        parse.finish()?;
+        Ok(HGet { key, field })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hget(&self.key, &self.field) {
+            Ok(Some(value)) => Frame::BulkString(value),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `HGETALL key` replies with every field of the hash at `key` followed by
+/// its value, the RESP2 form of a map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HGetAll {
+    key: String,
+}
+
+impl HGetAll {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HGetAll, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(HGetAll { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hgetall(&self.key) {
+            Ok(fields) => Frame::Array(
+                fields
+                    .into_iter()
+                    .flat_map(|(field, value)| {
+                        [
+                            Frame::BulkString(Bytes::from(field)),
+                            Frame::BulkString(value),
+                        ]
+                    })
+                    .collect(),
+            ),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, IncrError};
+use crate::resp::Frame;
+
+/// `HINCRBYFLOAT key field increment` adds `increment` to the number in
+/// `field` of the hash at `key`, starting from 0 if either does not exist,
+/// and replies with the result as it is now stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HIncrByFloat {
+    key: String,
+    field: String,
+    increment: f64,
+}
+
+impl HIncrByFloat {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HIncrByFloat, CommandError> {
+        let key = parse.next_string()?;
+        let field = parse.next_string()?;
+        let increment = parse.next_float()?;
+        parse.finish()?;
+        Ok(HIncrByFloat {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hincr_by_float(&self.key, &self.field, self.increment) {
+            Ok(sum) => Frame::BulkString(sum),
+            Err(IncrError::NotFloat) => Frame::Error("ERR hash value is not a float".into()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
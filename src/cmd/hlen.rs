@@ -0,0 +1,28 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `HLEN key` replies with the number of fields in the hash at `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HLen {
+    key: String,
+}
+
+impl HLen {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HLen, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(HLen { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hlen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `HSET key field value [field value ...]` sets fields of the hash at
+/// `key` and replies with how many of them were added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HSet {
+    key: String,
+    fields: Vec<(String, Bytes)>,
+}
+
+impl HSet {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<HSet, CommandError> {
+        let key = parse.next_string()?;
+        if !parse.remaining().is_multiple_of(2) {
+            return Err(CommandError::WrongArity(parse.name().to_string()));
+        }
+        let mut fields = Vec::new();
+        while parse.remaining() > 0 {
+            fields.push((parse.next_string()?, parse.next_bytes()?));
+        }
+        Ok(HSet { key, fields })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The size of the largest field or value stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|(field, value)| field.len().max(value.len()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.hset(&self.key, self.fields) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `INCRBYFLOAT key increment` adds `increment` to the number stored at
+/// `key`, starting from 0 if it does not exist, and replies with the result
+/// as it is now stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncrByFloat {
+    key: String,
+    increment: f64,
+}
+
+impl IncrByFloat {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<IncrByFloat, CommandError> {
+        let key = parse.next_string()?;
+        let increment = parse.next_float()?;
+        parse.finish()?;
+        Ok(IncrByFloat { key, increment })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.incr_by_float(&self.key, self.increment) {
+            Ok(sum) => Frame::BulkString(sum),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `KEYS pattern` replies with every key matching the glob `pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keys {
+    pattern: Bytes,
+}
+
+impl Keys {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Keys, CommandError> {
+        let pattern = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Keys { pattern })
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        Frame::Array(
+            db.keys(&self.pattern)
+                .into_iter()
+                .map(|key| Frame::BulkString(key.into()))
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,26 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `TYPE key` replies with the type of the value at `key`, or `none` if it
+/// does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyType {
+    key: String,
+}
+
+impl KeyType {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<KeyType, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(KeyType { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        Frame::SimpleString(db.type_name(&self.key).unwrap_or("none").into())
+    }
+}
@@ -0,0 +1,58 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::latency::Latencies;
+use crate::resp::Frame;
+
+/// The percentiles `LATENCY PERCENTILES` reports.
+const PERCENTILES: [(&str, f64); 3] = [("p50", 50.0), ("p99", 99.0), ("p99.9", 99.9)];
+
+/// `LATENCY PERCENTILES [command ...]` replies with each command's name
+/// followed by its call count and p50, p99 and p99.9 latencies in
+/// microseconds. `LATENCY RESET [command ...]` clears the samples and
+/// replies with how many commands were reset. Without names, both cover
+/// every command that has been called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Latency {
+    Percentiles(Vec<String>),
+    Reset(Vec<String>),
+}
+
+impl Latency {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Latency, CommandError> {
+        let subcommand = parse.next_string()?.to_lowercase();
+        let mut names = Vec::new();
+        while parse.remaining() > 0 {
+            names.push(parse.next_string()?.to_lowercase());
+        }
+        match subcommand.as_str() {
+            "percentiles" => Ok(Latency::Percentiles(names)),
+            "reset" => Ok(Latency::Reset(names)),
+            _ => Err(CommandError::Other(format!(
+                "unknown LATENCY subcommand '{subcommand}'"
+            ))),
+        }
+    }
+
+    pub(crate) fn apply(self, latencies: &Latencies) -> Frame {
+        match self {
+            Latency::Percentiles(names) => {
+                let mut reply = Vec::new();
+                for (name, histogram) in latencies.histograms(&names) {
+                    let mut stats = vec![
+                        Frame::BulkString(Bytes::from_static(b"calls")),
+                        Frame::Integer(histogram.len() as i64),
+                    ];
+                    for (label, percentile) in PERCENTILES {
+                        stats.push(Frame::BulkString(Bytes::from_static(label.as_bytes())));
+                        stats.push(Frame::Integer(histogram.percentile(percentile) as i64));
+                    }
+                    reply.push(Frame::BulkString(Bytes::from_static(name.as_bytes())));
+                    reply.push(Frame::Array(stats));
+                }
+                Frame::Array(reply)
+            }
+            Latency::Reset(names) => Frame::Integer(latencies.reset(&names) as i64),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `LLEN key` replies with the length of the list at `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LLen {
+    key: String,
+}
+
+impl LLen {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<LLen, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(LLen { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.llen(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
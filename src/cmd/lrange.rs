@@ -0,0 +1,34 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `LRANGE key start stop` replies with the elements of the list at `key`
+/// between the inclusive indexes `start` and `stop`. Negative indexes count
+/// from the end of the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LRange {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+impl LRange {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<LRange, CommandError> {
+        let key = parse.next_string()?;
+        let start = parse.next_int()?;
+        let stop = parse.next_int()?;
+        parse.finish()?;
+        Ok(LRange { key, start, stop })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.lrange(&self.key, self.start, self.stop) {
+            Ok(elements) => Frame::Array(elements.into_iter().map(Frame::BulkString).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `MGET key [key ...]` replies with the value of each key, nil for those
+/// that do not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MGet {
+    keys: Vec<String>,
+}
+
+impl MGet {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<MGet, CommandError> {
+        let mut keys = Vec::new();
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+        }
+        Ok(MGet { keys })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        Frame::Array(
+            db.mget(&self.keys)
+                .into_iter()
+                .map(|value| value.map_or(Frame::Null, Frame::BulkString))
+                .collect(),
+        )
+    }
+}
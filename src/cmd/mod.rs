@@ -0,0 +1,959 @@
+mod parse;
+pub use parse::{CommandError, Parse};
+
+mod spec;
+pub use spec::CommandSpec;
+
+#[cfg(feature = "extensions")]
+mod cas;
+#[cfg(feature = "extensions")]
+pub use cas::Cas;
+
+#[cfg(feature = "extensions")]
+mod waitkey;
+#[cfg(feature = "extensions")]
+pub use waitkey::WaitKey;
+
+mod bpop;
+pub use bpop::BPop;
+
+mod client;
+pub use client::ClientCommand;
+
+mod command;
+pub use command::CommandInfo;
+
+mod config;
+pub use config::ConfigCommand;
+
+mod dbsize;
+pub use dbsize::DbSize;
+
+mod debug;
+pub use debug::Debug;
+
+mod del;
+pub use del::Del;
+
+mod discard;
+pub use discard::Discard;
+
+mod echo;
+pub use echo::Echo;
+
+mod elementscan;
+pub use elementscan::ElementScan;
+
+mod exec;
+pub use exec::Exec;
+
+mod exists;
+pub use exists::Exists;
+
+mod expire;
+pub use expire::Expire;
+
+mod export;
+pub use export::Export;
+
+mod flush;
+pub use flush::Flush;
+
+mod get;
+pub use get::Get;
+
+mod getrange;
+pub use getrange::GetRange;
+
+mod hdel;
+pub use hdel::HDel;
+
+mod hexists;
+pub use hexists::HExists;
+
+mod hget;
+pub use hget::HGet;
+
+mod hgetall;
+pub use hgetall::HGetAll;
+
+mod hincrbyfloat;
+pub use hincrbyfloat::HIncrByFloat;
+
+mod hlen;
+pub use hlen::HLen;
+
+mod hset;
+pub use hset::HSet;
+
+mod incrbyfloat;
+pub use incrbyfloat::IncrByFloat;
+
+mod keys;
+pub use keys::Keys;
+
+mod keytype;
+pub use keytype::KeyType;
+
+mod latency;
+pub use latency::Latency;
+
+mod llen;
+pub use llen::LLen;
+
+mod lrange;
+pub use lrange::LRange;
+
+mod mget;
+pub use mget::MGet;
+
+mod movekey;
+pub use movekey::MoveKey;
+
+mod mset;
+pub use mset::MSet;
+
+mod multi;
+pub use multi::Multi;
+
+mod object;
+pub use object::ObjectCommand;
+
+mod persist;
+pub use persist::Persist;
+
+mod ping;
+pub use ping::Ping;
+
+mod pop;
+pub use pop::Pop;
+
+mod publish;
+pub use publish::Publish;
+
+mod push;
+pub use push::Push;
+
+mod randomkey;
+pub use randomkey::RandomKey;
+
+mod rename;
+pub use rename::Rename;
+
+mod sadd;
+pub use sadd::SAdd;
+
+mod scard;
+pub use scard::SCard;
+
+mod scan;
+pub use scan::Scan;
+
+mod select;
+pub use select::Select;
+
+mod set;
+pub use set::Set;
+
+mod setop;
+pub use setop::SetOp;
+
+mod setopstore;
+pub use setopstore::SetOpStore;
+
+mod setrange;
+pub use setrange::SetRange;
+
+mod sismember;
+pub use sismember::SIsMember;
+
+mod smembers;
+pub use smembers::SMembers;
+
+mod srem;
+pub use srem::SRem;
+
+mod subscribe;
+pub use subscribe::Subscribe;
+
+mod swapdb;
+pub use swapdb::SwapDb;
+
+mod time;
+pub use time::Time;
+
+mod unsubscribe;
+pub use unsubscribe::Unsubscribe;
+
+mod unwatch;
+pub use unwatch::Unwatch;
+
+mod watch;
+pub use watch::Watch;
+
+mod unknown;
+pub use unknown::Unknown;
+
+mod zadd;
+pub use zadd::ZAdd;
+
+mod zrange;
+pub use zrange::ZRange;
+
+mod zrem;
+pub use zrem::ZRem;
+
+mod zscore;
+pub use zscore::ZScore;
+
+use std::io;
+use std::time::Instant;
+
+use crate::aof;
+use crate::client::Client;
+use crate::config::Limits;
+use crate::db::{Databases, Db};
+use crate::pubsub::Kind;
+use crate::resp::Frame;
+use crate::shared::Shared;
+
+// Adding a command means adding it to this table and a case to `execute`,
+// plus one to `apply` if it waits on anything. The connection loop does not
+// change.
+spec::commands! {
+    #[cfg(feature = "extensions")]
+    Cas: "cas", arity -4, flags [write], keys (1, 1, 1), creates [..], stores value_size;
+    #[cfg(feature = "extensions")]
+    WaitKey: "waitkey", arity 3, flags [readonly, blocking], keys (1, 1, 1);
+    BPop: "blpop" | "brpop", arity -3, flags [write, blocking], keys (1, -2, 1);
+    ClientCommand: "client", arity -2, flags [admin, loading, stale], keys (0, 0, 0);
+    CommandInfo: "command", arity -1, flags [loading, stale], keys (0, 0, 0);
+    ConfigCommand: "config", arity -2, flags [admin, loading, stale], keys (0, 0, 0);
+    DbSize: "dbsize", arity 1, flags [readonly, fast], keys (0, 0, 0);
+    Debug: "debug", arity -2, flags [admin], keys (0, 0, 0);
+    Del: "del", arity -2, flags [write], keys (1, -1, 1);
+    Discard: "discard", arity 1, flags [noscript, loading, stale, fast], keys (0, 0, 0);
+    Echo: "echo", arity 2, flags [fast], keys (0, 0, 0);
+    ElementScan: "hscan" | "sscan" | "zscan", arity -3, flags [readonly], keys (1, 1, 1);
+    Exec: "exec", arity 1, flags [noscript, loading, stale, skip_slowlog], keys (0, 0, 0);
+    Exists: "exists", arity -2, flags [readonly, fast], keys (1, -1, 1);
+    Expire: "expire" | "pexpire" | "expireat" | "pexpireat",
+        arity -3, flags [write, fast], keys (1, 1, 1);
+    Export: "export", arity -2, flags [admin], keys (0, 0, 0);
+    Flush: "flushdb" | "flushall", arity -1, flags [write], keys (0, 0, 0);
+    Get: "get", arity 2, flags [readonly, fast], keys (1, 1, 1);
+    GetRange: "getrange", arity 4, flags [readonly], keys (1, 1, 1);
+    HDel: "hdel", arity -3, flags [write, fast], keys (1, 1, 1);
+    HExists: "hexists", arity 3, flags [readonly, fast], keys (1, 1, 1);
+    HGet: "hget", arity 3, flags [readonly, fast], keys (1, 1, 1);
+    HGetAll: "hgetall", arity 2, flags [readonly], keys (1, 1, 1);
+    HIncrByFloat: "hincrbyfloat", arity 4, flags [write, fast], keys (1, 1, 1), creates [..];
+    HLen: "hlen", arity 2, flags [readonly, fast], keys (1, 1, 1);
+    HSet: "hset", arity -4, flags [write, fast], keys (1, 1, 1), creates [..], stores value_size;
+    IncrByFloat: "incrbyfloat", arity 3, flags [write, fast], keys (1, 1, 1), creates [..];
+    Keys: "keys", arity 2, flags [readonly], keys (0, 0, 0);
+    KeyType: "type", arity 2, flags [readonly, fast], keys (1, 1, 1);
+    Latency: "latency", arity -2, flags [admin, loading, stale], keys (0, 0, 0);
+    LLen: "llen", arity 2, flags [readonly, fast], keys (1, 1, 1);
+    LRange: "lrange", arity 4, flags [readonly], keys (1, 1, 1);
+    MGet: "mget", arity -2, flags [readonly, fast], keys (1, -1, 1);
+    MoveKey: "move", arity 3, flags [write, fast], keys (1, 1, 1);
+    MSet: "mset", arity -3, flags [write], keys (1, -1, 2), creates [..], stores value_size;
+    Multi: "multi", arity 1, flags [noscript, loading, stale, fast], keys (0, 0, 0);
+    ObjectCommand: "object", arity -2, flags [readonly], keys (2, 2, 1);
+    Persist: "persist", arity 2, flags [write, fast], keys (1, 1, 1);
+    Ping: "ping", arity -1, flags [fast], keys (0, 0, 0);
+    Pop: "lpop" | "rpop", arity -2, flags [write, fast], keys (1, 1, 1);
+    Publish: "publish", arity 3, flags [pubsub, loading, stale, fast], keys (0, 0, 0);
+    Push: "lpush" | "rpush", arity -3, flags [write, fast], keys (1, 1, 1),
+        creates [..], stores value_size;
+    RandomKey: "randomkey", arity 1, flags [readonly], keys (0, 0, 0);
+    Rename: "rename" | "renamenx", arity 3, flags [write], keys (1, 2, 1), creates [1..];
+    SAdd: "sadd", arity -3, flags [write, fast], keys (1, 1, 1), creates [..], stores value_size;
+    SCard: "scard", arity 2, flags [readonly, fast], keys (1, 1, 1);
+    Scan: "scan", arity -2, flags [readonly], keys (0, 0, 0);
+    Select: "select", arity 2, flags [loading, stale, fast], keys (0, 0, 0);
+    Set: "set", arity -3, flags [write], keys (1, 1, 1), creates [..], stores value_size;
+    SetOp: "sinter" | "sunion" | "sdiff", arity -2, flags [readonly], keys (1, -1, 1);
+    SetOpStore: "sinterstore" | "sunionstore" | "sdiffstore",
+        arity -3, flags [write], keys (1, -1, 1), creates [..1];
+    SetRange: "setrange", arity 4, flags [write], keys (1, 1, 1), creates [..], stores value_size;
+    SIsMember: "sismember", arity 3, flags [readonly, fast], keys (1, 1, 1);
+    SMembers: "smembers", arity 2, flags [readonly], keys (1, 1, 1);
+    SRem: "srem", arity -3, flags [write, fast], keys (1, 1, 1);
+    Subscribe: "subscribe" | "psubscribe", arity -2, flags [pubsub, loading, stale], keys (0, 0, 0);
+    SwapDb: "swapdb", arity 3, flags [write, fast], keys (0, 0, 0);
+    Time: "time", arity 1, flags [fast], keys (0, 0, 0);
+    Unsubscribe: "unsubscribe" | "punsubscribe", arity -1, flags [pubsub, loading, stale], keys (0, 0, 0);
+    Unwatch: "unwatch", arity 1, flags [noscript, loading, stale, fast], keys (0, 0, 0);
+    Watch: "watch", arity -2, flags [noscript, loading, stale, fast], keys (1, -1, 1);
+    ZAdd: "zadd", arity -4, flags [write, fast], keys (1, 1, 1), creates [..], stores value_size;
+    ZRange: "zrange" | "zrangebyscore", arity -4, flags [readonly], keys (1, 1, 1);
+    ZRem: "zrem", arity -3, flags [write, fast], keys (1, 1, 1);
+    ZScore: "zscore", arity 3, flags [readonly, fast], keys (1, 1, 1);
+}
+
+impl Command {
+    /// Executes the command against the client's selected database and
+    /// writes the reply to the client, recording how long that took in the
+    /// server's stats. The `request` the command was parsed from is needed
+    /// when writes are logged to an append-only file.
+    pub async fn apply(
+        self,
+        databases: &Databases,
+        shared: &Shared,
+        client: &mut Client,
+        request: Option<Frame>,
+    ) -> io::Result<()> {
+        let db = &databases[client.db];
+        let started = Instant::now();
+        let stats = &shared.stats;
+        let subscribed = client.subscriptions.count() > 0;
+        if subscribed && !self.allowed_when_subscribed() {
+            let name = self.name();
+            let err = format!(
+                "ERR Can't execute '{name}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context"
+            );
+            return client.conn.write_frame(&Frame::Error(err)).await;
+        }
+        let blocked_on = match &self {
+            Command::BPop(cmd) => cmd.keys().to_vec(),
+            #[cfg(feature = "extensions")]
+            Command::WaitKey(cmd) => cmd.keys().to_vec(),
+            _ => Vec::new(),
+        };
+        stats.clients.update(client.id, |info| {
+            info.last_command = self.name();
+            info.blocked_on = blocked_on;
+        });
+
+        let traced = db.traced_keys(self.keys());
+        let before: Vec<_> = traced.iter().map(|key| db.key_snapshot(key)).collect();
+        let name = self.name();
+
+        let response = match self {
+            Command::Multi(_) | Command::Exec(_) | Command::Discard(_) | Command::Watch(_) => {
+                self.execute(databases, shared, client)
+            }
+            cmd if client.transaction.is_some() => cmd.queue(client, request),
+            #[cfg(feature = "extensions")]
+            Command::WaitKey(cmd) => cmd.apply(db).await,
+            Command::BPop(cmd) => {
+                let aof = shared.aof.as_ref().map(|aof| (aof, client.db));
+                let shutdown = client.shutdown.clone();
+                cmd.apply(db, &mut client.conn, shutdown, aof).await
+            }
+            Command::Debug(cmd) => cmd.apply(db, stats, &client.conn).await,
+            Command::Export(cmd) => cmd.apply(db).await,
+            Command::Subscribe(cmd) => {
+                cmd.apply(&mut client.subscriptions, &mut client.conn)
+                    .await?
+            }
+            Command::Unsubscribe(cmd) => {
+                cmd.apply(&mut client.subscriptions, &mut client.conn)
+                    .await?
+            }
+            cmd => match &shared.aof {
+                Some(aof) if cmd.is_write() => aof
+                    .record(|writes| cmd.run(request, databases, shared, client, writes))
+                    .unwrap_or_else(aof_failed),
+                _ => cmd.execute(databases, shared, client),
+            },
+        };
+
+        for (key, before) in traced.iter().zip(before) {
+            debug::trace(name, &client.conn, key, before, db.key_snapshot(key));
+        }
+        let written = client.conn.write_frame(&response).await;
+        stats.latencies.record(name, started.elapsed());
+        stats.clients.update(client.id, |info| {
+            info.last_active = Instant::now();
+            info.db = client.db;
+            info.channels = client.subscriptions.len(Kind::Channel);
+            info.patterns = client.subscriptions.len(Kind::Pattern);
+            info.blocked_on.clear();
+            info.query_buffer = client.conn.query_buffer_len();
+            info.output_buffer = client.conn.output_buffer_len();
+        });
+        written
+    }
+
+    /// Runs the command to completion without waiting on anything, as EXEC
+    /// does for each command it queued: blocking commands reply as if they
+    /// had timed out at once.
+    pub(crate) fn execute(
+        self,
+        databases: &Databases,
+        shared: &Shared,
+        client: &mut Client,
+    ) -> Frame {
+        let db = &databases[client.db];
+        if let Err(err) = self.check_limits(databases, db, shared.limits()) {
+            return Frame::Error(err);
+        }
+        let stats = &shared.stats;
+        let subscribed = client.subscriptions.count() > 0;
+        match self {
+            #[cfg(feature = "extensions")]
+            Command::Cas(cmd) => cmd.apply(db),
+            #[cfg(feature = "extensions")]
+            Command::WaitKey(_) => Frame::Null,
+            Command::BPop(cmd) => cmd.apply_now(db),
+            Command::ClientCommand(cmd) => cmd.apply(&stats.clients, client.id),
+            Command::CommandInfo(cmd) => cmd.apply(),
+            Command::ConfigCommand(cmd) => cmd.apply(shared),
+            Command::DbSize(cmd) => cmd.apply(db),
+            Command::Debug(cmd) => cmd.apply_now(db, stats, &client.conn),
+            Command::Discard(cmd) => cmd.apply(client),
+            Command::Del(cmd) => cmd.apply(db),
+            Command::Echo(cmd) => cmd.apply(),
+            Command::ElementScan(cmd) => cmd.apply(db),
+            Command::Exec(cmd) => cmd.apply(databases, shared, client),
+            Command::Exists(cmd) => cmd.apply(db),
+            Command::Expire(cmd) => cmd.apply(db),
+            Command::Export(cmd) => cmd.apply_now(db),
+            Command::Flush(cmd) => cmd.apply(databases, db),
+            Command::Get(cmd) => cmd.apply(db),
+            Command::GetRange(cmd) => cmd.apply(db),
+            Command::HDel(cmd) => cmd.apply(db),
+            Command::HExists(cmd) => cmd.apply(db),
+            Command::HGet(cmd) => cmd.apply(db),
+            Command::HGetAll(cmd) => cmd.apply(db),
+            Command::HIncrByFloat(cmd) => cmd.apply(db),
+            Command::HLen(cmd) => cmd.apply(db),
+            Command::HSet(cmd) => cmd.apply(db),
+            Command::IncrByFloat(cmd) => cmd.apply(db),
+            Command::Keys(cmd) => cmd.apply(db),
+            Command::KeyType(cmd) => cmd.apply(db),
+            Command::Latency(cmd) => cmd.apply(&stats.latencies),
+            Command::LLen(cmd) => cmd.apply(db),
+            Command::LRange(cmd) => cmd.apply(db),
+            Command::MGet(cmd) => cmd.apply(db),
+            Command::MoveKey(cmd) => cmd.apply(databases, client.db),
+            Command::MSet(cmd) => cmd.apply(db),
+            Command::Multi(cmd) => cmd.apply(client),
+            Command::ObjectCommand(cmd) => cmd.apply(db),
+            Command::Persist(cmd) => cmd.apply(db),
+            Command::Ping(cmd) if subscribed => cmd.apply_subscribed(),
+            Command::Ping(cmd) => cmd.apply(),
+            Command::Pop(cmd) => cmd.apply(db),
+            Command::Publish(cmd) => cmd.apply(&shared.pubsub),
+            Command::Push(cmd) => cmd.apply(db),
+            Command::RandomKey(cmd) => cmd.apply(db),
+            Command::Rename(cmd) => cmd.apply(db),
+            Command::SAdd(cmd) => cmd.apply(db),
+            Command::SCard(cmd) => cmd.apply(db),
+            Command::Scan(cmd) => cmd.apply(db),
+            Command::Select(cmd) => cmd.apply(databases.len(), &mut client.db),
+            Command::Set(cmd) => cmd.apply(db),
+            Command::SetOp(cmd) => cmd.apply(db),
+            Command::SetOpStore(cmd) => cmd.apply(db),
+            Command::SetRange(cmd) => cmd.apply(db),
+            Command::SIsMember(cmd) => cmd.apply(db),
+            Command::SMembers(cmd) => cmd.apply(db),
+            Command::SRem(cmd) => cmd.apply(db),
+            Command::SwapDb(cmd) => cmd.apply(databases),
+            Command::Time(cmd) => cmd.apply(),
+            Command::Unknown(cmd) => cmd.apply(),
+            Command::Subscribe(_) | Command::Unsubscribe(_) => not_in_transaction(),
+            Command::Unwatch(cmd) => cmd.apply(client),
+            Command::Watch(cmd) => cmd.apply(db, client),
+            Command::ZAdd(cmd) => cmd.apply(db),
+            Command::ZRange(cmd) => cmd.apply(db),
+            Command::ZRem(cmd) => cmd.apply(db),
+            Command::ZScore(cmd) => cmd.apply(db),
+        }
+    }
+
+    /// Executes the command like [`Command::execute`] and, if it is a write
+    /// that succeeded, adds the `request` it was parsed from to `writes`
+    /// for the append-only file.
+    pub(crate) fn run(
+        self,
+        request: Option<Frame>,
+        databases: &Databases,
+        shared: &Shared,
+        client: &mut Client,
+        writes: &mut Vec<aof::Write>,
+    ) -> Frame {
+        let Some(request) = request.filter(|_| self.is_write()) else {
+            return self.execute(databases, shared, client);
+        };
+        let keys = self.keys().to_vec();
+        let reply = self.execute(databases, shared, client);
+        if let Frame::Error(_) = reply {
+            return reply;
+        }
+        writes.push((client.db, request));
+        // TTLs are logged as the time they fall due, so that replaying the
+        // file later does not extend them.
+        let db = &databases[client.db];
+        for key in keys {
+            if let Some(at) = db.expires_at(&key) {
+                let at = crate::time::unix_millis_from_instant(at).to_string();
+                let pin = aof::command(&[b"pexpireat", key.as_bytes(), at.as_bytes()]);
+                writes.push((client.db, pin));
+            }
+        }
+        reply
+    }
+
+    /// Whether the command may change the keyspace.
+    fn is_write(&self) -> bool {
+        self.flags().contains(&"write")
+    }
+
+    /// Refuses a write that would go over `limits`, before any of it is
+    /// done.
+    fn check_limits(&self, databases: &Databases, db: &Db, limits: Limits) -> Result<(), String> {
+        let keys = self.new_keys();
+        if let Some(max) = limits.max_key_length {
+            if keys.iter().any(|key| key.len() > max) {
+                return Err(format!(
+                    "ERR key is longer than max-key-length ({max} bytes)"
+                ));
+            }
+        }
+        if let Some(max) = limits.max_value_size {
+            if self.value_size() > max {
+                return Err(format!(
+                    "ERR value is larger than max-value-size ({max} bytes)"
+                ));
+            }
+        }
+        if let Some(max) = limits.max_keys {
+            // A renamed key takes the place of the one it came from.
+            if !keys.is_empty() && !matches!(self, Command::Rename(_)) {
+                let mut added: Vec<_> = keys.iter().filter(|key| !db.exists(key)).collect();
+                added.sort();
+                added.dedup();
+                let stored: usize = databases.iter().map(Db::len).sum();
+                if !added.is_empty() && stored + added.len() > max {
+                    return Err(format!(
+                        "ERR max-keys reached, at most {max} keys may be stored"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues the command in the client's open transaction. One that is
+    /// rejected instead makes EXEC fail.
+    fn queue(self, client: &mut Client, request: Option<Frame>) -> Frame {
+        let reply = match self {
+            Command::Unknown(cmd) => cmd.apply(),
+            Command::Subscribe(_) | Command::Unsubscribe(_) => not_in_transaction(),
+            cmd => {
+                if let Some(transaction) = &mut client.transaction {
+                    transaction.queued.push((cmd, request));
+                }
+                return Frame::SimpleString("QUEUED".into());
+            }
+        };
+        client.abort_transaction();
+        reply
+    }
+
+    /// Whether the command may run while the client is subscribed to
+    /// channels. Unknown commands still get their own error.
+    fn allowed_when_subscribed(&self) -> bool {
+        matches!(
+            self,
+            Command::Ping(_)
+                | Command::Subscribe(_)
+                | Command::Unknown(_)
+                | Command::Unsubscribe(_)
+        )
+    }
+}
+
+fn not_in_transaction() -> Frame {
+    Frame::Error("ERR Command not allowed inside a transaction".into())
+}
+
+/// The reply to a write refused because the append-only file could not be
+/// written.
+pub(crate) fn aof_failed(err: io::Error) -> Frame {
+    Frame::Error(format!("MISCONF Errors writing to the AOF file: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::time::Duration;
+
+    fn parse(args: &[&str]) -> Result<Command, CommandError> {
+        Command::from_frame(Frame::Array(
+            args.iter()
+                .map(|a| Frame::BulkString(Bytes::copy_from_slice(a.as_bytes())))
+                .collect(),
+        ))
+    }
+
+    fn wrong_arity(name: &str) -> Result<Command, CommandError> {
+        Err(CommandError::WrongArity(name.into()))
+    }
+
+    fn parse_raw(buf: &[u8]) -> Command {
+        Command::from_frame(Frame::parse(buf).unwrap().0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_ping_request() {
+        assert_eq!(
+            parse_raw(b"*1\r\n$4\r\nping\r\n"),
+            Command::Ping(Ping::new(None))
+        );
+    }
+
+    #[test]
+    fn test_parse_echo_request() {
+        assert_eq!(
+            parse_raw(b"*2\r\n$4\r\necho\r\n$11\r\nhello world\r\n"),
+            Command::Echo(Echo::new("hello world"))
+        );
+    }
+
+    #[test]
+    fn test_parse_get_request() {
+        assert_eq!(
+            parse_raw(b"*2\r\n$3\r\nget\r\n$3\r\nkey\r\n"),
+            Command::Get(Get::new("key"))
+        );
+    }
+
+    #[test]
+    fn test_command_names_are_case_insensitive() {
+        assert_eq!(parse(&["PING"]).unwrap().name(), "ping");
+        assert_eq!(parse(&["GeT", "Foo"]).unwrap().name(), "get");
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        assert_eq!(
+            parse(&["foo", "bar"]).unwrap(),
+            Command::Unknown(Unknown::new("foo"))
+        );
+    }
+
+    #[test]
+    fn test_arity_is_validated() {
+        assert_eq!(parse(&["get"]), wrong_arity("get"));
+        assert_eq!(parse(&["get", "a", "b"]), wrong_arity("get"));
+        assert_eq!(parse(&["echo"]), wrong_arity("echo"));
+        assert_eq!(parse(&["ping", "a", "b"]), wrong_arity("ping"));
+        assert_eq!(parse(&["set", "a"]), wrong_arity("set"));
+        assert_eq!(parse(&["getrange", "a", "0"]), wrong_arity("getrange"));
+        assert_eq!(parse(&["export"]), wrong_arity("export"));
+        assert_eq!(parse(&["debug"]), wrong_arity("debug"));
+        assert_eq!(parse(&["del"]), wrong_arity("del"));
+        assert_eq!(parse(&["exists"]), wrong_arity("exists"));
+        assert_eq!(parse(&["pexpire", "a"]), wrong_arity("pexpire"));
+        assert_eq!(parse(&["time", "now"]), wrong_arity("time"));
+        assert_eq!(parse(&["persist"]), wrong_arity("persist"));
+        assert_eq!(parse(&["mget"]), wrong_arity("mget"));
+        assert_eq!(parse(&["mset", "a"]), wrong_arity("mset"));
+        assert_eq!(parse(&["mset", "a", "1", "b"]), wrong_arity("mset"));
+        assert_eq!(parse(&["hset", "h", "f"]), wrong_arity("hset"));
+        assert_eq!(parse(&["hset", "h", "f", "1", "g"]), wrong_arity("hset"));
+        assert_eq!(parse(&["hget", "h"]), wrong_arity("hget"));
+        assert_eq!(parse(&["hdel", "h"]), wrong_arity("hdel"));
+        assert_eq!(parse(&["hlen", "h", "f"]), wrong_arity("hlen"));
+        assert_eq!(parse(&["sadd", "s"]), wrong_arity("sadd"));
+        assert_eq!(parse(&["lpush", "l"]), wrong_arity("lpush"));
+        assert_eq!(parse(&["rpop", "l", "1", "2"]), wrong_arity("rpop"));
+        assert_eq!(parse(&["blpop", "l"]), wrong_arity("blpop"));
+        assert_eq!(parse(&["sinter"]), wrong_arity("sinter"));
+        assert_eq!(parse(&["sdiffstore", "d"]), wrong_arity("sdiffstore"));
+    }
+
+    #[test]
+    fn test_parse_del_request() {
+        assert_eq!(
+            parse(&["del", "a", "b"]).unwrap(),
+            Command::Del(Del::new(vec!["a".into(), "b".into()]))
+        );
+    }
+
+    #[test]
+    fn test_expire_options() {
+        assert_eq!(
+            parse(&["expireat", "a", "1", "xx", "GT"]).unwrap().name(),
+            "expireat"
+        );
+        assert_eq!(
+            parse(&["expire", "a", "1.5"]),
+            Err(CommandError::NotInteger)
+        );
+        assert!(parse(&["expire", "a", "10", "nx", "xx"]).is_err());
+        assert!(parse(&["expire", "a", "10", "gt", "lt"]).is_err());
+        assert!(parse(&["expire", "a", "10", "later"]).is_err());
+        assert!(parse(&["expire", "a", &i64::MAX.to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_command_keys() {
+        assert_eq!(parse(&["set", "a", "b"]).unwrap().keys(), ["a"]);
+        assert_eq!(parse(&["del", "a", "b"]).unwrap().keys(), ["a", "b"]);
+        assert_eq!(
+            parse(&["mset", "a", "1", "b", "2"]).unwrap().keys(),
+            ["a", "b"]
+        );
+        assert_eq!(
+            parse(&["sunionstore", "d", "a", "b"]).unwrap().keys(),
+            ["d", "a", "b"]
+        );
+        assert_eq!(parse(&["SDIFF", "a"]).unwrap().name(), "sdiff");
+        assert_eq!(parse(&["type", "a"]).unwrap().name(), "type");
+        assert_eq!(parse(&["RENAMENX", "a", "b"]).unwrap().keys(), ["a", "b"]);
+        assert_eq!(parse(&["RENAMENX", "a", "b"]).unwrap().name(), "renamenx");
+        assert_eq!(parse(&["object", "encoding", "a"]).unwrap().keys(), ["a"]);
+        assert!(parse(&["object", "freq", "a"]).is_err());
+        assert!(parse(&["echo", "a"]).unwrap().keys().is_empty());
+    }
+
+    #[test]
+    fn test_what_limits_apply_to() {
+        let sunionstore = parse(&["sunionstore", "d", "a", "b"]).unwrap();
+        assert_eq!(sunionstore.new_keys(), ["d"]);
+        assert_eq!(parse(&["rename", "a", "b"]).unwrap().new_keys(), ["b"]);
+        assert!(parse(&["del", "a"]).unwrap().new_keys().is_empty());
+
+        assert_eq!(parse(&["hset", "h", "field", "v"]).unwrap().value_size(), 5);
+        assert_eq!(
+            parse(&["setrange", "s", "10", "ab"]).unwrap().value_size(),
+            12
+        );
+        assert_eq!(parse(&["setrange", "s", "10", ""]).unwrap().value_size(), 0);
+        assert_eq!(parse(&["get", "s"]).unwrap().value_size(), 0);
+    }
+
+    #[test]
+    fn test_command_table() {
+        let table = Command::table();
+
+        for (i, spec) in table.iter().enumerate() {
+            assert!(
+                table[i + 1..].iter().all(|other| other.name != spec.name),
+                "{} is listed twice",
+                spec.name
+            );
+            assert_ne!(spec.arity, 0, "{} has no arity", spec.name);
+        }
+        assert!(table.iter().any(|spec| spec.name == "pexpireat"));
+    }
+
+    #[test]
+    fn test_parse_command_info() {
+        assert_eq!(
+            parse(&["command"]).unwrap(),
+            Command::CommandInfo(CommandInfo::All)
+        );
+        assert_eq!(
+            parse(&["command", "info", "GET", "nope"]).unwrap(),
+            Command::CommandInfo(CommandInfo::Info(vec!["get".into(), "nope".into()]))
+        );
+        assert!(parse(&["command", "count", "x"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_latency() {
+        assert_eq!(
+            parse(&["latency", "percentiles", "GET"]).unwrap(),
+            Command::Latency(Latency::Percentiles(vec!["get".into()]))
+        );
+        assert_eq!(
+            parse(&["latency", "reset"]).unwrap(),
+            Command::Latency(Latency::Reset(vec![]))
+        );
+        assert!(parse(&["latency", "doctor"]).is_err());
+    }
+
+    #[test]
+    fn test_zadd_options() {
+        assert!(parse(&["zadd", "z", "1", "a", "2.5", "b"]).is_ok());
+        assert!(parse(&["zadd", "z", "XX", "gt", "ch", "-inf", "a"]).is_ok());
+        assert_eq!(
+            parse(&["zadd", "z", "1", "a", "2"]),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(
+            parse(&["zadd", "z", "one", "a"]),
+            Err(CommandError::NotFloat)
+        );
+        assert_eq!(
+            parse(&["zadd", "z", "nan", "a"]),
+            Err(CommandError::NotFloat)
+        );
+        assert!(parse(&["zadd", "z", "nx", "xx", "1", "a"]).is_err());
+        assert!(parse(&["zadd", "z", "nx", "gt", "1", "a"]).is_err());
+    }
+
+    #[test]
+    fn test_zrange_options() {
+        assert!(parse(&["zrange", "z", "0", "-1", "withscores"]).is_ok());
+        assert!(parse(&["zrange", "z", "(1", "+inf", "byscore", "limit", "0", "10"]).is_ok());
+        assert!(parse(&["zrangebyscore", "z", "-inf", "(2", "LIMIT", "1", "-1"]).is_ok());
+        assert!(parse(&["zrange", "z", "0", "-1", "limit", "0", "10"]).is_err());
+        assert!(parse(&["zrange", "z", "(0", "-1"]).is_err());
+        assert!(parse(&["zrangebyscore", "z", "a", "1"]).is_err());
+        assert!(parse(&["zrangebyscore", "z", "0", "1", "byscore"]).is_err());
+        assert_eq!(
+            parse(&["ZRANGEBYSCORE", "z", "0", "1"]).unwrap().name(),
+            "zrangebyscore"
+        );
+    }
+
+    #[test]
+    fn test_scan_options() {
+        assert!(parse(&["scan", "0", "match", "user:*", "COUNT", "100"]).is_ok());
+        assert!(parse(&["scan", "18446744073709551615", "type", "hash"]).is_ok());
+        assert!(parse(&["hscan", "h", "0", "count", "5", "match", "f*"]).is_ok());
+        assert_eq!(
+            parse(&["scan", "-1"]),
+            Err(CommandError::Other("invalid cursor".into()))
+        );
+        assert_eq!(
+            parse(&["scan", "0", "count", "0"]),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(
+            parse(&["sscan", "s", "0", "type", "set"]),
+            Err(CommandError::Syntax)
+        );
+        assert!(parse(&["scan", "0", "match"]).is_err());
+        assert_eq!(parse(&["ZSCAN", "z", "0"]).unwrap().keys(), ["z"]);
+        assert_eq!(parse(&["ZSCAN", "z", "0"]).unwrap().name(), "zscan");
+    }
+
+    #[test]
+    fn test_blocking_pop_timeout() {
+        assert_eq!(
+            parse(&["brpop", "a", "b", "0.5"]).unwrap().keys(),
+            ["a", "b"]
+        );
+        assert!(parse(&["blpop", "a", "0"]).is_ok());
+        assert!(parse(&["blpop", "a", "-1"]).is_err());
+        assert!(parse(&["blpop", "a", "soon"]).is_err());
+        assert!(parse(&["blpop", "a", "inf"]).is_err());
+    }
+
+    #[test]
+    fn test_set_options() {
+        assert!(parse(&["set", "a", "b", "PX", "100"]).is_ok());
+        assert!(parse(&["set", "a", "b", "get", "EX", "10", "nx"]).is_ok());
+        assert!(parse(&["set", "a", "b", "xx", "keepttl"]).is_ok());
+        assert!(parse(&["set", "a", "b", "pxat", "1700000000000"]).is_ok());
+        assert!(parse(&["set", "a", "b", "nx", "NX"]).is_ok());
+        assert!(parse(&["set", "a", "b", "xx", "xx"]).is_ok());
+        assert_eq!(
+            parse(&["set", "a", "b", "nx", "xx"]),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(
+            parse(&["set", "a", "b", "xx", "nx"]),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(
+            parse(&["set", "a", "b", "ex", "1", "keepttl"]),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(
+            parse(&["set", "a", "b", "get", "get"]),
+            Err(CommandError::Syntax)
+        );
+        assert!(parse(&["set", "a", "b", "ex", "0"]).is_err());
+        assert!(parse(&["set", "a", "b", "ex", &i64::MAX.to_string()]).is_err());
+        assert_eq!(
+            parse(&["set", "a", "b", "px", "soon"]),
+            Err(CommandError::NotInteger)
+        );
+        assert_eq!(
+            parse(&["set", "a", "b", "ttl", "100"]),
+            Err(CommandError::Syntax)
+        );
+        assert_eq!(parse(&["set", "a", "b", "px"]), Err(CommandError::Syntax));
+    }
+
+    #[test]
+    fn test_database_indexes() {
+        assert!(parse(&["select", "-1"]).is_ok());
+        assert_eq!(parse(&["select", "one"]), Err(CommandError::NotInteger));
+        assert_eq!(parse(&["swapdb", "0", "x"]), Err(CommandError::NotInteger));
+        assert_eq!(
+            parse(&["move", "k", "1"]).unwrap().keys(),
+            ["k".to_string()]
+        );
+        assert_eq!(select::db_index(15, 16), Some(15));
+        assert_eq!(select::db_index(16, 16), None);
+        assert_eq!(select::db_index(-1, 16), None);
+    }
+
+    #[test]
+    fn test_flush_options() {
+        assert_eq!(parse(&["flushall"]).unwrap().name(), "flushall");
+        assert!(parse(&["flushdb", "ASYNC"]).is_ok());
+        assert!(parse(&["flushdb", "sync"]).is_ok());
+        assert_eq!(parse(&["flushdb", "now"]), Err(CommandError::Syntax));
+        assert_eq!(
+            parse(&["flushall", "async", "sync"]),
+            wrong_arity("flushall")
+        );
+    }
+
+    #[test]
+    fn test_setrange_offsets() {
+        assert!(parse(&["setrange", "a", "0", "x"]).is_ok());
+        assert!(parse(&["setrange", "a", "-1", "x"]).is_err());
+        assert!(parse(&["setrange", "a", "536870912", "x"]).is_err());
+        assert_eq!(parse(&["setrange", "a", "0"]), wrong_arity("setrange"));
+    }
+
+    #[test]
+    fn test_getrange_requires_integers() {
+        assert_eq!(
+            parse(&["getrange", "a", "x", "1"]),
+            Err(CommandError::NotInteger)
+        );
+    }
+
+    #[test]
+    fn test_debug_subcommands() {
+        assert_eq!(
+            parse(&["debug", "populate", "10"]).unwrap(),
+            Command::Debug(Debug::Populate {
+                count: 10,
+                prefix: "key".into(),
+                size: None,
+            })
+        );
+        assert_eq!(
+            parse(&["debug", "sleep", "0.5"]).unwrap(),
+            Command::Debug(Debug::Sleep(Duration::from_millis(500)))
+        );
+        assert!(parse(&["debug", "sleep", "-1"]).is_err());
+        assert!(parse(&["debug", "quickack", "2"]).is_err());
+        assert_eq!(
+            parse(&["debug", "tracekey", "Foo", "ON"]).unwrap(),
+            Command::Debug(Debug::TraceKey {
+                key: "Foo".into(),
+                duration: Some(Duration::from_secs(60)),
+            })
+        );
+        assert_eq!(
+            parse(&["debug", "tracekey", "Foo", "off"]).unwrap(),
+            Command::Debug(Debug::TraceKey {
+                key: "Foo".into(),
+                duration: None,
+            })
+        );
+        assert!(parse(&["debug", "tracekey", "Foo", "off", "10"]).is_err());
+        assert_eq!(
+            parse(&["debug", "BADCLIENTS"]).unwrap(),
+            Command::Debug(Debug::BadClients)
+        );
+        assert!(parse(&["debug", "reload"]).is_err());
+    }
+}
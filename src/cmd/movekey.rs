@@ -0,0 +1,37 @@
+use crate::cmd::select::{db_index, out_of_range};
+use crate::cmd::{CommandError, Parse};
+use crate::db::Databases;
+use crate::resp::Frame;
+
+/// `MOVE key db` moves `key` from the selected database to database `db`,
+/// replying with whether it did: only if `key` exists and `db` has no key
+/// by that name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveKey {
+    key: String,
+    db: i64,
+}
+
+impl MoveKey {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<MoveKey, CommandError> {
+        let key = parse.next_string()?;
+        let db = parse.next_int()?;
+        parse.finish()?;
+        Ok(MoveKey { key, db })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, databases: &Databases, selected: usize) -> Frame {
+        let Some(db) = db_index(self.db, databases.len()) else {
+            return out_of_range();
+        };
+        if db == selected {
+            return Frame::Error("ERR source and destination objects are the same".into());
+        }
+        let moved = databases.move_key(&self.key, selected, db);
+        Frame::Integer(moved.into())
+    }
+}
@@ -0,0 +1,42 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `MSET key value [key value ...]` stores every pair at once, clearing
+/// any TTLs. No client sees some of the keys updated and others not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MSet {
+    keys: Vec<String>,
+    values: Vec<Bytes>,
+}
+
+impl MSet {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<MSet, CommandError> {
+        if !parse.remaining().is_multiple_of(2) {
+            return Err(CommandError::WrongArity(parse.name().to_string()));
+        }
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+            values.push(parse.next_bytes()?);
+        }
+        Ok(MSet { keys, values })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// The size of the largest element stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.values.iter().map(Bytes::len).max().unwrap_or(0)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        db.mset(self.keys.into_iter().zip(self.values).collect());
+        Frame::SimpleString("OK".into())
+    }
+}
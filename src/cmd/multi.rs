@@ -0,0 +1,23 @@
+use crate::client::{Client, Transaction};
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+
+/// `MULTI` starts a transaction: the client's commands are queued, each
+/// answered with `QUEUED`, until EXEC runs them or DISCARD drops them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multi;
+
+impl Multi {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Multi, CommandError> {
+        parse.finish()?;
+        Ok(Multi)
+    }
+
+    pub(crate) fn apply(self, client: &mut Client) -> Frame {
+        if client.transaction.is_some() {
+            return Frame::Error("ERR MULTI calls can not be nested".into());
+        }
+        client.transaction = Some(Transaction::default());
+        Frame::SimpleString("OK".into())
+    }
+}
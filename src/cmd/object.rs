@@ -0,0 +1,43 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `OBJECT ENCODING key` replies with the name of the representation used
+/// for the value at `key`, or nil if it does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectCommand {
+    Encoding(String),
+}
+
+impl ObjectCommand {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ObjectCommand, CommandError> {
+        let subcommand = parse.next_string()?.to_lowercase();
+        let cmd = match subcommand.as_str() {
+            "encoding" => ObjectCommand::Encoding(parse.next_string()?),
+            _ => {
+                return Err(CommandError::Other(format!(
+                    "unknown OBJECT subcommand '{subcommand}'"
+                )))
+            }
+        };
+        parse.finish()?;
+        Ok(cmd)
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        match self {
+            ObjectCommand::Encoding(key) => std::slice::from_ref(key),
+        }
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match self {
+            ObjectCommand::Encoding(key) => match db.encoding(&key) {
+                Some(encoding) => Frame::BulkString(Bytes::from_static(encoding.as_bytes())),
+                None => Frame::Null,
+            },
+        }
+    }
+}
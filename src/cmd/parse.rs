@@ -0,0 +1,220 @@
+use bytes::Bytes;
+use std::fmt;
+use std::str::{self, FromStr};
+use std::vec;
+
+use crate::float;
+use crate::resp::Frame;
+
+/// Why a request could not be turned into a command. The `Display` output
+/// is the error reply sent to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    /// Too few or too many arguments for the named command.
+    WrongArity(String),
+    Syntax,
+    NotInteger,
+    NotFloat,
+    Protocol(String),
+    Other(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::WrongArity(name) => {
+                write!(f, "ERR wrong number of arguments for '{name}' command")
+            }
+            CommandError::Syntax => f.write_str("ERR syntax error"),
+            CommandError::NotInteger => f.write_str("ERR value is not an integer or out of range"),
+            CommandError::NotFloat => f.write_str("ERR value is not a valid float"),
+            CommandError::Protocol(msg) => write!(f, "ERR Protocol error: {msg}"),
+            CommandError::Other(msg) => write!(f, "ERR {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Walks the arguments of a request, which is an array of bulk strings
+/// starting with the command name.
+#[derive(Debug)]
+pub struct Parse {
+    name: String,
+    args: vec::IntoIter<Bytes>,
+}
+
+impl Parse {
+    pub fn new(frame: Frame) -> Result<Parse, CommandError> {
+        let frames = match frame {
+            Frame::Array(frames) => frames,
+            _ => return Err(CommandError::Protocol("expected array".into())),
+        };
+
+        let mut args = Vec::with_capacity(frames.len());
+        for frame in frames {
+            match frame {
+                Frame::BulkString(data) => args.push(data),
+                _ => return Err(CommandError::Protocol("expected bulk string".into())),
+            }
+        }
+
+        let mut args = args.into_iter();
+        let name = args
+            .next()
+            .ok_or_else(|| CommandError::Protocol("empty command".into()))?;
+        let name = String::from_utf8_lossy(&name).to_lowercase();
+        Ok(Parse { name, args })
+    }
+
+    /// The lowercased command name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.args.len()
+    }
+
+    pub fn next_bytes(&mut self) -> Result<Bytes, CommandError> {
+        self.args
+            .next()
+            .ok_or_else(|| CommandError::WrongArity(self.name.clone()))
+    }
+
+    /// The next argument as a string, e.g. a key. Invalid UTF-8 is
+    /// rejected, as replacing it would make different keys the same.
+    pub fn next_string(&mut self) -> Result<String, CommandError> {
+        let arg = self.next_bytes()?;
+        String::from_utf8(arg.into())
+            .map_err(|_| CommandError::Other("argument is not valid UTF-8".into()))
+    }
+
+    pub fn next_int<T: FromStr>(&mut self) -> Result<T, CommandError> {
+        let arg = self.next_bytes()?;
+        str::from_utf8(&arg)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(CommandError::NotInteger)
+    }
+
+    pub fn next_float(&mut self) -> Result<f64, CommandError> {
+        let arg = self.next_bytes()?;
+        float::parse(&arg).ok_or(CommandError::NotFloat)
+    }
+
+    /// Fails with a wrong-arity error unless the request, including the
+    /// command name, has the number of arguments `arity` calls for, in the
+    /// form used by [`CommandSpec`](crate::cmd::CommandSpec).
+    pub fn check_arity(&self, arity: i32) -> Result<(), CommandError> {
+        let args = self.args.len() as i64 + 1;
+        let arity = i64::from(arity);
+        if (arity >= 0 && args == arity) || (arity < 0 && args >= -arity) {
+            Ok(())
+        } else {
+            Err(CommandError::WrongArity(self.name.clone()))
+        }
+    }
+
+    /// Fails with a wrong-arity error if any arguments are left over.
+    pub fn finish(&self) -> Result<(), CommandError> {
+        if self.args.len() == 0 {
+            Ok(())
+        } else {
+            Err(CommandError::WrongArity(self.name.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(args: &[&str]) -> Frame {
+        Frame::Array(
+            args.iter()
+                .map(|a| Frame::BulkString(Bytes::copy_from_slice(a.as_bytes())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let mut parse = Parse::new(request(&["SET", "Foo", "42", "1.5"])).unwrap();
+
+        assert_eq!(parse.name(), "set");
+        assert_eq!(parse.remaining(), 3);
+        assert_eq!(parse.next_string().unwrap(), "Foo");
+        assert_eq!(parse.next_int::<i64>().unwrap(), 42);
+        assert_eq!(parse.next_float().unwrap(), 1.5);
+        assert!(parse.finish().is_ok());
+    }
+
+    #[test]
+    fn test_parse_missing_argument() {
+        let mut parse = Parse::new(request(&["get"])).unwrap();
+
+        assert_eq!(
+            parse.next_string(),
+            Err(CommandError::WrongArity("get".into()))
+        );
+    }
+
+    #[test]
+    fn test_check_arity() {
+        let parse = Parse::new(request(&["get", "a"])).unwrap();
+
+        assert!(parse.check_arity(2).is_ok());
+        assert!(parse.check_arity(-2).is_ok());
+        assert!(parse.check_arity(3).is_err());
+        assert!(parse.check_arity(-3).is_err());
+    }
+
+    #[test]
+    fn test_parse_extra_argument() {
+        let parse = Parse::new(request(&["ping", "a"])).unwrap();
+
+        assert_eq!(parse.finish(), Err(CommandError::WrongArity("ping".into())));
+    }
+
+    #[test]
+    fn test_parse_invalid_numbers() {
+        let mut parse = Parse::new(request(&["x", "abc", "nan"])).unwrap();
+
+        assert_eq!(parse.next_int::<i64>(), Err(CommandError::NotInteger));
+        assert_eq!(parse.next_float(), Err(CommandError::NotFloat));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_utf8() {
+        for key in [&b"\xff"[..], b"\xfe"] {
+            let set = Frame::Array(
+                [&b"set"[..], key, b"v"]
+                    .into_iter()
+                    .map(|arg| Frame::BulkString(Bytes::copy_from_slice(arg)))
+                    .collect(),
+            );
+            let mut parse = Parse::new(set).unwrap();
+            assert_eq!(
+                parse.next_string(),
+                Err(CommandError::Other("argument is not valid UTF-8".into()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_non_bulk_requests() {
+        assert!(Parse::new(Frame::SimpleString("ping".into())).is_err());
+        assert!(Parse::new(Frame::Array(vec![Frame::Integer(1)])).is_err());
+        assert!(Parse::new(Frame::Array(vec![])).is_err());
+    }
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(
+            CommandError::WrongArity("get".into()).to_string(),
+            "ERR wrong number of arguments for 'get' command"
+        );
+        assert_eq!(CommandError::Syntax.to_string(), "ERR syntax error");
+    }
+}
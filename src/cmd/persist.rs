@@ -0,0 +1,26 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `PERSIST key` removes the TTL from `key`, replying with 1 if it had one
+/// and 0 otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Persist {
+    key: String,
+}
+
+impl Persist {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Persist, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(Persist { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        Frame::Integer(db.persist(&self.key) as i64)
+    }
+}
@@ -0,0 +1,41 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+
+/// `PING [message]` replies PONG, or echoes `message` back if given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ping {
+    msg: Option<Bytes>,
+}
+
+impl Ping {
+    pub fn new(msg: Option<Bytes>) -> Ping {
+        Ping { msg }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Ping, CommandError> {
+        let msg = match parse.remaining() {
+            0 => None,
+            _ => Some(parse.next_bytes()?),
+        };
+        parse.finish()?;
+        Ok(Ping { msg })
+    }
+
+    pub(crate) fn apply(self) -> Frame {
+        match self.msg {
+            None => Frame::SimpleString("PONG".into()),
+            Some(msg) => Frame::BulkString(msg),
+        }
+    }
+
+    /// Subscribed clients get a `pong` array instead, so the reply can be
+    /// told apart from published messages.
+    pub(crate) fn apply_subscribed(self) -> Frame {
+        Frame::Array(vec![
+            Frame::BulkString(Bytes::from_static(b"pong")),
+            Frame::BulkString(self.msg.unwrap_or_default()),
+        ])
+    }
+}
@@ -0,0 +1,56 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, ListEnd};
+use crate::resp::Frame;
+
+/// `LPOP key [count]` and `RPOP key [count]` remove and reply with the
+/// first or last element of the list at `key`, or nil if there is none.
+/// With a count they reply with an array of up to that many elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pop {
+    end: ListEnd,
+    key: String,
+    count: Option<usize>,
+}
+
+impl Pop {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Pop, CommandError> {
+        let end = match parse.name() {
+            "lpop" => ListEnd::Left,
+            _ => ListEnd::Right,
+        };
+        let key = parse.next_string()?;
+        let count = match parse.remaining() {
+            0 => None,
+            _ => Some(parse.next_int().map_err(|_| {
+                CommandError::Other("value is out of range, must be positive".into())
+            })?),
+        };
+        parse.finish()?;
+        Ok(Pop { end, key, count })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self.end {
+            ListEnd::Left => "lpop",
+            ListEnd::Right => "rpop",
+        }
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match (
+            db.pop(&self.key, self.end, self.count.unwrap_or(1)),
+            self.count,
+        ) {
+            (Ok(None), _) => Frame::Null,
+            (Ok(Some(mut popped)), None) => Frame::BulkString(popped.remove(0)),
+            (Ok(Some(popped)), Some(_)) => {
+                Frame::Array(popped.into_iter().map(Frame::BulkString).collect())
+            }
+            (Err(err), _) => Frame::Error(err.to_string()),
+        }
+    }
+}
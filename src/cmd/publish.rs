@@ -0,0 +1,26 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::pubsub::PubSub;
+use crate::resp::Frame;
+
+/// `PUBLISH channel message` sends `message` to every client subscribed to
+/// `channel` and replies with how many there were.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Publish {
+    channel: Bytes,
+    message: Bytes,
+}
+
+impl Publish {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Publish, CommandError> {
+        let channel = parse.next_bytes()?;
+        let message = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(Publish { channel, message })
+    }
+
+    pub(crate) fn apply(self, pubsub: &PubSub) -> Frame {
+        Frame::Integer(pubsub.publish(&self.channel, self.message) as i64)
+    }
+}
@@ -0,0 +1,53 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, ListEnd};
+use crate::resp::Frame;
+
+/// `LPUSH key element [element ...]` and `RPUSH key element [element ...]`
+/// push elements onto the head or tail of the list at `key`, one at a time,
+/// and reply with its new length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Push {
+    end: ListEnd,
+    key: String,
+    values: Vec<Bytes>,
+}
+
+impl Push {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Push, CommandError> {
+        let end = match parse.name() {
+            "lpush" => ListEnd::Left,
+            _ => ListEnd::Right,
+        };
+        let key = parse.next_string()?;
+        let mut values = Vec::new();
+        while parse.remaining() > 0 {
+            values.push(parse.next_bytes()?);
+        }
+        Ok(Push { end, key, values })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self.end {
+            ListEnd::Left => "lpush",
+            ListEnd::Right => "rpush",
+        }
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The size of the largest element stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.values.iter().map(Bytes::len).max().unwrap_or(0)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.push(&self.key, self.end, self.values) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
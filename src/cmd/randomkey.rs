@@ -0,0 +1,24 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `RANDOMKEY` replies with a key picked at random, or nil if there are
+/// none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomKey;
+
+impl RandomKey {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<RandomKey, CommandError> {
+        parse.finish()?;
+        Ok(RandomKey)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.random_key() {
+            Some(key) => Frame::BulkString(Bytes::from(key)),
+            None => Frame::Null,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `RENAME key newkey` moves the value and TTL of `key` to `newkey`,
+/// replacing what `newkey` held, and `RENAMENX key newkey` does so only if
+/// `newkey` does not exist, replying with whether it did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    nx: bool,
+    keys: Vec<String>,
+}
+
+impl Rename {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Rename, CommandError> {
+        let nx = parse.name() == "renamenx";
+        let keys = vec![parse.next_string()?, parse.next_string()?];
+        parse.finish()?;
+        Ok(Rename { nx, keys })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        if self.nx {
+            "renamenx"
+        } else {
+            "rename"
+        }
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.rename(&self.keys[0], &self.keys[1], self.nx) {
+            Ok(renamed) if self.nx => Frame::Integer(renamed.into()),
+            Ok(_) => Frame::SimpleString("OK".into()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
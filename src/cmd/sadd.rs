@@ -0,0 +1,40 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `SADD key member [member ...]` adds members to the set at `key` and
+/// replies with how many were not already in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SAdd {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+impl SAdd {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SAdd, CommandError> {
+        let key = parse.next_string()?;
+        let mut members = Vec::new();
+        while parse.remaining() > 0 {
+            members.push(parse.next_bytes()?);
+        }
+        Ok(SAdd { key, members })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The size of the largest element stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.members.iter().map(Bytes::len).max().unwrap_or(0)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.sadd(&self.key, self.members) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
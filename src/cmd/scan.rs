@@ -0,0 +1,101 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// Keys or elements looked at per call when `COUNT` is not given.
+const DEFAULT_COUNT: usize = 10;
+
+/// `SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]` walks the
+/// keyspace a few keys at a time. It replies with the cursor to pass to the
+/// next call, `0` once the walk is done, and the keys found on the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scan {
+    cursor: u64,
+    options: ScanOptions,
+    type_name: Option<String>,
+}
+
+/// The options every SCAN command takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ScanOptions {
+    pub(crate) pattern: Option<Bytes>,
+    /// Roughly how many keys or elements to look at, which bounds how long
+    /// one call takes. Fewer may be returned once `MATCH` has filtered them.
+    pub(crate) count: usize,
+}
+
+impl Scan {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Scan, CommandError> {
+        let cursor = parse_cursor(parse)?;
+        let mut type_name = None;
+        let options = ScanOptions::parse(parse, |option, parse| match option {
+            "type" => {
+                type_name = Some(parse.next_string()?.to_lowercase());
+                Ok(())
+            }
+            _ => Err(CommandError::Syntax),
+        })?;
+        Ok(Scan {
+            cursor,
+            options,
+            type_name,
+        })
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let (cursor, keys) = db.scan(
+            self.cursor,
+            self.options.count,
+            self.options.pattern.as_deref(),
+            self.type_name.as_deref(),
+        );
+        reply(
+            cursor,
+            keys.into_iter()
+                .map(|key| Frame::BulkString(key.into()))
+                .collect(),
+        )
+    }
+}
+
+impl ScanOptions {
+    /// Reads `MATCH` and `COUNT` up to the end of the request, passing any
+    /// other option, lowercased, to `other`.
+    pub(crate) fn parse(
+        parse: &mut Parse,
+        mut other: impl FnMut(&str, &mut Parse) -> Result<(), CommandError>,
+    ) -> Result<ScanOptions, CommandError> {
+        let mut options = ScanOptions {
+            pattern: None,
+            count: DEFAULT_COUNT,
+        };
+        while parse.remaining() > 0 {
+            match parse.next_string()?.to_lowercase().as_str() {
+                "match" => options.pattern = Some(parse.next_bytes()?),
+                "count" => match parse.next_int::<i64>()? {
+                    count if count < 1 => return Err(CommandError::Syntax),
+                    count => options.count = usize::try_from(count).unwrap_or(usize::MAX),
+                },
+                option => other(option, parse)?,
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub(crate) fn parse_cursor(parse: &mut Parse) -> Result<u64, CommandError> {
+    parse
+        .next_int()
+        .map_err(|_| CommandError::Other("invalid cursor".into()))
+}
+
+/// The reply to every SCAN command: the next cursor, as a bulk string, and
+/// what was found.
+pub(crate) fn reply(cursor: u64, found: Vec<Frame>) -> Frame {
+    Frame::Array(vec![
+        Frame::BulkString(Bytes::from(cursor.to_string())),
+        Frame::Array(found),
+    ])
+}
@@ -0,0 +1,28 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `SCARD key` replies with the number of members in the set at `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SCard {
+    key: String,
+}
+
+impl SCard {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SCard, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(SCard { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.scard(&self.key) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
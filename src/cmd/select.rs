@@ -0,0 +1,38 @@
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+
+/// `SELECT index` makes the client's commands run against database
+/// `index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Select {
+    index: i64,
+}
+
+impl Select {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Select, CommandError> {
+        let index = parse.next_int()?;
+        parse.finish()?;
+        Ok(Select { index })
+    }
+
+    pub(crate) fn apply(self, databases: usize, selected: &mut usize) -> Frame {
+        match db_index(self.index, databases) {
+            Some(index) => {
+                *selected = index;
+                Frame::SimpleString("OK".into())
+            }
+            None => out_of_range(),
+        }
+    }
+}
+
+/// `index` as a database index, if there is such a database.
+pub(crate) fn db_index(index: i64, databases: usize) -> Option<usize> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&index| index < databases)
+}
+
+pub(crate) fn out_of_range() -> Frame {
+    Frame::Error("ERR DB index is out of range".into())
+}
@@ -0,0 +1,130 @@
+use bytes::Bytes;
+use std::time::Duration;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, SetCondition, SetExpiry};
+use crate::resp::Frame;
+use crate::time;
+
+/// `SET key value [NX|XX] [GET] [EX seconds|PX milliseconds|EXAT
+/// unix-seconds|PXAT unix-milliseconds|KEEPTTL]` stores `value` under
+/// `key`. Without an expiry option any previous TTL is cleared.
+///
+/// Replies OK, or nil if NX or XX prevented the write. With GET it replies
+/// with the previous value instead, whether or not the write happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Set {
+    key: String,
+    value: Bytes,
+    condition: SetCondition,
+    ttl: Ttl,
+    get: bool,
+}
+
+/// The expiry option of a SET, resolved to an instant when it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ttl {
+    Clear,
+    Keep,
+    /// Milliseconds from now.
+    After(i64),
+    /// Milliseconds since the UNIX epoch.
+    At(i64),
+}
+
+impl Set {
+    pub fn new(key: impl Into<String>, value: Bytes, expire: Option<Duration>) -> Set {
+        Set {
+            key: key.into(),
+            value,
+            condition: SetCondition::Always,
+            ttl: expire.map_or(Ttl::Clear, |d| {
+                Ttl::After(i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+            }),
+            get: false,
+        }
+    }
+
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Set, CommandError> {
+        let key = parse.next_string()?;
+        let value = parse.next_bytes()?;
+
+        let mut condition = SetCondition::Always;
+        let mut ttl = Ttl::Clear;
+        let mut get = false;
+        while parse.remaining() > 0 {
+            let opt = parse.next_string()?.to_lowercase();
+            match opt.as_str() {
+                // Repeating a condition is allowed, contradicting it is not.
+                "nx" if condition != SetCondition::Exists => condition = SetCondition::NotExists,
+                "xx" if condition != SetCondition::NotExists => condition = SetCondition::Exists,
+                "get" if !get => get = true,
+                "keepttl" if ttl == Ttl::Clear => ttl = Ttl::Keep,
+                "ex" | "px" | "exat" | "pxat" if ttl == Ttl::Clear => {
+                    if parse.remaining() == 0 {
+                        return Err(CommandError::Syntax);
+                    }
+                    let scale = if opt.starts_with('e') { 1000 } else { 1 };
+                    let millis = parse
+                        .next_int::<i64>()?
+                        .checked_mul(scale)
+                        .filter(|&millis| millis > 0)
+                        .ok_or_else(|| {
+                            CommandError::Other("invalid expire time in 'set' command".into())
+                        })?;
+                    ttl = if opt.ends_with("at") {
+                        Ttl::At(millis)
+                    } else {
+                        Ttl::After(millis)
+                    };
+                }
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+
+        Ok(Set {
+            key,
+            value,
+            condition,
+            ttl,
+            get,
+        })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The size of the value stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.value.len()
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let expiry = match self.ttl {
+            Ttl::Clear => Some(SetExpiry::Clear),
+            Ttl::Keep => Some(SetExpiry::Keep),
+            Ttl::After(millis) => time::instant_after_millis(millis).map(SetExpiry::At),
+            Ttl::At(millis) => time::instant_from_unix_millis(millis).map(SetExpiry::At),
+        };
+        let Some(expiry) = expiry else {
+            return Frame::Error("ERR invalid expire time in 'set' command".into());
+        };
+
+        let (written, old) =
+            match db.set_with(self.key, self.value, self.condition, expiry, self.get) {
+                Ok(result) => result,
+                Err(err) => return Frame::Error(err.to_string()),
+            };
+        if self.get {
+            match old.as_ref().and_then(|value| value.as_bytes()) {
+                Some(data) => Frame::BulkString(data.clone()),
+                None => Frame::Null,
+            }
+        } else if written {
+            Frame::SimpleString("OK".into())
+        } else {
+            Frame::Null
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, SetOperation};
+use crate::resp::Frame;
+
+/// `SINTER key [key ...]`, `SUNION key [key ...]` and `SDIFF key [key ...]`
+/// reply with the intersection, union or difference of the given sets.
+/// Missing keys count as empty sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetOp {
+    name: &'static str,
+    op: SetOperation,
+    keys: Vec<String>,
+}
+
+impl SetOp {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SetOp, CommandError> {
+        let (name, op) = match parse.name() {
+            "sinter" => ("sinter", SetOperation::Inter),
+            "sunion" => ("sunion", SetOperation::Union),
+            _ => ("sdiff", SetOperation::Diff),
+        };
+        let mut keys = Vec::new();
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+        }
+        Ok(SetOp { name, op, keys })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.combine_sets(self.op, &self.keys) {
+            Ok(members) => Frame::Array(members.into_iter().map(Frame::BulkString).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, SetOperation};
+use crate::resp::Frame;
+
+/// `SINTERSTORE destination key [key ...]`, and likewise `SUNIONSTORE`
+/// and `SDIFFSTORE`, store the result of SINTER, SUNION or SDIFF at
+/// `destination` and reply with its size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetOpStore {
+    name: &'static str,
+    op: SetOperation,
+    /// The destination followed by the source keys.
+    keys: Vec<String>,
+}
+
+impl SetOpStore {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SetOpStore, CommandError> {
+        let (name, op) = match parse.name() {
+            "sinterstore" => ("sinterstore", SetOperation::Inter),
+            "sunionstore" => ("sunionstore", SetOperation::Union),
+            _ => ("sdiffstore", SetOperation::Diff),
+        };
+        let mut keys = Vec::new();
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+        }
+        Ok(SetOpStore { name, op, keys })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.store_combined_sets(self.op, &self.keys[0], &self.keys[1..]) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
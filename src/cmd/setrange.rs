@@ -0,0 +1,55 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// Largest string SETRANGE may create, as in Redis.
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// `SETRANGE key offset value` overwrites part of the string at `key`
+/// starting at byte `offset`, padding with zero bytes if the string is
+/// shorter than that, and replies with the new length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetRange {
+    key: String,
+    offset: usize,
+    value: Bytes,
+}
+
+impl SetRange {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SetRange, CommandError> {
+        let key = parse.next_string()?;
+        let offset = parse.next_int::<i64>()?;
+        let value = parse.next_bytes()?;
+        parse.finish()?;
+
+        let offset = usize::try_from(offset)
+            .map_err(|_| CommandError::Other("offset is out of range".into()))?;
+        if offset.saturating_add(value.len()) > MAX_STRING_LEN {
+            return Err(CommandError::Other(
+                "string exceeds maximum allowed size (proto-max-bulk-len)".into(),
+            ));
+        }
+        Ok(SetRange { key, offset, value })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The length the string grows to, or 0 if it is left alone.
+    pub(crate) fn value_size(&self) -> usize {
+        match self.value.len() {
+            0 => 0,
+            len => self.offset.saturating_add(len),
+        }
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.set_range(&self.key, self.offset, &self.value) {
+            Ok(len) => Frame::Integer(len as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
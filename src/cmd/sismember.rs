@@ -0,0 +1,33 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `SISMEMBER key member` replies with 1 if `member` is in the set at
+/// `key`, otherwise 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SIsMember {
+    key: String,
+    member: Bytes,
+}
+
+impl SIsMember {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SIsMember, CommandError> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(SIsMember { key, member })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.sismember(&self.key, &self.member) {
+            Ok(found) => Frame::Integer(found as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
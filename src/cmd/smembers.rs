@@ -0,0 +1,28 @@
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `SMEMBERS key` replies with every member of the set at `key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SMembers {
+    key: String,
+}
+
+impl SMembers {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SMembers, CommandError> {
+        let key = parse.next_string()?;
+        parse.finish()?;
+        Ok(SMembers { key })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.smembers(&self.key) {
+            Ok(members) => Frame::Array(members.into_iter().map(Frame::BulkString).collect()),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+/// What `COMMAND` reports about a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// The number of arguments including the command name, or minus the
+    /// minimum number when it takes a variable number.
+    pub arity: i32,
+    pub flags: &'static [&'static str],
+    /// Position of the first key argument, 0 if there are none.
+    pub first_key: i32,
+    /// Position of the last key argument, negative counting from the end.
+    pub last_key: i32,
+    /// Distance between key arguments.
+    pub step: i32,
+}
+
+/// Declares the command table. Each entry names the variant of `Command`
+/// (and the struct it wraps), the names clients use for it, its arity,
+/// flags and key positions. A write that may create keys says which of its
+/// keys with `creates`, a range of them, and one that stores values names
+/// the method giving the size of the largest with `stores`:
+///
+/// ```text
+/// Get: "get", arity 2, flags [readonly, fast], keys (1, 1, 1);
+/// Set: "set", arity -3, flags [write], keys (1, 1, 1), creates [..], stores value_size;
+/// ```
+///
+/// This generates the `Command` enum, `Command::from_frame`, which checks
+/// the arity before handing the arguments to the struct's `parse_frames`,
+/// `Command::name`, `Command::flags`, `Command::table`, and `Command::keys`,
+/// `Command::new_keys` and `Command::value_size`, which check what a write
+/// stores against the limits. A struct whose first key is not 0 must
+/// provide `keys()`, and variants with several names must provide their
+/// own `name()`. Executing a command is still written out by hand in
+/// `Command::apply`, as commands need different parts of the server.
+macro_rules! commands {
+    ($(
+        $(#[$attr:meta])*
+        $variant:ident: $($name:literal)|+,
+            arity $arity:expr,
+            flags [$($flag:ident),*],
+            keys ($first:tt, $last:expr, $step:expr)
+            $(, creates [$($created:tt)*])?
+            $(, stores $size:ident)?;
+    )*) => {
+        /// A client request, parsed and validated but not yet executed.
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Command {
+            $(
+                $(#[$attr])*
+                $variant($variant),
+            )*
+            Unknown(Unknown),
+        }
+
+        impl Command {
+            /// Parses a request frame, which must be an array of bulk
+            /// strings starting with the command name. Argument count and
+            /// syntax errors are reported here, before anything is executed.
+            pub fn from_frame(frame: Frame) -> Result<Command, CommandError> {
+                let mut parse = Parse::new(frame)?;
+
+                let command = match parse.name() {
+                    $(
+                        $(#[$attr])*
+                        $($name)|+ => {
+                            parse.check_arity($arity)?;
+                            Command::$variant($variant::parse_frames(&mut parse)?)
+                        }
+                    )*
+                    name => return Ok(Command::Unknown(Unknown::new(name))),
+                };
+
+                parse.finish()?;
+                Ok(command)
+            }
+
+            /// The command name as clients spell it, lowercased.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(
+                        $(#[$attr])*
+                        Command::$variant(_cmd) => $crate::cmd::spec::command_name!(_cmd, $($name),+),
+                    )*
+                    Command::Unknown(_) => "unknown",
+                }
+            }
+
+            /// The command's flags, as `COMMAND` reports them.
+            pub fn flags(&self) -> &'static [&'static str] {
+                match self {
+                    $(
+                        $(#[$attr])*
+                        Command::$variant(_) => &[$(stringify!($flag)),*],
+                    )*
+                    Command::Unknown(_) => &[],
+                }
+            }
+
+            /// The keys the command reads or writes.
+            pub fn keys(&self) -> &[String] {
+                match self {
+                    $(
+                        $(#[$attr])*
+                        Command::$variant(_cmd) => $crate::cmd::spec::command_keys!(_cmd, $first),
+                    )*
+                    Command::Unknown(_) => &[],
+                }
+            }
+
+            /// The keys the command may create.
+            fn new_keys(&self) -> &[String] {
+                match self {
+                    $(
+                        $(#[$attr])*
+                        Command::$variant(_cmd) => {
+                            $crate::cmd::spec::new_keys!(_cmd $(, $($created)*)?)
+                        }
+                    )*
+                    Command::Unknown(_) => &[],
+                }
+            }
+
+            /// The size of the largest string or element the command stores.
+            fn value_size(&self) -> usize {
+                match self {
+                    $(
+                        $(#[$attr])*
+                        Command::$variant(_cmd) => $crate::cmd::spec::value_size!(_cmd $(, $size)?),
+                    )*
+                    Command::Unknown(_) => 0,
+                }
+            }
+
+            /// Every command this server understands.
+            pub fn table() -> Vec<CommandSpec> {
+                let mut table = Vec::new();
+                $(
+                    $(#[$attr])*
+                    {
+                        let spec = CommandSpec {
+                            name: "",
+                            arity: $arity,
+                            flags: &[$(stringify!($flag)),*],
+                            first_key: $first,
+                            last_key: $last,
+                            step: $step,
+                        };
+                        $(
+                            table.push(CommandSpec { name: $name, ..spec });
+                        )+
+                    }
+                )*
+                table
+            }
+        }
+    };
+}
+
+macro_rules! command_name {
+    ($cmd:ident, $name:literal) => {
+        $name
+    };
+    ($cmd:ident, $($name:literal),+) => {
+        $cmd.name()
+    };
+}
+
+macro_rules! command_keys {
+    ($cmd:ident, 0) => {
+        &[]
+    };
+    ($cmd:ident, $first:tt) => {
+        $cmd.keys()
+    };
+}
+
+macro_rules! new_keys {
+    ($cmd:ident) => {
+        &[]
+    };
+    ($cmd:ident, $($range:tt)*) => {
+        &$cmd.keys()[$($range)*]
+    };
+}
+
+macro_rules! value_size {
+    ($cmd:ident) => {
+        0
+    };
+    ($cmd:ident, $size:ident) => {
+        $cmd.$size()
+    };
+}
+
+pub(crate) use command_keys;
+pub(crate) use command_name;
+pub(crate) use commands;
+pub(crate) use new_keys;
+pub(crate) use value_size;
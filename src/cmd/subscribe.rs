@@ -0,0 +1,77 @@
+use bytes::Bytes;
+use std::io;
+
+use crate::cmd::{CommandError, Parse};
+use crate::connection::Connection;
+use crate::pubsub::{Kind, Subscriptions};
+use crate::resp::Frame;
+
+/// `SUBSCRIBE channel [channel ...]` and `PSUBSCRIBE pattern [pattern ...]`
+/// subscribe the client to each channel or glob pattern, confirming each
+/// with its own `subscribe` or `psubscribe` reply. Published messages are
+/// then pushed to the client as they arrive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscribe {
+    kind: Kind,
+    channels: Vec<Bytes>,
+}
+
+impl Subscribe {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Subscribe, CommandError> {
+        let kind = match parse.name() {
+            "psubscribe" => Kind::Pattern,
+            _ => Kind::Channel,
+        };
+        let mut channels = vec![parse.next_bytes()?];
+        while parse.remaining() > 0 {
+            channels.push(parse.next_bytes()?);
+        }
+        Ok(Subscribe { kind, channels })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self.kind {
+            Kind::Channel => "subscribe",
+            Kind::Pattern => "psubscribe",
+        }
+    }
+
+    pub(crate) async fn apply(
+        self,
+        subscriptions: &mut Subscriptions,
+        conn: &mut Connection,
+    ) -> io::Result<Frame> {
+        let name = self.name();
+        let replies = self
+            .channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriptions.subscribe(self.kind, channel.clone());
+                confirmation(name, Some(channel), count)
+            })
+            .collect();
+        write_all_but_last(replies, conn).await
+    }
+}
+
+/// The reply confirming a change to a client's subscriptions.
+pub(crate) fn confirmation(kind: &'static str, channel: Option<Bytes>, count: usize) -> Frame {
+    Frame::Array(vec![
+        Frame::BulkString(Bytes::from_static(kind.as_bytes())),
+        channel.map_or(Frame::Null, Frame::BulkString),
+        Frame::Integer(count as i64),
+    ])
+}
+
+/// Writes all but the last of several replies to one request, returning the
+/// last to be written like any other reply.
+pub(crate) async fn write_all_but_last(
+    mut replies: Vec<Frame>,
+    conn: &mut Connection,
+) -> io::Result<Frame> {
+    let last = replies.pop().unwrap_or(Frame::Null);
+    for reply in &replies {
+        conn.write_frame(reply).await?;
+    }
+    Ok(last)
+}
@@ -0,0 +1,31 @@
+use crate::cmd::select::{db_index, out_of_range};
+use crate::cmd::{CommandError, Parse};
+use crate::db::Databases;
+use crate::resp::Frame;
+
+/// `SWAPDB index1 index2` swaps the keys of two databases, so clients that
+/// selected one see the other's keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapDb {
+    first: i64,
+    second: i64,
+}
+
+impl SwapDb {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<SwapDb, CommandError> {
+        let first = parse.next_int()?;
+        let second = parse.next_int()?;
+        parse.finish()?;
+        Ok(SwapDb { first, second })
+    }
+
+    pub(crate) fn apply(self, databases: &Databases) -> Frame {
+        let first = db_index(self.first, databases.len());
+        let second = db_index(self.second, databases.len());
+        let (Some(first), Some(second)) = (first, second) else {
+            return out_of_range();
+        };
+        databases.swap(first, second);
+        Frame::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,25 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+use crate::time;
+
+/// `TIME` replies with the server's UNIX time as seconds and the
+/// microseconds elapsed in the current second.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Time;
+
+impl Time {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Time, CommandError> {
+        parse.finish()?;
+        Ok(Time)
+    }
+
+    pub(crate) fn apply(self) -> Frame {
+        let now = time::unix_now();
+        Frame::Array(vec![
+            Frame::BulkString(Bytes::from(now.as_secs().to_string())),
+            Frame::BulkString(Bytes::from(now.subsec_micros().to_string())),
+        ])
+    }
+}
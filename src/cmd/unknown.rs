@@ -0,0 +1,17 @@
+use crate::resp::Frame;
+
+/// A command RedisLite does not implement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unknown {
+    name: String,
+}
+
+impl Unknown {
+    pub(crate) fn new(name: impl Into<String>) -> Unknown {
+        Unknown { name: name.into() }
+    }
+
+    pub(crate) fn apply(self) -> Frame {
+        Frame::Error(format!("ERR unknown command '{}'", self.name))
+    }
+}
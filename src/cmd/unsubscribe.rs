@@ -0,0 +1,64 @@
+use bytes::Bytes;
+use std::io;
+
+use crate::cmd::subscribe::{confirmation, write_all_but_last};
+use crate::cmd::{CommandError, Parse};
+use crate::connection::Connection;
+use crate::pubsub::{Kind, Subscriptions};
+use crate::resp::Frame;
+
+/// `UNSUBSCRIBE [channel ...]` and `PUNSUBSCRIBE [pattern ...]` unsubscribe
+/// the client from each channel or pattern, or from all of them if none are
+/// given, confirming each with its own `unsubscribe` or `punsubscribe`
+/// reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsubscribe {
+    kind: Kind,
+    channels: Vec<Bytes>,
+}
+
+impl Unsubscribe {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Unsubscribe, CommandError> {
+        let kind = match parse.name() {
+            "punsubscribe" => Kind::Pattern,
+            _ => Kind::Channel,
+        };
+        let mut channels = Vec::new();
+        while parse.remaining() > 0 {
+            channels.push(parse.next_bytes()?);
+        }
+        Ok(Unsubscribe { kind, channels })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self.kind {
+            Kind::Channel => "unsubscribe",
+            Kind::Pattern => "punsubscribe",
+        }
+    }
+
+    pub(crate) async fn apply(
+        self,
+        subscriptions: &mut Subscriptions,
+        conn: &mut Connection,
+    ) -> io::Result<Frame> {
+        let name = self.name();
+        let mut channels = self.channels;
+        if channels.is_empty() {
+            channels = subscriptions.names(self.kind);
+        }
+        if channels.is_empty() {
+            // Redis still confirms, with no channel.
+            return Ok(confirmation(name, None, subscriptions.count()));
+        }
+
+        let replies = channels
+            .into_iter()
+            .map(|channel| {
+                let count = subscriptions.unsubscribe(self.kind, &channel);
+                confirmation(name, Some(channel), count)
+            })
+            .collect();
+        write_all_but_last(replies, conn).await
+    }
+}
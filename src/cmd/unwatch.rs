@@ -0,0 +1,19 @@
+use crate::client::Client;
+use crate::cmd::{CommandError, Parse};
+use crate::resp::Frame;
+
+/// `UNWATCH` forgets the keys the client is watching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unwatch;
+
+impl Unwatch {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Unwatch, CommandError> {
+        parse.finish()?;
+        Ok(Unwatch)
+    }
+
+    pub(crate) fn apply(self, client: &mut Client) -> Frame {
+        client.watched.clear();
+        Frame::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,49 @@
+use bytes::Bytes;
+use std::time::Duration;
+
+use crate::cmd::bpop;
+use crate::cmd::{CommandError, Parse};
+use crate::db::{Db, WrongType};
+use crate::resp::Frame;
+use crate::value::Value;
+
+/// `WAITKEY key timeout` waits for `key` to be written, for up to `timeout`
+/// seconds or forever if it is 0, then replies with the key and its new
+/// value, which is nil if it was deleted. Replies with nil on timeout. This
+/// is a RedisLite extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaitKey {
+    key: String,
+    timeout: Option<Duration>,
+}
+
+impl WaitKey {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<WaitKey, CommandError> {
+        let key = parse.next_string()?;
+        let timeout = bpop::parse_timeout(parse)?;
+        Ok(WaitKey { key, timeout })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) async fn apply(self, db: &Db) -> Frame {
+        let watch = db.watch(&self.key);
+        let modified = watch.modified();
+        match self.timeout {
+            None => modified.await,
+            Some(timeout) => {
+                if tokio::time::timeout(timeout, modified).await.is_err() {
+                    return Frame::Null;
+                }
+            }
+        }
+        let value = match db.get_value(&self.key) {
+            None => Frame::Null,
+            Some(Value::String(data)) => Frame::BulkString(data),
+            Some(_) => return Frame::Error(WrongType.to_string()),
+        };
+        Frame::Array(vec![Frame::BulkString(Bytes::from(self.key)), value])
+    }
+}
@@ -0,0 +1,35 @@
+use crate::client::Client;
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `WATCH key [key ...]` makes the client's next EXEC fail, replying with
+/// nil, if any of the keys is written before it runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    keys: Vec<String>,
+}
+
+impl Watch {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<Watch, CommandError> {
+        let mut keys = Vec::new();
+        while parse.remaining() > 0 {
+            keys.push(parse.next_string()?);
+        }
+        Ok(Watch { keys })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub(crate) fn apply(self, db: &Db, client: &mut Client) -> Frame {
+        if client.transaction.is_some() {
+            return Frame::Error("ERR WATCH inside MULTI is not allowed".into());
+        }
+        client
+            .watched
+            .extend(self.keys.iter().map(|key| db.watch(key)));
+        Frame::SimpleString("OK".into())
+    }
+}
@@ -0,0 +1,88 @@
+use bytes::Bytes;
+use std::str;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+use crate::zset::AddOptions;
+
+/// `ZADD key [NX|XX] [GT|LT] [CH] score member [score member ...]` adds
+/// members to the sorted set at `key` or updates their scores. Replies with
+/// the number of members added, or with `CH` added or updated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZAdd {
+    key: String,
+    options: AddOptions,
+    members: Vec<(f64, Bytes)>,
+}
+
+impl ZAdd {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ZAdd, CommandError> {
+        let key = parse.next_string()?;
+
+        let mut options = AddOptions::default();
+        let first_score = loop {
+            let arg = parse.next_bytes()?;
+            match arg.to_ascii_lowercase().as_slice() {
+                b"nx" => options.nx = true,
+                b"xx" => options.xx = true,
+                b"gt" => options.gt = true,
+                b"lt" => options.lt = true,
+                b"ch" => options.ch = true,
+                _ => break arg,
+            }
+        };
+        if options.nx && options.xx {
+            return Err(CommandError::Other(
+                "XX and NX options at the same time are not compatible".into(),
+            ));
+        }
+        if (options.gt && options.lt) || (options.nx && (options.gt || options.lt)) {
+            return Err(CommandError::Other(
+                "GT, LT, and/or NX options at the same time are not compatible".into(),
+            ));
+        }
+        if parse.remaining().is_multiple_of(2) {
+            return Err(CommandError::Syntax);
+        }
+
+        let mut members = vec![(parse_score(&first_score)?, parse.next_bytes()?)];
+        while parse.remaining() > 0 {
+            let score = parse.next_float()?;
+            members.push((score, parse.next_bytes()?));
+        }
+        Ok(ZAdd {
+            key,
+            options,
+            members,
+        })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    /// The size of the largest element stored.
+    pub(crate) fn value_size(&self) -> usize {
+        self.members
+            .iter()
+            .map(|(_, member)| member.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.zadd(&self.key, self.members, self.options) {
+            Ok(counted) => Frame::Integer(counted as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
+
+fn parse_score(arg: &[u8]) -> Result<f64, CommandError> {
+    str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|f| !f.is_nan())
+        .ok_or(CommandError::NotFloat)
+}
@@ -0,0 +1,126 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::float;
+use crate::resp::Frame;
+use crate::zset::ScoreBound;
+
+/// `ZRANGE key start stop [BYSCORE] [LIMIT offset count] [WITHSCORES]` and
+/// `ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]` reply with
+/// members of the sorted set at `key` in score order, either by rank or,
+/// with `BYSCORE`, by score. Score bounds may be `-inf`, `+inf` or prefixed
+/// with `(` to exclude the bound itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRange {
+    name: &'static str,
+    key: String,
+    by: RangeBy,
+    /// Members to skip and, if not negative, the most to return.
+    limit: Option<(i64, i64)>,
+    with_scores: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+}
+
+impl ZRange {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ZRange, CommandError> {
+        let name = match parse.name() {
+            "zrange" => "zrange",
+            _ => "zrangebyscore",
+        };
+        let key = parse.next_string()?;
+        let start = parse.next_bytes()?;
+        let stop = parse.next_bytes()?;
+
+        let mut by_score = name == "zrangebyscore";
+        let mut limit = None;
+        let mut with_scores = false;
+        while parse.remaining() > 0 {
+            match parse.next_string()?.to_lowercase().as_str() {
+                "byscore" if name == "zrange" => by_score = true,
+                "withscores" => with_scores = true,
+                "limit" => limit = Some((parse.next_int()?, parse.next_int()?)),
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+
+        let by = if by_score {
+            RangeBy::Score(score_bound(&start)?, score_bound(&stop)?)
+        } else if limit.is_some() {
+            return Err(CommandError::Other(
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                    .into(),
+            ));
+        } else {
+            RangeBy::Rank(integer(&start)?, integer(&stop)?)
+        };
+        Ok(ZRange {
+            name,
+            key,
+            by,
+            limit,
+            with_scores,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        let members = match self.by {
+            RangeBy::Rank(start, stop) => db.zrange(&self.key, start, stop),
+            RangeBy::Score(min, max) => match self.limit {
+                Some((offset, _)) if offset < 0 => Ok(Vec::new()),
+                Some((offset, count)) => {
+                    let count = usize::try_from(count).ok();
+                    db.zrange_by_score(&self.key, min, max, offset as usize, count)
+                }
+                None => db.zrange_by_score(&self.key, min, max, 0, None),
+            },
+        };
+        match members {
+            Ok(members) => {
+                let mut frames = Vec::new();
+                for (member, score) in members {
+                    frames.push(Frame::BulkString(member));
+                    if self.with_scores {
+                        frames.push(Frame::BulkString(Bytes::from(float::format(score))));
+                    }
+                }
+                Frame::Array(frames)
+            }
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
+
+fn integer(arg: &[u8]) -> Result<i64, CommandError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or(CommandError::NotInteger)
+}
+
+/// Parses `1.5`, `(1.5`, `-inf` or `+inf`.
+fn score_bound(arg: &[u8]) -> Result<ScoreBound, CommandError> {
+    let (exclusive, score) = match arg.strip_prefix(b"(") {
+        Some(score) => (true, score),
+        None => (false, arg),
+    };
+    let score = std::str::from_utf8(score)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|f| !f.is_nan())
+        .ok_or_else(|| CommandError::Other("min or max is not a float".into()))?;
+    Ok(ScoreBound { score, exclusive })
+}
@@ -0,0 +1,35 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::resp::Frame;
+
+/// `ZREM key member [member ...]` removes members from the sorted set at
+/// `key` and replies with how many were in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZRem {
+    key: String,
+    members: Vec<Bytes>,
+}
+
+impl ZRem {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ZRem, CommandError> {
+        let key = parse.next_string()?;
+        let mut members = Vec::new();
+        while parse.remaining() > 0 {
+            members.push(parse.next_bytes()?);
+        }
+        Ok(ZRem { key, members })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.zrem(&self.key, &self.members) {
+            Ok(removed) => Frame::Integer(removed as i64),
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
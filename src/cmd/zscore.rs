@@ -0,0 +1,35 @@
+use bytes::Bytes;
+
+use crate::cmd::{CommandError, Parse};
+use crate::db::Db;
+use crate::float;
+use crate::resp::Frame;
+
+/// `ZSCORE key member` replies with the score of `member` in the sorted set
+/// at `key`, or nil if either does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZScore {
+    key: String,
+    member: Bytes,
+}
+
+impl ZScore {
+    pub(crate) fn parse_frames(parse: &mut Parse) -> Result<ZScore, CommandError> {
+        let key = parse.next_string()?;
+        let member = parse.next_bytes()?;
+        parse.finish()?;
+        Ok(ZScore { key, member })
+    }
+
+    pub(crate) fn keys(&self) -> &[String] {
+        std::slice::from_ref(&self.key)
+    }
+
+    pub(crate) fn apply(self, db: &Db) -> Frame {
+        match db.zscore(&self.key, &self.member) {
+            Ok(Some(score)) => Frame::BulkString(Bytes::from(float::format(score))),
+            Ok(None) => Frame::Null,
+            Err(err) => Frame::Error(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,742 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::aof::Fsync;
+use crate::cdc::Target;
+use crate::db::DEFAULT_DATABASES;
+use crate::hasher::KeyHasher;
+
+const DEFAULT_BIND: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 6379;
+/// How long clients get to finish once shutdown starts, as in Redis.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a client IP stays banned once it reaches the ban threshold.
+pub const DEFAULT_BAN_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Hostnames or IP addresses to listen on. Names are resolved when the
+    /// server starts and again whenever it is asked to rebind.
+    pub bind: Vec<String>,
+    pub port: u16,
+    pub key_hasher: KeyHasher,
+    /// The number of databases SELECT can choose from.
+    pub databases: usize,
+    /// How long to wait for connected clients to finish before closing
+    /// them during shutdown.
+    pub shutdown_timeout: Duration,
+    /// Port for health check probes, on the same addresses as `bind`.
+    pub probe_port: Option<u16>,
+    /// Protocol errors and unknown commands after which a client IP is
+    /// refused connections for `ban_cooldown`. `None` never bans.
+    pub ban_threshold: Option<u64>,
+    pub ban_cooldown: Duration,
+    /// The longest key a write may create, in bytes.
+    pub max_key_length: Option<usize>,
+    /// The largest string, or element of a container, a write may store,
+    /// in bytes.
+    pub max_value_size: Option<usize>,
+    /// How many keys all the databases together may hold.
+    pub max_keys: Option<usize>,
+    /// The append-only file every write is logged to and that is replayed
+    /// at startup, `None` to keep nothing across restarts.
+    pub aof_file: Option<PathBuf>,
+    pub aof_fsync: Fsync,
+    /// Where every write is streamed to, `None` to not record them.
+    pub cdc_sink: Option<Target>,
+    /// The config file the settings were read from, which CONFIG REWRITE
+    /// writes back to.
+    pub file: Option<PathBuf>,
+}
+
+/// Caps on the keyspace, for servers with little memory to spare. `None`
+/// means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub max_key_length: Option<usize>,
+    pub max_value_size: Option<usize>,
+    pub max_keys: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The type of a setting's value, which decides what it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// One or more hostnames or IP addresses.
+    Hosts,
+    Port,
+    /// A port, or empty for none.
+    OptionalPort,
+    /// A positive whole number.
+    Count,
+    /// A positive whole number, or empty for none.
+    OptionalCount,
+    /// A non-negative number of seconds, which may have a fraction.
+    Seconds,
+    KeyHasher,
+    /// A change data capture sink, or empty for none.
+    OptionalSink,
+    /// A file path, or empty for none.
+    OptionalPath,
+    Fsync,
+}
+
+impl Kind {
+    /// What a valid value looks like, for error messages.
+    pub fn expected(self) -> &'static str {
+        match self {
+            Kind::Hosts => "one or more hostnames or IP addresses",
+            Kind::Port => "a port number (0-65535)",
+            Kind::OptionalPort => "a port number (0-65535), or empty for none",
+            Kind::Count => "a positive whole number",
+            Kind::OptionalCount => "a positive whole number, or empty for none",
+            Kind::Seconds => "a number of seconds (e.g. 2.5)",
+            Kind::KeyHasher => "one of siphash or fx",
+            Kind::OptionalSink => "file:PATH or tcp:HOST:PORT, or empty for none",
+            Kind::OptionalPath => "a file path, or empty for none",
+            Kind::Fsync => "one of always, everysec or no",
+        }
+    }
+}
+
+/// One setting, as it is named on the command line (with a leading `--`),
+/// in config files and by CONFIG GET and SET.
+#[derive(Debug)]
+pub struct Param {
+    pub name: &'static str,
+    pub kind: Kind,
+    /// Whether CONFIG SET may change it while the server runs.
+    pub dynamic: bool,
+    get: fn(&Config) -> String,
+    /// Parses and stores a value, returning `None` if it is not valid for
+    /// `kind`.
+    set: fn(&mut Config, &[String]) -> Option<()>,
+}
+
+impl Param {
+    pub fn get(&self, config: &Config) -> String {
+        (self.get)(config)
+    }
+
+    pub fn default_value(&self) -> String {
+        self.get(&Config::default())
+    }
+}
+
+/// Every setting there is.
+pub static PARAMS: &[Param] = &[
+    Param {
+        name: "bind",
+        kind: Kind::Hosts,
+        dynamic: false,
+        get: |c| c.bind.join(" "),
+        set: |c, values| {
+            if values.is_empty() || values.iter().any(|v| v.is_empty()) {
+                return None;
+            }
+            c.bind = values
+                .iter()
+                .map(|h| strip_brackets(h).to_string())
+                .collect();
+            Some(())
+        },
+    },
+    Param {
+        name: "port",
+        kind: Kind::Port,
+        dynamic: false,
+        get: |c| c.port.to_string(),
+        set: |c, values| {
+            c.port = single(values)?.parse().ok()?;
+            Some(())
+        },
+    },
+    Param {
+        name: "probe-port",
+        kind: Kind::OptionalPort,
+        dynamic: false,
+        get: |c| c.probe_port.map_or(String::new(), |p| p.to_string()),
+        set: |c, values| {
+            c.probe_port = optional(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "key-hasher",
+        kind: Kind::KeyHasher,
+        dynamic: false,
+        get: |c| c.key_hasher.name().to_string(),
+        set: |c, values| {
+            c.key_hasher = single(values)?.parse().ok()?;
+            Some(())
+        },
+    },
+    Param {
+        name: "databases",
+        kind: Kind::Count,
+        dynamic: false,
+        get: |c| c.databases.to_string(),
+        set: |c, values| {
+            c.databases = single(values)?.parse().ok().filter(|&n| n > 0)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "shutdown-timeout",
+        kind: Kind::Seconds,
+        dynamic: true,
+        get: |c| c.shutdown_timeout.as_secs_f64().to_string(),
+        set: |c, values| {
+            c.shutdown_timeout = seconds(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "ban-threshold",
+        kind: Kind::OptionalCount,
+        dynamic: true,
+        get: |c| c.ban_threshold.map_or(String::new(), |n| n.to_string()),
+        set: |c, values| {
+            let threshold = optional(single(values)?)?;
+            if threshold == Some(0) {
+                return None;
+            }
+            c.ban_threshold = threshold;
+            Some(())
+        },
+    },
+    Param {
+        name: "ban-cooldown",
+        kind: Kind::Seconds,
+        dynamic: true,
+        get: |c| c.ban_cooldown.as_secs_f64().to_string(),
+        set: |c, values| {
+            c.ban_cooldown = seconds(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "max-key-length",
+        kind: Kind::OptionalCount,
+        dynamic: true,
+        get: |c| c.max_key_length.map_or(String::new(), |n| n.to_string()),
+        set: |c, values| {
+            c.max_key_length = limit(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "max-value-size",
+        kind: Kind::OptionalCount,
+        dynamic: true,
+        get: |c| c.max_value_size.map_or(String::new(), |n| n.to_string()),
+        set: |c, values| {
+            c.max_value_size = limit(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "max-keys",
+        kind: Kind::OptionalCount,
+        dynamic: true,
+        get: |c| c.max_keys.map_or(String::new(), |n| n.to_string()),
+        set: |c, values| {
+            c.max_keys = limit(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "aof-file",
+        kind: Kind::OptionalPath,
+        dynamic: false,
+        get: |c| {
+            c.aof_file
+                .as_ref()
+                .map_or(String::new(), |p| p.display().to_string())
+        },
+        set: |c, values| {
+            c.aof_file = optional(single(values)?)?;
+            Some(())
+        },
+    },
+    Param {
+        name: "aof-fsync",
+        kind: Kind::Fsync,
+        dynamic: false,
+        get: |c| c.aof_fsync.to_string(),
+        set: |c, values| {
+            c.aof_fsync = single(values)?.parse().ok()?;
+            Some(())
+        },
+    },
+    Param {
+        name: "cdc-sink",
+        kind: Kind::OptionalSink,
+        dynamic: false,
+        get: |c| c.cdc_sink.as_ref().map_or(String::new(), |t| t.to_string()),
+        set: |c, values| {
+            c.cdc_sink = optional(single(values)?)?;
+            Some(())
+        },
+    },
+];
+
+/// Looks up a setting by name, ignoring case.
+pub fn param(name: &str) -> Option<&'static Param> {
+    PARAMS.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+fn single(values: &[String]) -> Option<&str> {
+    match values {
+        [value] => Some(value),
+        _ => None,
+    }
+}
+
+fn optional<T: FromStr>(value: &str) -> Option<Option<T>> {
+    match value {
+        "" | "\"\"" => Some(None),
+        _ => value.parse().ok().map(Some),
+    }
+}
+
+/// A positive limit, or `None` for no limit.
+fn limit(value: &str) -> Option<Option<usize>> {
+    optional(value).filter(|&limit| limit != Some(0))
+}
+
+fn seconds(value: &str) -> Option<Duration> {
+    Duration::try_from_secs_f64(value.parse().ok()?).ok()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind: vec![DEFAULT_BIND.to_string()],
+            port: DEFAULT_PORT,
+            key_hasher: KeyHasher::default(),
+            databases: DEFAULT_DATABASES,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            probe_port: None,
+            ban_threshold: None,
+            ban_cooldown: DEFAULT_BAN_COOLDOWN,
+            max_key_length: None,
+            max_value_size: None,
+            max_keys: None,
+            aof_file: None,
+            aof_fsync: Fsync::default(),
+            cdc_sink: None,
+            file: None,
+        }
+    }
+}
+
+impl Config {
+    /// Builds a config from command line arguments (excluding the program
+    /// name), e.g. `--bind myhost.internal 10.0.0.1 --port 6380`. As with
+    /// Redis, the first argument may instead be the path of a config file,
+    /// which the remaining options then override.
+    pub fn from_args<I>(args: I) -> Result<Config, ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut config = Config::default();
+        let mut args = args.into_iter().peekable();
+
+        if let Some(path) = args.next_if(|a| !a.starts_with("--")) {
+            let text = fs::read_to_string(&path)
+                .map_err(|err| ConfigError(format!("could not read '{path}': {err}")))?;
+            config.load(&text)?;
+            config.file = Some(path.into());
+        }
+
+        while let Some(arg) = args.next() {
+            let name = arg
+                .strip_prefix("--")
+                .filter(|name| param(name).is_some())
+                .ok_or_else(|| ConfigError(format!("unknown option '{arg}'")))?;
+            let mut values = Vec::new();
+            while let Some(value) = args.next_if(|a| !a.starts_with("--")) {
+                values.push(value);
+            }
+            config.set(name, &values)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Applies the settings in a config file, one `name value ...` per line
+    /// with `#` starting a comment line, reporting the line of any error.
+    pub fn load(&mut self, text: &str) -> Result<(), ConfigError> {
+        for (n, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next().filter(|w| !w.starts_with('#')) else {
+                continue;
+            };
+            let values: Vec<_> = words.map(String::from).collect();
+            let at_line = |err: ConfigError| ConfigError(format!("{err} at line {}", n + 1));
+            if param(name).is_none() {
+                return Err(at_line(ConfigError(format!("unknown setting '{name}'"))));
+            }
+            self.set(name, &values).map_err(at_line)?;
+        }
+        Ok(())
+    }
+
+    /// The limits on what writes may store.
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_key_length: self.max_key_length,
+            max_value_size: self.max_value_size,
+            max_keys: self.max_keys,
+        }
+    }
+
+    /// The value of setting `name`, as CONFIG GET shows it.
+    pub fn get(&self, name: &str) -> Option<String> {
+        param(name).map(|param| param.get(self))
+    }
+
+    /// Changes setting `name`, checking the values against its type.
+    pub fn set(&mut self, name: &str, values: &[String]) -> Result<(), ConfigError> {
+        let param = param(name).ok_or_else(|| ConfigError(format!("unknown setting '{name}'")))?;
+        (param.set)(self, values)
+            .ok_or_else(|| ConfigError(format!("{} must be {}", param.name, param.kind.expected())))
+    }
+
+    /// Rewrites the text of a config file to hold the current settings.
+    /// Lines for settings are updated in place, comments and blank lines
+    /// are kept, and settings that differ from their defaults but were not
+    /// in the file are added at the end.
+    pub fn rewrite(&self, original: &str) -> String {
+        let mut written = Vec::new();
+        let mut text = String::new();
+        for line in original.lines() {
+            let name = line.split_whitespace().next().unwrap_or("");
+            match param(name) {
+                Some(param) if written.contains(&param.name) => continue,
+                Some(param) => {
+                    text.push_str(&setting_line(param, self));
+                    written.push(param.name);
+                }
+                None => text.push_str(line),
+            }
+            text.push('\n');
+        }
+        for param in PARAMS {
+            if !written.contains(&param.name) && param.get(self) != param.default_value() {
+                text.push_str(&setting_line(param, self));
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+fn setting_line(param: &Param, config: &Config) -> String {
+    match param.get(config) {
+        value if value.is_empty() => format!("{} \"\"", param.name),
+        value => format!("{} {value}", param.name),
+    }
+}
+
+/// The configuration of a running server, which CONFIG SET changes.
+#[derive(Debug)]
+pub struct LiveConfig {
+    config: RwLock<Config>,
+}
+
+impl LiveConfig {
+    pub fn new(config: Config) -> LiveConfig {
+        LiveConfig {
+            config: RwLock::new(config),
+        }
+    }
+
+    pub fn get(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Changes settings that may be changed at runtime, returning the new
+    /// configuration. Nothing changes unless every setting is valid.
+    pub fn set(&self, settings: &[(String, Vec<String>)]) -> Result<Config, ConfigError> {
+        let mut config = self.config.write().unwrap();
+        let mut changed = config.clone();
+        for (name, values) in settings {
+            if let Some(param) = param(name).filter(|param| !param.dynamic) {
+                return Err(ConfigError(format!(
+                    "'{}' can only be set at startup",
+                    param.name
+                )));
+            }
+            changed.set(name, values)?;
+        }
+        *config = changed.clone();
+        Ok(changed)
+    }
+
+    /// Writes the current settings back to the config file the server was
+    /// started with.
+    pub fn rewrite(&self) -> Result<(), ConfigError> {
+        let config = self.config.read().unwrap();
+        let path = config
+            .file
+            .as_ref()
+            .ok_or_else(|| ConfigError("the server is running without a config file".into()))?;
+        let original = fs::read_to_string(path).unwrap_or_default();
+        fs::write(path, config.rewrite(&original))
+            .map_err(|err| ConfigError(format!("could not write '{}': {err}", path.display())))
+    }
+}
+
+/// Accepts IPv6 literals written in URL style, so `[::1]` binds like `::1`.
+fn strip_brackets(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::from_args(args("")).unwrap();
+
+        assert_eq!(config.bind, ["127.0.0.1"]);
+        assert_eq!(config.port, 6379);
+        assert_eq!(config.key_hasher, KeyHasher::SipHash);
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(10));
+        assert_eq!(config.probe_port, None);
+    }
+
+    #[test]
+    fn test_bind_hostname_and_port() {
+        let config = Config::from_args(args("--bind myhost.internal --port 6380")).unwrap();
+
+        assert_eq!(config.bind, ["myhost.internal"]);
+        assert_eq!(config.port, 6380);
+    }
+
+    #[test]
+    fn test_bind_multiple_addresses() {
+        let config = Config::from_args(args("--port 7000 --bind localhost 10.0.0.1")).unwrap();
+
+        assert_eq!(config.bind, ["localhost", "10.0.0.1"]);
+        assert_eq!(config.port, 7000);
+    }
+
+    #[test]
+    fn test_bind_ipv6_addresses() {
+        let config = Config::from_args(args("--bind :: [::1]")).unwrap();
+
+        assert_eq!(config.bind, ["::", "::1"]);
+    }
+
+    #[test]
+    fn test_bind_without_address() {
+        assert!(Config::from_args(args("--bind")).is_err());
+        assert!(Config::from_args(args("--bind --port 6380")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_port() {
+        assert!(Config::from_args(args("--port redis")).is_err());
+        assert!(Config::from_args(args("--port 70000")).is_err());
+        assert!(Config::from_args(args("--port")).is_err());
+    }
+
+    #[test]
+    fn test_probe_port() {
+        let config = Config::from_args(args("--probe-port 8080")).unwrap();
+
+        assert_eq!(config.probe_port, Some(8080));
+        assert!(Config::from_args(args("--probe-port http")).is_err());
+        assert!(Config::from_args(args("--probe-port")).is_err());
+    }
+
+    #[test]
+    fn test_ban_options() {
+        let config = Config::from_args(args("--ban-threshold 100 --ban-cooldown 30")).unwrap();
+
+        assert_eq!(config.ban_threshold, Some(100));
+        assert_eq!(config.ban_cooldown, Duration::from_secs(30));
+        assert_eq!(Config::default().ban_threshold, None);
+        assert!(Config::from_args(args("--ban-threshold 0")).is_err());
+        assert!(Config::from_args(args("--ban-cooldown forever")).is_err());
+    }
+
+    #[test]
+    fn test_key_hasher() {
+        let config = Config::from_args(args("--key-hasher fx")).unwrap();
+
+        assert_eq!(config.key_hasher, KeyHasher::Fx);
+        assert!(Config::from_args(args("--key-hasher md5")).is_err());
+        assert!(Config::from_args(args("--key-hasher")).is_err());
+    }
+
+    #[test]
+    fn test_shutdown_timeout() {
+        let config = Config::from_args(args("--shutdown-timeout 2.5")).unwrap();
+
+        assert_eq!(config.shutdown_timeout, Duration::from_millis(2500));
+        assert!(Config::from_args(args("--shutdown-timeout -1")).is_err());
+        assert!(Config::from_args(args("--shutdown-timeout soon")).is_err());
+    }
+
+    #[test]
+    fn test_databases() {
+        assert_eq!(Config::default().databases, 16);
+        assert_eq!(
+            Config::from_args(args("--databases 2")).unwrap().databases,
+            2
+        );
+        assert!(Config::from_args(args("--databases 0")).is_err());
+        assert!(Config::from_args(args("--databases many")).is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let config = Config::from_args(args("--max-key-length 64 --max-keys 1000")).unwrap();
+
+        assert_eq!(
+            config.limits(),
+            Limits {
+                max_key_length: Some(64),
+                max_value_size: None,
+                max_keys: Some(1000),
+            }
+        );
+        assert_eq!(config.get("max-value-size").unwrap(), "");
+        assert!(Config::from_args(args("--max-keys 0")).is_err());
+        assert!(Config::from_args(args("--max-value-size -1")).is_err());
+    }
+
+    #[test]
+    fn test_aof() {
+        let config = Config::from_args(args("--aof-file data.aof --aof-fsync always")).unwrap();
+
+        assert_eq!(config.aof_file, Some("data.aof".into()));
+        assert_eq!(config.aof_fsync, Fsync::Always);
+        assert_eq!(Config::default().get("aof-fsync").unwrap(), "everysec");
+        assert_eq!(Config::default().get("aof-file").unwrap(), "");
+        assert!(Config::from_args(args("--aof-fsync sometimes")).is_err());
+    }
+
+    #[test]
+    fn test_cdc_sink() {
+        let config = Config::from_args(args("--cdc-sink tcp:mirror:9000")).unwrap();
+
+        assert_eq!(
+            config.cdc_sink,
+            Some(Target::Tcp("mirror:9000".to_string()))
+        );
+        assert_eq!(config.get("cdc-sink").unwrap(), "tcp:mirror:9000");
+        assert_eq!(Config::default().cdc_sink, None);
+        assert!(Config::from_args(args("--cdc-sink mirror:9000")).is_err());
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        assert!(Config::from_args(args("--verbose")).is_err());
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let mut config = Config::default();
+
+        config.set("ban-threshold", &args("5")).unwrap();
+        config.set("BIND", &args("a b")).unwrap();
+        config.set("ban-threshold", &[String::new()]).unwrap();
+
+        assert_eq!(config.ban_threshold, None);
+        assert_eq!(config.get("bind").unwrap(), "a b");
+        assert_eq!(config.get("shutdown-timeout").unwrap(), "10");
+        assert_eq!(config.get("probe-port").unwrap(), "");
+        assert_eq!(config.get("nothing"), None);
+        assert_eq!(
+            config
+                .set("port", &args("6379 6380"))
+                .unwrap_err()
+                .to_string(),
+            "port must be a port number (0-65535)"
+        );
+    }
+
+    #[test]
+    fn test_load_file() {
+        let mut config = Config::default();
+        let text = "# listen everywhere\n\nbind 0.0.0.0 ::\nport 7000\nban-cooldown 1.5\n";
+
+        config.load(text).unwrap();
+
+        assert_eq!(config.bind, ["0.0.0.0", "::"]);
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.ban_cooldown, Duration::from_millis(1500));
+        assert_eq!(
+            config
+                .load("port 1\n\nkey-hasher md5\n")
+                .unwrap_err()
+                .to_string(),
+            "key-hasher must be one of siphash or fx at line 3"
+        );
+        assert_eq!(
+            config.load("maxmemory 100mb").unwrap_err().to_string(),
+            "unknown setting 'maxmemory' at line 1"
+        );
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let mut config = Config::default();
+        config.load("port 7000\nban-threshold 3").unwrap();
+        config.port = 7001;
+        config.ban_threshold = None;
+        config.shutdown_timeout = Duration::from_secs(2);
+
+        let original = "# mine\nport 7000\nport 7002\nban-threshold 3\n";
+        assert_eq!(
+            config.rewrite(original),
+            "# mine\nport 7001\nban-threshold \"\"\nshutdown-timeout 2\n"
+        );
+    }
+
+    #[test]
+    fn test_live_config_refuses_static_settings() {
+        let live = LiveConfig::new(Config::default());
+
+        let setting = |name: &str, value: &str| (name.to_string(), args(value));
+
+        assert!(live
+            .set(&[setting("ban-cooldown", "5"), setting("port", "7000")])
+            .is_err());
+        assert_eq!(live.get().ban_cooldown, DEFAULT_BAN_COOLDOWN);
+        assert_eq!(
+            live.set(&[setting("ban-cooldown", "5")])
+                .unwrap()
+                .ban_cooldown,
+            Duration::from_secs(5)
+        );
+        assert_eq!(live.get().ban_cooldown, Duration::from_secs(5));
+        assert!(live.rewrite().is_err());
+    }
+}
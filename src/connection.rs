@@ -0,0 +1,325 @@
+use bytes::{Buf, BytesMut};
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, DuplexStream};
+use tokio::net::TcpStream;
+
+use crate::frame_writer::FrameWriter;
+use crate::resp::{Frame, ParseError, Scanner};
+
+/// The most a client may send that has not been parsed into a request yet,
+/// matching Redis' client-query-buffer-limit default.
+pub const QUERY_BUFFER_LIMIT: usize = 1024 * 1024 * 1024;
+
+/// The byte stream a client is connected over: a TCP socket, or an
+/// in-memory pipe from [`tokio::io::duplex`] that lets tests drive the whole
+/// server without the network.
+pub trait Transport: AsyncRead + AsyncWrite + Send + Sync + Unpin + fmt::Debug {
+    /// The socket underneath, for the few commands that need one.
+    fn tcp(&self) -> Option<&TcpStream> {
+        None
+    }
+}
+
+impl Transport for TcpStream {
+    fn tcp(&self) -> Option<&TcpStream> {
+        Some(self)
+    }
+}
+
+impl Transport for DuplexStream {}
+
+/// Reads and writes RESP frames on a client's transport.
+///
+/// Incoming bytes are accumulated in a growable buffer until a whole frame
+/// is available, so requests are not limited by the size of a single read,
+/// only by the query buffer limit. Pipelined requests that arrive together
+/// are returned one at a time, and their replies are held back until the
+/// last of them has been answered.
+#[derive(Debug)]
+pub struct Connection {
+    stream: BufWriter<Box<dyn Transport>>,
+    buffer: BytesMut,
+    query_buffer_limit: usize,
+    /// How much of the request at the start of `buffer` has arrived.
+    scanner: Scanner,
+    /// A complete request already parsed out of `buffer` while deciding
+    /// whether to flush.
+    pending: Option<Frame>,
+}
+
+impl Connection {
+    pub fn new(transport: impl Transport + 'static) -> Connection {
+        Connection {
+            stream: BufWriter::new(Box::new(transport)),
+            buffer: BytesMut::with_capacity(4 * 1024),
+            query_buffer_limit: QUERY_BUFFER_LIMIT,
+            scanner: Scanner::default(),
+            pending: None,
+        }
+    }
+
+    /// Sets the most the client may send ahead of what has been parsed,
+    /// beyond which reading fails, in place of [`QUERY_BUFFER_LIMIT`].
+    pub fn with_query_buffer_limit(mut self, limit: usize) -> Connection {
+        self.query_buffer_limit = limit;
+        self
+    }
+
+    /// The TCP socket the client is connected on, `None` for an in-memory
+    /// connection.
+    pub fn socket(&self) -> Option<&TcpStream> {
+        self.stream.get_ref().tcp()
+    }
+
+    /// Bytes received that have not been parsed into a request yet.
+    pub fn query_buffer_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Bytes of replies waiting to be flushed to the socket.
+    pub fn output_buffer_len(&self) -> usize {
+        self.stream.buffer().len()
+    }
+
+    /// Reads the next frame, waiting for more data while the buffered bytes
+    /// only hold part of one. Returns `None` when the peer closes the
+    /// connection cleanly between frames.
+    ///
+    /// Malformed input is reported as an `InvalidData` error wrapping the
+    /// `ParseError`, as is going over the query buffer limit.
+    pub async fn read_frame(&mut self) -> io::Result<Option<Frame>> {
+        if let Some(frame) = self.pending.take() {
+            return Ok(Some(frame));
+        }
+
+        loop {
+            if let Some(frame) = self.parse_frame()? {
+                return Ok(Some(frame));
+            }
+
+            if self.fill().await? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "connection closed mid-frame",
+                ));
+            }
+        }
+    }
+
+    /// Completes once the peer closes the connection or it fails, keeping
+    /// anything else the peer sends meanwhile for `read_frame`. Commands
+    /// that wait race against this, so they stop when their client leaves.
+    pub async fn closed(&mut self) {
+        loop {
+            match self.fill().await {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+        }
+    }
+
+    /// Returns the next frame if one has already been received in full,
+    /// without waiting for the peer.
+    pub fn buffered_frame(&mut self) -> io::Result<Option<Frame>> {
+        match self.pending.take() {
+            Some(frame) => Ok(Some(frame)),
+            None => self.parse_frame(),
+        }
+    }
+
+    /// Reads more from the peer into the buffer, returning how much.
+    async fn fill(&mut self) -> io::Result<usize> {
+        let read = self.stream.read_buf(&mut self.buffer).await?;
+        if self.buffer.len() > self.query_buffer_limit {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "query buffer limit of {} bytes exceeded",
+                    self.query_buffer_limit
+                ),
+            ));
+        }
+        Ok(read)
+    }
+
+    /// Parses the next frame once it has been received in full. Until
+    /// then only the bytes that arrived since the last try are looked at.
+    fn parse_frame(&mut self) -> io::Result<Option<Frame>> {
+        let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+        if self.scanner.scan(&self.buffer).map_err(invalid)?.is_none() {
+            return Ok(None);
+        }
+        self.scanner = Scanner::default();
+        match Frame::parse(&self.buffer) {
+            Ok((frame, len)) => {
+                self.buffer.advance(len);
+                Ok(Some(frame))
+            }
+            Err(ParseError::Incomplete) => Ok(None),
+            Err(err) => Err(invalid(err)),
+        }
+    }
+
+    /// Writes a reply. It is flushed to the socket straight away unless
+    /// another complete request is already buffered, in which case it goes
+    /// out together with the replies to the rest of the pipeline.
+    ///
+    /// Large values are streamed to the socket rather than copied into an
+    /// encoded reply first.
+    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        FrameWriter::new(&mut self.stream)
+            .write_frame(frame)
+            .await?;
+
+        if self.pending.is_none() {
+            // Malformed input is left in the buffer for read_frame to report.
+            self.pending = self.parse_frame().unwrap_or(None);
+        }
+        if self.pending.is_none() {
+            self.stream.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use tokio::net::TcpListener;
+
+    async fn connected_pair() -> (Connection, DuplexStream) {
+        let (client, server) = tokio::io::duplex(4 * 1024);
+        (Connection::new(server), client)
+    }
+
+    #[tokio::test]
+    async fn test_socket_only_for_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+
+        assert!(Connection::new(server).socket().is_some());
+        assert!(connected_pair().await.0.socket().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_larger_than_one_read() {
+        let (mut conn, mut client) = connected_pair().await;
+        let value = vec![b'x'; 100_000];
+        let frame = Frame::Array(vec![
+            Frame::BulkString(Bytes::from("set")),
+            Frame::BulkString(Bytes::from("key")),
+            Frame::BulkString(Bytes::from(value)),
+        ]);
+        let encoded = frame.to_bytes();
+
+        let writer = tokio::spawn(async move {
+            for chunk in encoded.chunks(1000) {
+                client.write_all(chunk).await.unwrap();
+            }
+            client
+        });
+
+        assert_eq!(conn.read_frame().await.unwrap(), Some(frame));
+        drop(writer.await.unwrap());
+        assert_eq!(conn.read_frame().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_closed_mid_frame() {
+        let (mut conn, mut client) = connected_pair().await;
+
+        client.write_all(b"*1\r\n$4\r\npi").await.unwrap();
+        drop(client);
+
+        let err = conn.read_frame().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_malformed() {
+        let (mut conn, mut client) = connected_pair().await;
+
+        client.write_all(b"?what\r\n").await.unwrap();
+
+        let err = conn.read_frame().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_over_query_buffer_limit() {
+        let (conn, mut client) = connected_pair().await;
+        let mut conn = conn.with_query_buffer_limit(16);
+
+        client.write_all(b"*1\r\n$4\r\nping\r\n").await.unwrap();
+        assert!(conn.read_frame().await.unwrap().is_some());
+
+        client.write_all(b"*1\r\n$20\r\n0123456789").await.unwrap();
+        let err = conn.read_frame().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_read_pipelined_frames() {
+        let (mut conn, mut client) = connected_pair().await;
+
+        client
+            .write_all(b"*1\r\n$4\r\nping\r\n*2\r\n$4\r\necho\r\n$2\r\nhi\r\n:1\r\n")
+            .await
+            .unwrap();
+
+        let ping = Frame::Array(vec![Frame::BulkString(Bytes::from("ping"))]);
+        let echo = Frame::Array(vec![
+            Frame::BulkString(Bytes::from("echo")),
+            Frame::BulkString(Bytes::from("hi")),
+        ]);
+        assert_eq!(conn.read_frame().await.unwrap(), Some(ping));
+        assert_eq!(conn.read_frame().await.unwrap(), Some(echo));
+        assert_eq!(conn.read_frame().await.unwrap(), Some(Frame::Integer(1)));
+    }
+
+    #[tokio::test]
+    async fn test_buffered_frame_does_not_wait() {
+        let (mut conn, mut client) = connected_pair().await;
+        client.write_all(b":1\r\n:2").await.unwrap();
+
+        assert_eq!(conn.read_frame().await.unwrap(), Some(Frame::Integer(1)));
+        assert_eq!(conn.buffered_frame().unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies_flushed_after_last_request() {
+        let (mut conn, mut client) = connected_pair().await;
+        client.write_all(b":1\r\n:2\r\n$3\r\nabc").await.unwrap();
+
+        conn.read_frame().await.unwrap();
+        conn.write_frame(&Frame::Integer(10)).await.unwrap();
+        conn.read_frame().await.unwrap();
+        conn.write_frame(&Frame::Integer(20)).await.unwrap();
+
+        // Only a partial request is left, so both replies must be sent.
+        let mut buf = [0; 10];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b":10\r\n:20\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_write_frame() {
+        let (mut conn, mut client) = connected_pair().await;
+
+        conn.write_frame(&Frame::SimpleString("OK".into()))
+            .await
+            .unwrap();
+
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"+OK\r\n");
+    }
+}
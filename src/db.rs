@@ -1,11 +1,33 @@
 use bytes::Bytes;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard};
 use std::time::{Duration, Instant};
 
+use crate::float;
+use crate::glob;
+use crate::hasher::{self, KeyHasher, KeyHasherBuilder};
+use crate::range;
+use crate::signal::Signal;
+use crate::value::Value;
+use crate::zset::{self, AddOptions, ScoreBound, SortedSet};
+
+/// How many databases there are unless configured otherwise, as in Redis.
+pub const DEFAULT_DATABASES: usize = 16;
+
 #[derive(Debug)]
 pub struct DbHandle {
-    db: Db,
+    databases: Databases,
+}
+
+/// The numbered databases a client chooses between with SELECT, each a
+/// separate keyspace.
+#[derive(Debug, Clone)]
+pub struct Databases {
+    dbs: Arc<[Db]>,
 }
 
 #[derive(Debug, Clone)]
@@ -16,16 +38,219 @@ pub struct Db {
 #[derive(Debug)]
 struct SharedState {
     state: Mutex<State>,
+    /// Held shared by every operation, and exclusively by
+    /// [`Databases::exclusive`]. All the databases of a handle share one.
+    gate: Arc<RwLock<()>>,
+    /// Keys being traced with `DEBUG TRACEKEY`, and until when.
+    traced: Mutex<HashMap<String, Instant>>,
+    /// Notified whenever a list is pushed to, for blocked pops.
+    pushed: Signal,
 }
 
 #[derive(Debug)]
 struct State {
-    entries: HashMap<String, Entry>,
+    entries: HashMap<String, Entry, KeyHasherBuilder>,
+    /// Keys with a TTL, ordered by when they expire.
+    expirations: BTreeSet<(Instant, String)>,
+    /// Every key, in the order SCAN walks them.
+    scan_order: BTreeSet<(u64, String)>,
+    /// Keys somebody is waiting to see written, each with a signal that is
+    /// notified on every write.
+    watched: HashMap<String, Arc<Signal>>,
+    /// The keys written recently, once [`Db::record_changes`] is called.
+    changes: Option<ChangeLog>,
+}
+
+#[derive(Debug)]
+struct ChangeLog {
+    /// The offset the next write gets.
+    next_offset: u64,
+    capacity: usize,
+    /// The most recent writes, oldest first, each with its offset and the
+    /// key written, or `None` for a flush.
+    recent: VecDeque<(u64, Option<String>)>,
+    /// Notified on every write.
+    written: Arc<Signal>,
+}
+
+/// One write recorded by [`Db::record_changes`], with the key's value and
+/// TTL when it was read back: `None` if it has since been deleted or has
+/// expired. Later writes to the same key have their own changes, so a
+/// reader that applies them in order ends up with the same keyspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub offset: u64,
+    /// The key written, `None` if every key was deleted by a flush.
+    pub key: Option<String>,
+    pub value: Option<Value>,
+    /// Time left before the key expires, `None` if it never does.
+    pub ttl: Option<Duration>,
+}
+
+/// Returned by [`Db::changes_since`] when the change log has already
+/// dropped the requested offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangesLost {
+    /// The oldest offset still held.
+    pub oldest: u64,
+}
+
+impl fmt::Display for ChangesLost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "changes before offset {} were dropped", self.oldest)
+    }
+}
+
+impl std::error::Error for ChangesLost {}
+
+/// How many keys [`Db::snapshot`] copies each time it takes the lock.
+const SNAPSHOT_BATCH: usize = 128;
+
+/// A point-in-time copy of one key, as returned by [`Db::snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySnapshot {
+    pub key: String,
+    pub value: Value,
+    /// Time left before the key expires, `None` if it never does.
+    pub ttl: Option<Duration>,
+}
+
+/// The keys removed by [`Db::flush`]. Freeing a large keyspace takes a while,
+/// so the caller decides where this is dropped.
+#[derive(Debug)]
+pub struct Flushed {
+    _entries: HashMap<String, Entry, KeyHasherBuilder>,
+    _expirations: BTreeSet<(Instant, String)>,
+    _scan_order: BTreeSet<(u64, String)>,
+}
+
+/// A watch on one key, started by [`Db::watch`] and stopped by dropping it.
+#[derive(Debug)]
+pub struct KeyWatch {
+    db: Db,
+    key: String,
+    signal: Arc<Signal>,
+    epoch: u64,
+}
+
+/// The `NX`, `XX`, `GT` and `LT` options of the EXPIRE commands. A key
+/// without a TTL counts as expiring infinitely far in the future.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExpireCondition {
+    /// Only if the key has no TTL.
+    pub nx: bool,
+    /// Only if the key already has a TTL.
+    pub xx: bool,
+    /// Only if the new expiry is later than the current one.
+    pub gt: bool,
+    /// Only if the new expiry is earlier than the current one.
+    pub lt: bool,
+}
+
+impl ExpireCondition {
+    fn allows(&self, current: Option<Instant>, new: Instant) -> bool {
+        match current {
+            None => !self.xx && !self.gt,
+            Some(current) => !self.nx && (!self.gt || new > current) && (!self.lt || new < current),
+        }
+    }
+}
+
+/// When SET writes its value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SetCondition {
+    #[default]
+    Always,
+    /// Only if the key does not exist (`NX`).
+    NotExists,
+    /// Only if the key already exists (`XX`).
+    Exists,
+}
+
+/// What SET does with the key's TTL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetExpiry {
+    /// Remove any TTL.
+    Clear,
+    /// Leave the current TTL in place (`KEEPTTL`).
+    Keep,
+    At(Instant),
+}
+
+/// Returned when a command expects a key to hold a different type of value
+/// than it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrongType;
+
+impl fmt::Display for WrongType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WRONGTYPE Operation against a key holding the wrong kind of value")
+    }
+}
+
+impl std::error::Error for WrongType {}
+
+/// Returned when a command needs a key to exist and it does not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoSuchKey;
+
+impl fmt::Display for NoSuchKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ERR no such key")
+    }
+}
+
+impl std::error::Error for NoSuchKey {}
+
+/// Why INCRBYFLOAT or HINCRBYFLOAT could not add to a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncrError {
+    WrongType,
+    /// The value is not a number.
+    NotFloat,
+    /// The result would be infinite or NaN.
+    NotFinite,
+}
+
+impl fmt::Display for IncrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncrError::WrongType => WrongType.fmt(f),
+            IncrError::NotFloat => f.write_str("ERR value is not a valid float"),
+            IncrError::NotFinite => f.write_str("ERR increment would produce NaN or Infinity"),
+        }
+    }
+}
+
+impl std::error::Error for IncrError {}
+
+impl From<WrongType> for IncrError {
+    fn from(_: WrongType) -> Self {
+        IncrError::WrongType
+    }
+}
+
+/// Which end of a list to push to or pop from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// How [`Db::combine_sets`] combines its sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    /// Members of every set.
+    Inter,
+    /// Members of any set.
+    Union,
+    /// Members of the first set that are in none of the others.
+    Diff,
 }
 
 #[derive(Debug)]
 struct Entry {
-    data: Bytes,
+    data: Stored,
     expires_at: Option<Instant>,
 }
 
@@ -37,11 +262,157 @@ impl Default for DbHandle {
 
 impl DbHandle {
     pub fn new() -> DbHandle {
-        DbHandle { db: Db::new() }
+        DbHandle::with_key_hasher(KeyHasher::default())
+    }
+
+    pub fn with_key_hasher(hasher: KeyHasher) -> DbHandle {
+        DbHandle::with_databases(DEFAULT_DATABASES, hasher)
+    }
+
+    /// Creates `count` empty databases, at least one.
+    pub fn with_databases(count: usize, hasher: KeyHasher) -> DbHandle {
+        let gate = Arc::default();
+        DbHandle {
+            databases: Databases {
+                dbs: (0..count.max(1))
+                    .map(|_| Db::with_gate(hasher, Arc::clone(&gate)))
+                    .collect(),
+            },
+        }
     }
 
+    /// Database 0.
     pub fn db(&self) -> Db {
-        self.db.clone()
+        self.databases[0].clone()
+    }
+
+    pub fn databases(&self) -> Databases {
+        self.databases.clone()
+    }
+}
+
+impl Databases {
+    pub fn get(&self, index: usize) -> Option<&Db> {
+        self.dbs.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.dbs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dbs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Db> {
+        self.dbs.iter()
+    }
+
+    /// Runs `f` with every other operation on these databases held off, so
+    /// that the operations `f` makes happen together. `f` runs on this
+    /// thread and must not wait for other clients.
+    pub fn exclusive<T>(&self, f: impl FnOnce() -> T) -> T {
+        let gate = &self.dbs[0].shared.gate;
+        let _exclusive = gate.write().unwrap();
+        let _held = HeldGate::enter(gate);
+        f()
+    }
+
+    /// Swaps the keys of databases `a` and `b`. Anyone watching a key in
+    /// either sees it written.
+    pub fn swap(&self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        let _gate = self.dbs[a].enter_gate();
+        let (mut first, mut second) = self.lock_pair(a, b);
+        std::mem::swap(&mut first.entries, &mut second.entries);
+        std::mem::swap(&mut first.expirations, &mut second.expirations);
+        std::mem::swap(&mut first.scan_order, &mut second.scan_order);
+        first.replaced();
+        second.replaced();
+    }
+
+    /// Moves `key`, with its TTL, from database `from` to database `to`,
+    /// returning whether it was moved: it must exist in `from` and not in
+    /// `to`.
+    pub fn move_key(&self, key: &str, from: usize, to: usize) -> bool {
+        if from == to {
+            return false;
+        }
+        let now = Instant::now();
+        let _gate = self.dbs[from].enter_gate();
+        let (mut src, mut dst) = self.lock_pair(from, to);
+        if src.live_entry(key, now).is_none() || dst.live_entry(key, now).is_some() {
+            return false;
+        }
+        let entry = src.remove(key).unwrap();
+        dst.insert(key.to_string(), entry);
+        true
+    }
+
+    /// Locks the state of two different databases, always in index order
+    /// so that concurrent calls cannot deadlock. The caller holds the gate.
+    fn lock_pair(&self, a: usize, b: usize) -> (MutexGuard<'_, State>, MutexGuard<'_, State>) {
+        let lock = |index: usize| self.dbs[index].shared.state.lock().unwrap();
+        if a < b {
+            let first = lock(a);
+            (first, lock(b))
+        } else {
+            let second = lock(b);
+            (lock(a), second)
+        }
+    }
+}
+
+thread_local! {
+    /// The gate this thread holds exclusively, if any, as an address.
+    static HELD_GATE: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks a gate as held by this thread until dropped, so operations run
+/// inside [`Databases::exclusive`] don't wait for it themselves.
+struct HeldGate;
+
+impl HeldGate {
+    fn enter(gate: &Arc<RwLock<()>>) -> HeldGate {
+        HELD_GATE.with(|held| held.set(Arc::as_ptr(gate) as usize));
+        HeldGate
+    }
+}
+
+impl Drop for HeldGate {
+    fn drop(&mut self) {
+        HELD_GATE.with(|held| held.set(0));
+    }
+}
+
+/// The locked state of one database, along with the shared hold on the
+/// gate taken first.
+struct Locked<'a> {
+    state: MutexGuard<'a, State>,
+    _gate: Option<RwLockReadGuard<'a, ()>>,
+}
+
+impl Deref for Locked<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        &self.state
+    }
+}
+
+impl DerefMut for Locked<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        &mut self.state
+    }
+}
+
+impl std::ops::Index<usize> for Databases {
+    type Output = Db;
+
+    fn index(&self, index: usize) -> &Db {
+        &self.dbs[index]
     }
 }
 
@@ -53,110 +424,2846 @@ impl Default for Db {
 
 impl Db {
     pub fn new() -> Db {
+        Db::with_key_hasher(KeyHasher::default())
+    }
+
+    pub fn with_key_hasher(hasher: KeyHasher) -> Db {
+        Db::with_gate(hasher, Arc::default())
+    }
+
+    fn with_gate(hasher: KeyHasher, gate: Arc<RwLock<()>>) -> Db {
         let shared = Arc::new(SharedState {
+            gate,
             state: Mutex::new(State {
-                entries: HashMap::new(),
+                entries: HashMap::with_hasher(hasher.build()),
+                expirations: BTreeSet::new(),
+                scan_order: BTreeSet::new(),
+                watched: HashMap::new(),
+                changes: None,
             }),
+            traced: Mutex::new(HashMap::new()),
+            pushed: Signal::default(),
         });
         Db { shared }
     }
 
+    /// Takes a shared hold on the gate, unless this thread holds it
+    /// exclusively already.
+    fn enter_gate(&self) -> Option<RwLockReadGuard<'_, ()>> {
+        let gate = &self.shared.gate;
+        let held = HELD_GATE.with(Cell::get) == Arc::as_ptr(gate) as usize;
+        (!held).then(|| gate.read().unwrap())
+    }
+
+    fn lock(&self) -> Locked<'_> {
+        let gate = self.enter_gate();
+        Locked {
+            state: self.shared.state.lock().unwrap(),
+            _gate: gate,
+        }
+    }
+
+    /// The value of `key` if it holds a string.
     pub fn get(&self, key: &str) -> Option<Bytes> {
-        let state = self.shared.state.lock().unwrap();
-        let value = match state.entries.get(key) {
-            None => None,
-            Some(e) => {
-                let data = e.data.clone();
-                match &e.expires_at {
-                    None => Some(data),
-                    Some(expiry) => {
-                        if expiry < &Instant::now() {
-                            // todo delete entry
-                            None
-                        } else {
-                            Some(data)
-                        }
-                    }
-                }
-            }
-        };
-        value
+        let mut state = self.lock();
+        state.live_entry(key, Instant::now())?.data.bytes()
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<Value> {
+        let mut state = self.lock();
+        let entry = state.live_entry(key, Instant::now())?;
+        Some(entry.data.value().into_owned())
+    }
+
+    /// Looks up several keys under one lock, so the values all come from
+    /// the same moment. Keys that do not hold strings are `None`.
+    pub fn mget(&self, keys: &[String]) -> Vec<Option<Bytes>> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        keys.iter()
+            .map(|key| state.live_entry(key, now)?.data.bytes())
+            .collect()
+    }
+
+    /// Stores several keys under one lock, removing any TTLs they had.
+    pub fn mset(&self, pairs: Vec<(String, Bytes)>) {
+        let mut state = self.lock();
+        for (key, value) in pairs {
+            state.insert(
+                key,
+                Entry {
+                    data: Value::String(value).into(),
+                    expires_at: None,
+                },
+            );
+        }
+    }
+
+    /// The type of the value at `key`, as named by [`Value::type_name`].
+    pub fn type_name(&self, key: &str) -> Option<&'static str> {
+        let mut state = self.lock();
+        let entry = state.live_entry(key, Instant::now())?;
+        Some(entry.data.type_name())
+    }
+
+    /// How the value at `key` is represented, as named by
+    /// [`Value::encoding`], except that a string short enough to be held in
+    /// the entry itself is `embstr`, as Redis calls its own.
+    pub fn encoding(&self, key: &str) -> Option<&'static str> {
+        let mut state = self.lock();
+        let entry = state.live_entry(key, Instant::now())?;
+        Some(entry.data.encoding())
+    }
+
+    /// Whether `key` holds a value that has not expired.
+    pub fn exists(&self, key: &str) -> bool {
+        let mut state = self.lock();
+        state.live_entry(key, Instant::now()).is_some()
+    }
+
+    /// The number of live keys.
+    pub fn len(&self) -> usize {
+        let state = self.lock();
+        state.live_len(Instant::now())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A live key picked uniformly at random, or `None` if there are none.
+    /// Nothing is copied but the key, though finding it can take a walk
+    /// through much of the keyspace.
+    pub fn random_key(&self) -> Option<String> {
+        let now = Instant::now();
+        let state = self.lock();
+        let live = state.live_len(now);
+        if live == 0 {
+            return None;
+        }
+        let n = (hasher::random() % live as u64) as usize;
+        state
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_expired(now))
+            .nth(n)
+            .map(|(key, _)| key.clone())
+    }
+
+    /// The live keys matching the glob `pattern`, sorted. This walks the
+    /// whole keyspace under the lock.
+    pub fn keys(&self, pattern: &[u8]) -> Vec<String> {
+        let now = Instant::now();
+        let state = self.lock();
+        let mut keys: Vec<_> = state
+            .entries
+            .iter()
+            .filter(|(key, e)| !e.is_expired(now) && glob::matches(pattern, key.as_bytes()))
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// One step of walking the keyspace: looks at about `count` keys from
+    /// `cursor` on and returns the cursor to continue from, 0 once the walk
+    /// is done, with those that are live, match `pattern` and hold a value
+    /// of type `type_name`. A key that exists for the whole walk is
+    /// returned exactly once, however the keyspace changes in between.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        type_name: Option<&str>,
+    ) -> (u64, Vec<String>) {
+        let now = Instant::now();
+        let state = self.lock();
+        let start = (cursor, String::new());
+        let keys = state
+            .scan_order
+            .range(start..)
+            .map(|(hash, key)| (*hash, key));
+        let (cursor, keys) = scan_batch(keys, count);
+        let keys = keys
+            .into_iter()
+            .filter(|key| {
+                let entry = &state.entries[key.as_str()];
+                !entry.is_expired(now)
+                    && pattern.is_none_or(|pattern| glob::matches(pattern, key.as_bytes()))
+                    && type_name.is_none_or(|name| entry.data.type_name() == name)
+            })
+            .cloned()
+            .collect();
+        (cursor, keys)
     }
 
     pub fn set(&self, key: String, value: Bytes, duration: Option<Duration>) {
         let expires_at: Option<Instant> = duration.map(|d| Instant::now() + d);
 
-        let mut state = self.shared.state.lock().unwrap();
-        state.entries.insert(
+        let mut state = self.lock();
+        state.insert(
             key,
             Entry {
-                data: value,
+                data: Value::String(value).into(),
                 expires_at,
             },
         );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::{thread, time};
+    /// Stores `value` under `key` if `condition` allows it, replacing a
+    /// value of any type. Returns whether it was written, together with the
+    /// value `key` held before. With `get`, as for `SET ... GET`, a key that
+    /// holds something other than a string is left alone instead.
+    pub fn set_with(
+        &self,
+        key: String,
+        value: Bytes,
+        condition: SetCondition,
+        expiry: SetExpiry,
+        get: bool,
+    ) -> Result<(bool, Option<Value>), WrongType> {
+        let mut state = self.lock();
+        let current = state.live_entry(&key, Instant::now());
+        let old = current
+            .as_ref()
+            .map(|entry| entry.data.value().into_owned());
+        let old_expiry = current.and_then(|entry| entry.expires_at);
+        if get && old.as_ref().is_some_and(|old| old.as_bytes().is_none()) {
+            return Err(WrongType);
+        }
 
-    #[test]
-    fn test_create_new_db() {
-        let _ = Db::new();
+        let allowed = match condition {
+            SetCondition::Always => true,
+            SetCondition::NotExists => old.is_none(),
+            SetCondition::Exists => old.is_some(),
+        };
+        if allowed {
+            let expires_at = match expiry {
+                SetExpiry::Clear => None,
+                SetExpiry::Keep => old_expiry,
+                SetExpiry::At(at) => Some(at),
+            };
+            state.insert(
+                key,
+                Entry {
+                    data: Value::String(value).into(),
+                    expires_at,
+                },
+            );
+        }
+        Ok((allowed, old))
     }
 
-    #[test]
-    fn test_set_and_get_key_in_db() {
-        let db = Db::new();
-        let key: &str = "Foo";
-        let value = Bytes::from("Bar");
-        db.set(key.to_string(), value.clone(), None);
+    /// Overwrites `key` with `data` from byte `offset` on, zero-padding the
+    /// string first if it is shorter than `offset`, and returns the new
+    /// length. A missing key is treated as an empty string, but is only
+    /// created if there is something to write. The TTL is kept.
+    pub fn set_range(&self, key: &str, offset: usize, data: &[u8]) -> Result<usize, WrongType> {
+        let mut state = self.lock();
+        let current = state.live_entry(key, Instant::now());
+        let old = match current.as_ref().map(|entry| entry.data.bytes()) {
+            None => Bytes::new(),
+            Some(Some(old)) => old,
+            Some(None) => return Err(WrongType),
+        };
+        if data.is_empty() {
+            return Ok(old.len());
+        }
 
-        let value_got = db.get(key);
+        let mut new = Vec::with_capacity(old.len().max(offset + data.len()));
+        new.extend_from_slice(&old);
+        if new.len() < offset + data.len() {
+            new.resize(offset + data.len(), 0);
+        }
+        new[offset..offset + data.len()].copy_from_slice(data);
+        let len = new.len();
 
-        assert_eq!(value, value_got.unwrap());
+        match current {
+            Some(entry) => {
+                entry.data = Value::String(new.into()).into();
+                state.touch(key);
+            }
+            None => state.insert(
+                key.to_string(),
+                Entry {
+                    data: Value::String(new.into()).into(),
+                    expires_at: None,
+                },
+            ),
+        }
+        Ok(len)
     }
 
-    #[test]
-    fn test_get_missing_entry_in_db() {
-        let db = Db::new();
-        let key: &str = "Foo";
+    /// Adds `increment` to the number stored as a string at `key`, treating
+    /// a missing key as 0, and returns the result as it is now stored,
+    /// formatted by [`float::format`]. The TTL is kept.
+    pub fn incr_by_float(&self, key: &str, increment: f64) -> Result<Bytes, IncrError> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let current = match state.live_entry(key, now).map(|entry| entry.data.bytes()) {
+            None => None,
+            Some(Some(data)) => Some(data),
+            Some(None) => return Err(IncrError::WrongType),
+        };
+        let sum = add_float(current.as_deref(), increment)?;
+        match state.live_entry(key, now) {
+            Some(entry) => {
+                entry.data = Value::String(sum.clone()).into();
+                state.touch(key);
+            }
+            None => state.insert(
+                key.to_string(),
+                Entry {
+                    data: Value::String(sum.clone()).into(),
+                    expires_at: None,
+                },
+            ),
+        }
+        Ok(sum)
+    }
 
-        let value_got = db.get(key);
+    /// Removes the given keys and returns how many of them existed. Keys
+    /// that had already expired are removed but not counted.
+    pub fn del(&self, keys: &[String]) -> u64 {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let mut removed = 0;
+        for key in keys {
+            if let Some(entry) = state.remove(key) {
+                if !entry.is_expired(now) {
+                    removed += 1;
+                }
+            }
+        }
+        removed
+    }
 
-        assert!(value_got.is_none());
+    /// Moves the value and TTL of `src` to `dst`, replacing whatever `dst`
+    /// held, or with `nx` only if `dst` does not exist. Returns whether it
+    /// was moved. Renaming a key to itself changes nothing, and counts as
+    /// moved unless `nx` is given.
+    pub fn rename(&self, src: &str, dst: &str, nx: bool) -> Result<bool, NoSuchKey> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        if state.live_entry(src, now).is_none() {
+            return Err(NoSuchKey);
+        }
+        if src == dst {
+            return Ok(!nx);
+        }
+        if nx && state.live_entry(dst, now).is_some() {
+            return Ok(false);
+        }
+        let entry = state.remove(src).unwrap();
+        state.insert(dst.to_string(), entry);
+        Ok(true)
     }
 
-    #[test]
-    fn test_add_entry_to_db_with_expiry() {
-        let db = Db::new();
-        let key: &str = "Foo";
-        let value = Bytes::from("Bar");
-        let expiry = Duration::new(5, 0);
+    /// Sets `key` to expire at `expires_at` if it exists and `condition`
+    /// allows it, deleting it straight away if that time has passed.
+    /// Returns whether the key was changed.
+    pub fn expire(&self, key: &str, expires_at: Instant, condition: ExpireCondition) -> bool {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let Some(entry) = state.live_entry(key, now) else {
+            return false;
+        };
+        if !condition.allows(entry.expires_at, expires_at) {
+            return false;
+        }
 
-        db.set(key.to_string(), value.clone(), Some(expiry));
+        if expires_at <= now {
+            state.remove(key);
+        } else {
+            state.set_expiry(key, Some(expires_at));
+        }
+        true
+    }
 
-        let value_got = db.get(key);
+    /// Removes the TTL from `key`, returning whether it had one.
+    pub fn persist(&self, key: &str) -> bool {
+        let mut state = self.lock();
+        match state.live_entry(key, Instant::now()) {
+            Some(entry) if entry.expires_at.is_some() => {
+                state.set_expiry(key, None);
+                true
+            }
+            _ => false,
+        }
+    }
 
-        assert_eq!(value, value_got.unwrap());
+    /// Replaces the value of `key` with `value` only if it currently equals
+    /// `expected`. Returns the value held before the call, so the swap
+    /// happened exactly when the returned value equals `expected`. Missing
+    /// keys are never created.
+    pub fn compare_and_set(
+        &self,
+        key: &str,
+        expected: &[u8],
+        value: Bytes,
+        duration: Option<Duration>,
+    ) -> Result<Option<Bytes>, WrongType> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let Some(entry) = state.live_entry(key, now) else {
+            return Ok(None);
+        };
+
+        let Some(current) = entry.data.bytes() else {
+            return Err(WrongType);
+        };
+        if current == expected {
+            entry.data = Value::String(value).into();
+            state.set_expiry(key, duration.map(|d| now + d));
+        }
+        Ok(Some(current))
     }
 
-    #[test]
-    fn test_get_entry_in_db_that_has_expired() {
-        let db = Db::new();
-        let key: &str = "Foo";
-        let value = Bytes::from("Bar");
-        let expiry = Duration::new(0, 10);
+    /// Sets fields of the hash at `key`, creating it if needed, and returns
+    /// how many of them are new.
+    pub fn hset(&self, key: &str, fields: Vec<(String, Bytes)>) -> Result<u64, WrongType> {
+        let mut state = self.lock();
+        let entry = state.live_entry_or_insert(key, Instant::now(), || Value::Hash(HashMap::new()));
+        let Some(Value::Hash(hash)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        let mut added = 0;
+        for (field, value) in fields {
+            if hash.insert(field, value).is_none() {
+                added += 1;
+            }
+        }
+        state.touch(key);
+        Ok(added)
+    }
 
-        db.set(key.to_string(), value, Some(expiry));
+    /// Like [`Db::incr_by_float`], but for a field of the hash at `key`,
+    /// which is created if needed.
+    pub fn hincr_by_float(
+        &self,
+        key: &str,
+        field: &str,
+        increment: f64,
+    ) -> Result<Bytes, IncrError> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let current = match state
+            .live_entry(key, now)
+            .map(|entry| entry.data.value())
+            .as_deref()
+        {
+            None => None,
+            Some(Value::Hash(hash)) => hash.get(field).cloned(),
+            Some(_) => return Err(IncrError::WrongType),
+        };
+        let sum = add_float(current.as_deref(), increment)?;
+        let entry = state.live_entry_or_insert(key, now, || Value::Hash(HashMap::new()));
+        if let Some(Value::Hash(hash)) = entry.data.value_mut() {
+            hash.insert(field.to_string(), sum.clone());
+        }
+        state.touch(key);
+        Ok(sum)
+    }
 
-        let ten_millis = time::Duration::from_millis(10);
-        thread::sleep(ten_millis);
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<Bytes>, WrongType> {
+        self.with_hash(key, |hash| hash.get(field).cloned())
+            .map(Option::flatten)
+    }
 
-        let value_got = db.get(key);
+    /// Removes fields from the hash at `key` and returns how many existed.
+    /// The key is removed along with its last field.
+    pub fn hdel(&self, key: &str, fields: &[String]) -> Result<u64, WrongType> {
+        let mut state = self.lock();
+        let Some(entry) = state.live_entry(key, Instant::now()) else {
+            return Ok(0);
+        };
+        let Some(Value::Hash(hash)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        let removed = fields
+            .iter()
+            .filter(|field| hash.remove(field.as_str()).is_some())
+            .count();
+        if hash.is_empty() {
+            state.remove(key);
+        } else if removed > 0 {
+            state.touch(key);
+        }
+        Ok(removed as u64)
+    }
 
-        assert!(value_got.is_none());
+    /// Every field of the hash at `key` with its value, ordered by field.
+    pub fn hgetall(&self, key: &str) -> Result<Vec<(String, Bytes)>, WrongType> {
+        let fields = self.with_hash(key, |hash| {
+            let mut fields: Vec<_> = hash.iter().map(|(f, v)| (f.clone(), v.clone())).collect();
+            fields.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            fields
+        })?;
+        Ok(fields.unwrap_or_default())
+    }
+
+    /// Like [`Db::scan`], but over the fields of the hash at `key`, with
+    /// their values. The hash is walked in full on every call, but only
+    /// `count` fields are copied out.
+    pub fn hscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<(u64, Vec<(String, Bytes)>), WrongType> {
+        let step = self.with_hash(key, |hash| {
+            let fields = hash.iter().map(|field| (field.0.as_bytes(), field));
+            let (cursor, fields) = scan_elements(fields, cursor, count, pattern);
+            let fields = fields
+                .into_iter()
+                .map(|(_, (field, value))| (field.clone(), value.clone()))
+                .collect();
+            (cursor, fields)
+        })?;
+        Ok(step.unwrap_or_default())
+    }
+
+    pub fn hexists(&self, key: &str, field: &str) -> Result<bool, WrongType> {
+        self.with_hash(key, |hash| hash.contains_key(field))
+            .map(|exists| exists.unwrap_or(false))
+    }
+
+    pub fn hlen(&self, key: &str) -> Result<usize, WrongType> {
+        self.with_hash(key, |hash| hash.len())
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Pushes `values` one at a time onto the list at `key`, creating it if
+    /// needed, and returns its new length.
+    pub fn push(&self, key: &str, end: ListEnd, values: Vec<Bytes>) -> Result<usize, WrongType> {
+        let mut state = self.lock();
+        let entry =
+            state.live_entry_or_insert(key, Instant::now(), || Value::List(VecDeque::new()));
+        let Some(Value::List(list)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        for value in values {
+            match end {
+                ListEnd::Left => list.push_front(value),
+                ListEnd::Right => list.push_back(value),
+            }
+        }
+        let len = list.len();
+        state.touch(key);
+        self.shared.pushed.notify();
+        Ok(len)
+    }
+
+    /// Pops up to `count` elements from the list at `key`, or returns `None`
+    /// if there is no such list. The key is removed along with its last
+    /// element.
+    pub fn pop(
+        &self,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<Bytes>>, WrongType> {
+        let mut state = self.lock();
+        state.pop(key, end, count, Instant::now())
+    }
+
+    /// Pops one element from the first of `keys` that holds a non-empty
+    /// list, waiting for one to be pushed if none do. Returns the key
+    /// together with the element.
+    ///
+    /// Waiting does not need a particular runtime, and can be given a
+    /// timeout by dropping the future.
+    pub async fn blocking_pop(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+    ) -> Result<(String, Bytes), WrongType> {
+        self.blocking_pop_with(|| self.pop_first(keys, end)).await
+    }
+
+    /// Calls `pop` until it pops something, waiting for a push to any list
+    /// in between. This lets the caller pop under its own locks, which
+    /// must be taken before the keyspace's.
+    pub async fn blocking_pop_with<T, E>(
+        &self,
+        mut pop: impl FnMut() -> Result<Option<T>, E>,
+    ) -> Result<T, E> {
+        loop {
+            // Taken before trying, so a push the attempt misses always
+            // moves the epoch on.
+            let epoch = self.shared.pushed.epoch();
+            if let Some(popped) = pop()? {
+                return Ok(popped);
+            }
+            self.shared.pushed.changed(epoch).await;
+        }
+    }
+
+    /// Pops like [`Db::blocking_pop`], but without waiting: `None` if every
+    /// list is empty.
+    pub fn pop_first(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+    ) -> Result<Option<(String, Bytes)>, WrongType> {
+        self.lock().pop_first(keys, end, Instant::now())
+    }
+
+    pub fn llen(&self, key: &str) -> Result<usize, WrongType> {
+        self.with_list(key, |list| list.len())
+            .map(Option::unwrap_or_default)
+    }
+
+    /// The elements of the list at `key` between the inclusive indexes
+    /// `start` and `stop`, which count from the end when negative.
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<Bytes>, WrongType> {
+        let elements = self.with_list(key, |list| {
+            match range::normalize(start, stop, list.len()) {
+                Some((from, to)) => list.range(from..=to).cloned().collect(),
+                None => Vec::new(),
+            }
+        })?;
+        Ok(elements.unwrap_or_default())
+    }
+
+    /// Runs `f` on the list at `key`, giving `None` if the key is missing.
+    fn with_list<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&VecDeque<Bytes>) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        let mut state = self.lock();
+        match state
+            .live_entry(key, Instant::now())
+            .map(|entry| entry.data.value())
+            .as_deref()
+        {
+            None => Ok(None),
+            Some(Value::List(list)) => Ok(Some(f(list))),
+            Some(_) => Err(WrongType),
+        }
+    }
+
+    /// Adds members to the set at `key`, creating it if needed, and returns
+    /// how many of them are new.
+    pub fn sadd(&self, key: &str, members: Vec<Bytes>) -> Result<u64, WrongType> {
+        let mut state = self.lock();
+        let entry = state.live_entry_or_insert(key, Instant::now(), || Value::Set(HashSet::new()));
+        let Some(Value::Set(set)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        let added = members
+            .into_iter()
+            .filter(|m| set.insert(m.clone()))
+            .count();
+        if added > 0 {
+            state.touch(key);
+        }
+        Ok(added as u64)
+    }
+
+    /// Removes members from the set at `key` and returns how many were in
+    /// it. The key is removed along with its last member.
+    pub fn srem(&self, key: &str, members: &[Bytes]) -> Result<u64, WrongType> {
+        let mut state = self.lock();
+        let Some(entry) = state.live_entry(key, Instant::now()) else {
+            return Ok(0);
+        };
+        let Some(Value::Set(set)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        let removed = members.iter().filter(|m| set.remove(*m)).count();
+        if set.is_empty() {
+            state.remove(key);
+        } else if removed > 0 {
+            state.touch(key);
+        }
+        Ok(removed as u64)
+    }
+
+    /// The members of the set at `key`, in byte order.
+    pub fn smembers(&self, key: &str) -> Result<Vec<Bytes>, WrongType> {
+        let members = self.with_set(key, |set| sorted(set.iter().cloned()))?;
+        Ok(members.unwrap_or_default())
+    }
+
+    pub fn sismember(&self, key: &str, member: &[u8]) -> Result<bool, WrongType> {
+        self.with_set(key, |set| set.contains(member))
+            .map(|found| found.unwrap_or(false))
+    }
+
+    pub fn scard(&self, key: &str) -> Result<usize, WrongType> {
+        self.with_set(key, |set| set.len())
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Like [`Db::hscan`], but over the members of the set at `key`.
+    pub fn sscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<(u64, Vec<Bytes>), WrongType> {
+        let step = self.with_set(key, |set| {
+            let members = set.iter().map(|member| (&member[..], member));
+            let (cursor, members) = scan_elements(members, cursor, count, pattern);
+            (
+                cursor,
+                members.into_iter().map(|(_, m)| m.clone()).collect(),
+            )
+        })?;
+        Ok(step.unwrap_or_default())
+    }
+
+    /// Combines the sets at `keys`, treating missing keys as empty sets,
+    /// and returns the members of the result in byte order.
+    pub fn combine_sets(&self, op: SetOperation, keys: &[String]) -> Result<Vec<Bytes>, WrongType> {
+        let mut state = self.lock();
+        let combined = state.combine_sets(op, keys, Instant::now())?;
+        Ok(sorted(combined))
+    }
+
+    /// Like [`Db::combine_sets`], but stores the result as a set at `dest`,
+    /// replacing whatever was there, and returns its size. An empty result
+    /// deletes `dest`.
+    pub fn store_combined_sets(
+        &self,
+        op: SetOperation,
+        dest: &str,
+        keys: &[String],
+    ) -> Result<usize, WrongType> {
+        let mut state = self.lock();
+        let combined = state.combine_sets(op, keys, Instant::now())?;
+        let len = combined.len();
+        if combined.is_empty() {
+            state.remove(dest);
+        } else {
+            state.insert(
+                dest.to_string(),
+                Entry {
+                    data: Value::Set(combined).into(),
+                    expires_at: None,
+                },
+            );
+        }
+        Ok(len)
+    }
+
+    /// Adds members to the sorted set at `key`, or updates their scores, as
+    /// `options` allow. Returns how many were added, or with `ch` also how
+    /// many were updated. The key is only created if a member is added,
+    /// and only counts as written if a member is added or updated.
+    pub fn zadd(
+        &self,
+        key: &str,
+        members: Vec<(f64, Bytes)>,
+        options: AddOptions,
+    ) -> Result<u64, WrongType> {
+        let add = |set: &mut SortedSet| {
+            let (mut added, mut updated) = (0, 0);
+            for (score, member) in members {
+                match set.add(member, score, options) {
+                    Some(zset::Change::Added) => added += 1,
+                    Some(zset::Change::Updated) => updated += 1,
+                    None => {}
+                }
+            }
+            (added, updated)
+        };
+        let mut state = self.lock();
+        let current = state.live_entry(key, Instant::now());
+        let (added, updated) = match current.map(|entry| entry.data.value_mut()) {
+            Some(Some(Value::SortedSet(set))) => {
+                let counts = add(set);
+                if counts != (0, 0) {
+                    state.touch(key);
+                }
+                counts
+            }
+            Some(_) => return Err(WrongType),
+            None if options.xx => (0, 0),
+            None => {
+                let mut set = SortedSet::new();
+                let counts = add(&mut set);
+                if !set.is_empty() {
+                    let entry = Entry {
+                        data: Value::SortedSet(set).into(),
+                        expires_at: None,
+                    };
+                    state.insert(key.to_string(), entry);
+                }
+                counts
+            }
+        };
+        Ok(added + if options.ch { updated } else { 0 })
+    }
+
+    /// Removes members from the sorted set at `key` and returns how many
+    /// were in it. The key is removed along with its last member.
+    pub fn zrem(&self, key: &str, members: &[Bytes]) -> Result<u64, WrongType> {
+        let mut state = self.lock();
+        let Some(entry) = state.live_entry(key, Instant::now()) else {
+            return Ok(0);
+        };
+        let Some(Value::SortedSet(set)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        let removed = members.iter().filter(|m| set.remove(m)).count();
+        if set.is_empty() {
+            state.remove(key);
+        } else if removed > 0 {
+            state.touch(key);
+        }
+        Ok(removed as u64)
+    }
+
+    pub fn zscore(&self, key: &str, member: &[u8]) -> Result<Option<f64>, WrongType> {
+        self.with_sorted_set(key, |set| set.score(member))
+            .map(Option::flatten)
+    }
+
+    /// Like [`Db::hscan`], but over the members of the sorted set at `key`,
+    /// with their scores.
+    pub fn zscan(
+        &self,
+        key: &str,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<(u64, Vec<(Bytes, f64)>), WrongType> {
+        let step = self.with_sorted_set(key, |set| {
+            let members = set
+                .iter()
+                .map(|(member, score)| (&member[..], (member, score)));
+            let (cursor, members) = scan_elements(members, cursor, count, pattern);
+            let members = members
+                .into_iter()
+                .map(|(_, (member, score))| (member.clone(), score))
+                .collect();
+            (cursor, members)
+        })?;
+        Ok(step.unwrap_or_default())
+    }
+
+    /// Members of the sorted set at `key` ranked `start..=stop`, with their
+    /// scores.
+    pub fn zrange(&self, key: &str, start: i64, stop: i64) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        let members = self.with_sorted_set(key, |set| set.range(start, stop))?;
+        Ok(members.unwrap_or_default())
+    }
+
+    /// Members of the sorted set at `key` scored between `min` and `max`,
+    /// with their scores, skipping `offset` of them and returning at most
+    /// `count`.
+    pub fn zrange_by_score(
+        &self,
+        key: &str,
+        min: ScoreBound,
+        max: ScoreBound,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Result<Vec<(Bytes, f64)>, WrongType> {
+        let members = self.with_sorted_set(key, |set| {
+            set.range_by_score(min, max)
+                .skip(offset)
+                .take(count.unwrap_or(usize::MAX))
+                .map(|(member, score)| (member.clone(), score))
+                .collect()
+        })?;
+        Ok(members.unwrap_or_default())
+    }
+
+    /// Runs `f` on the sorted set at `key`, giving `None` if the key is
+    /// missing.
+    fn with_sorted_set<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&SortedSet) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        let mut state = self.lock();
+        match state
+            .live_entry(key, Instant::now())
+            .map(|entry| entry.data.value())
+            .as_deref()
+        {
+            None => Ok(None),
+            Some(Value::SortedSet(set)) => Ok(Some(f(set))),
+            Some(_) => Err(WrongType),
+        }
+    }
+
+    /// Runs `f` on the set at `key`, giving `None` if the key is missing.
+    fn with_set<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&HashSet<Bytes>) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        let mut state = self.lock();
+        match state
+            .live_entry(key, Instant::now())
+            .map(|entry| entry.data.value())
+            .as_deref()
+        {
+            None => Ok(None),
+            Some(Value::Set(set)) => Ok(Some(f(set))),
+            Some(_) => Err(WrongType),
+        }
+    }
+
+    /// Runs `f` on the hash at `key`, giving `None` if the key is missing.
+    fn with_hash<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&HashMap<String, Bytes>) -> T,
+    ) -> Result<Option<T>, WrongType> {
+        let mut state = self.lock();
+        match state
+            .live_entry(key, Instant::now())
+            .map(|entry| entry.data.value())
+            .as_deref()
+        {
+            None => Ok(None),
+            Some(Value::Hash(hash)) => Ok(Some(f(hash))),
+            Some(_) => Err(WrongType),
+        }
+    }
+
+    /// A copy of one live key, like a single entry of [`Db::snapshot`].
+    pub fn key_snapshot(&self, key: &str) -> Option<KeySnapshot> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let entry = state.live_entry(key, now)?;
+        Some(KeySnapshot {
+            key: key.to_string(),
+            value: entry.data.value().into_owned(),
+            ttl: entry.expires_at.map(|expiry| expiry - now),
+        })
+    }
+
+    /// When `key` expires, `None` if it has no TTL or does not exist.
+    pub fn expires_at(&self, key: &str) -> Option<Instant> {
+        let mut state = self.lock();
+        state.live_entry(key, Instant::now())?.expires_at
+    }
+
+    /// Starts watching `key` for writes. Any command that changes its value
+    /// or TTL, deletes it, or creates it counts, and so does removing it
+    /// once it has expired.
+    pub fn watch(&self, key: &str) -> KeyWatch {
+        let mut state = self.lock();
+        let signal = state.watched.entry(key.to_string()).or_default().clone();
+        // Taken under the state lock, so any write after this call moves
+        // the epoch on.
+        let epoch = signal.epoch();
+        KeyWatch {
+            db: self.clone(),
+            key: key.to_string(),
+            signal,
+            epoch,
+        }
+    }
+
+    /// Removes every key. Anyone watching a key sees it written.
+    pub fn flush(&self) -> Flushed {
+        self.lock().flush()
+    }
+
+    /// Starts recording every write, numbering them from `first_offset`
+    /// and keeping the most recent `capacity` for [`Db::changes_since`].
+    /// Does nothing if changes are already being recorded.
+    pub fn record_changes(&self, capacity: usize, first_offset: u64) {
+        let mut state = self.lock();
+        state.changes.get_or_insert_with(|| ChangeLog {
+            next_offset: first_offset,
+            capacity: capacity.max(1),
+            recent: VecDeque::new(),
+            written: Arc::default(),
+        });
+    }
+
+    /// The offset the next write will get, `None` if changes are not being
+    /// recorded.
+    pub fn next_change_offset(&self) -> Option<u64> {
+        let state = self.lock();
+        state.changes.as_ref().map(|log| log.next_offset)
+    }
+
+    /// Up to `max` recorded writes, starting at `offset`, with the values
+    /// the keys hold now. Empty if there are none yet, or if changes are
+    /// not being recorded.
+    pub fn changes_since(&self, offset: u64, max: usize) -> Result<Vec<Change>, ChangesLost> {
+        let now = Instant::now();
+        let state = self.lock();
+        let Some(log) = &state.changes else {
+            return Ok(Vec::new());
+        };
+        let oldest = log.recent.front().map_or(log.next_offset, |(o, _)| *o);
+        if offset < oldest {
+            return Err(ChangesLost { oldest });
+        }
+        let skip = (offset - oldest) as usize;
+        Ok(log
+            .recent
+            .iter()
+            .skip(skip)
+            .take(max)
+            .map(|(offset, key)| {
+                let entry = key
+                    .as_deref()
+                    .and_then(|key| state.entries.get(key))
+                    .filter(|e| !e.is_expired(now));
+                Change {
+                    offset: *offset,
+                    key: key.clone(),
+                    value: entry.map(|e| e.data.value().into_owned()),
+                    ttl: entry.and_then(|e| e.expires_at).map(|expiry| expiry - now),
+                }
+            })
+            .collect())
+    }
+
+    /// Completes once a write with an offset of at least `offset` has been
+    /// recorded. Never completes if changes are not being recorded.
+    pub async fn wait_for_change(&self, offset: u64) {
+        loop {
+            let waiting = {
+                let state = self.lock();
+                match &state.changes {
+                    Some(log) if log.next_offset > offset => return,
+                    // Taken under the state lock, so a write after the
+                    // check above always moves the epoch on.
+                    Some(log) => Some((log.written.clone(), log.written.epoch())),
+                    None => None,
+                }
+            };
+            match waiting {
+                Some((written, epoch)) => written.changed(epoch).await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    /// Removes up to `limit` keys whose TTL has passed, earliest first, and
+    /// returns how many were removed. Expired keys are never visible, but
+    /// their memory is only reclaimed when they are either looked up or
+    /// removed here, so the server calls this periodically.
+    pub fn purge_expired(&self, limit: usize) -> usize {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let mut removed = 0;
+        while removed < limit {
+            match state.expirations.first() {
+                Some((expiry, _)) if *expiry < now => {}
+                _ => break,
+            }
+            let (_, key) = state.expirations.pop_first().unwrap();
+            state.remove(&key);
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Starts tracing `key` for `duration`, or stops if `None`.
+    pub fn trace_key(&self, key: &str, duration: Option<Duration>) {
+        let mut traced = self.shared.traced.lock().unwrap();
+        match duration.and_then(|d| Instant::now().checked_add(d)) {
+            Some(until) => traced.insert(key.to_string(), until),
+            None => traced.remove(key),
+        };
+    }
+
+    /// Those of `keys` that are currently being traced.
+    pub fn traced_keys(&self, keys: &[String]) -> Vec<String> {
+        let mut traced = self.shared.traced.lock().unwrap();
+        if traced.is_empty() {
+            return Vec::new();
+        }
+        let now = Instant::now();
+        traced.retain(|_, until| *until >= now);
+        let mut found = Vec::new();
+        for key in keys {
+            if traced.contains_key(key) && !found.contains(key) {
+                found.push(key.clone());
+            }
+        }
+        found
+    }
+
+    /// Fills the store with `count` keys named `{prefix}:{n}` holding
+    /// `value:{n}`, zero-padded or truncated to `size` bytes when given.
+    /// Existing keys are left untouched. Returns the number of keys added.
+    pub fn populate(&self, count: u64, prefix: &str, size: Option<usize>) -> u64 {
+        let now = Instant::now();
+        let mut state = self.lock();
+        let mut added = 0;
+        for n in 0..count {
+            let key = format!("{prefix}:{n}");
+            if state.live_entry(&key, now).is_some() {
+                continue;
+            }
+
+            let mut value = format!("value:{n}").into_bytes();
+            if let Some(size) = size {
+                value.resize(size, 0);
+            }
+            state.insert(
+                key,
+                Entry {
+                    data: Value::from(value).into(),
+                    expires_at: None,
+                },
+            );
+            added += 1;
+        }
+        added
+    }
+
+    /// Copies every live key out of the store, walking it in scan order
+    /// and taking the lock for one batch of keys at a time, so writers only
+    /// wait for a batch to be copied. As with SCAN, a key that exists for
+    /// the whole walk is copied exactly once, each key is copied as it was
+    /// at one moment, and a key written meanwhile may be copied before or
+    /// after the write. Strings are reference counted, but collections are
+    /// copied whole, so one large collection still holds the lock for as
+    /// long as that takes.
+    pub fn snapshot(&self) -> Vec<KeySnapshot> {
+        let mut snapshot = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let now = Instant::now();
+            let state = self.lock();
+            let keys = state
+                .scan_order
+                .range((cursor, String::new())..)
+                .map(|(hash, key)| (*hash, key));
+            let (next, keys) = scan_batch(keys, SNAPSHOT_BATCH);
+            snapshot.extend(keys.into_iter().filter_map(|key| {
+                let entry = &state.entries[key.as_str()];
+                (!entry.is_expired(now)).then(|| KeySnapshot {
+                    key: key.clone(),
+                    value: entry.data.value().into_owned(),
+                    ttl: entry.expires_at.map(|expiry| expiry - now),
+                })
+            }));
+            if next == 0 {
+                return snapshot;
+            }
+            cursor = next;
+        }
+    }
+}
+
+impl State {
+    /// Looks up `key`, removing it first if it has expired.
+    fn live_entry(&mut self, key: &str, now: Instant) -> Option<&mut Entry> {
+        if self.entries.get(key)?.is_expired(now) {
+            self.remove(key);
+            return None;
+        }
+        self.entries.get_mut(key)
+    }
+
+    /// Looks up `key` like `live_entry`, first storing `default()` without
+    /// a TTL under it if it is missing.
+    fn live_entry_or_insert(
+        &mut self,
+        key: &str,
+        now: Instant,
+        default: impl FnOnce() -> Value,
+    ) -> &mut Entry {
+        if self.live_entry(key, now).is_none() {
+            self.insert(
+                key.to_string(),
+                Entry {
+                    data: default().into(),
+                    expires_at: None,
+                },
+            );
+        }
+        self.entries.get_mut(key).unwrap()
+    }
+
+    fn pop(
+        &mut self,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+        now: Instant,
+    ) -> Result<Option<Vec<Bytes>>, WrongType> {
+        let Some(entry) = self.live_entry(key, now) else {
+            return Ok(None);
+        };
+        let Some(Value::List(list)) = entry.data.value_mut() else {
+            return Err(WrongType);
+        };
+        let count = count.min(list.len());
+        let popped = match end {
+            ListEnd::Left => list.drain(..count).collect(),
+            ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+        };
+        if list.is_empty() {
+            self.remove(key);
+        } else if count > 0 {
+            self.touch(key);
+        }
+        Ok(Some(popped))
+    }
+
+    /// Pops one element from the first of `keys` holding a list.
+    fn pop_first(
+        &mut self,
+        keys: &[String],
+        end: ListEnd,
+        now: Instant,
+    ) -> Result<Option<(String, Bytes)>, WrongType> {
+        for key in keys {
+            if let Some(mut popped) = self.pop(key, end, 1, now)? {
+                if !popped.is_empty() {
+                    return Ok(Some((key.clone(), popped.remove(0))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn combine_sets(
+        &mut self,
+        op: SetOperation,
+        keys: &[String],
+        now: Instant,
+    ) -> Result<HashSet<Bytes>, WrongType> {
+        // Every key is type checked, even once the result can't change.
+        let mut sets = Vec::with_capacity(keys.len());
+        for key in keys {
+            match self
+                .live_entry(key, now)
+                .map(|entry| entry.data.value())
+                .as_deref()
+            {
+                None => sets.push(None),
+                Some(Value::Set(set)) => sets.push(Some(set.clone())),
+                Some(_) => return Err(WrongType),
+            }
+        }
+
+        let mut sets = sets.into_iter().map(Option::unwrap_or_default);
+        let mut combined = sets.next().unwrap_or_default();
+        for set in sets {
+            match op {
+                SetOperation::Inter => combined.retain(|m| set.contains(m)),
+                SetOperation::Union => combined.extend(set),
+                SetOperation::Diff => combined.retain(|m| !set.contains(m)),
+            }
+        }
+        Ok(combined)
+    }
+
+    fn insert(&mut self, key: String, entry: Entry) {
+        self.touch(&key);
+        if let Some(expiry) = entry.expires_at {
+            self.expirations.insert((expiry, key.clone()));
+        }
+        match self.entries.insert(key.clone(), entry) {
+            Some(old) => {
+                if let Some(expiry) = old.expires_at {
+                    self.expirations.remove(&(expiry, key));
+                }
+            }
+            None => {
+                self.scan_order
+                    .insert((hasher::scan_hash(key.as_bytes()), key));
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        if let Some(expiry) = entry.expires_at {
+            self.expirations.remove(&(expiry, key.to_string()));
+        }
+        self.scan_order
+            .remove(&(hasher::scan_hash(key.as_bytes()), key.to_string()));
+        self.touch(key);
+        Some(entry)
+    }
+
+    /// Replaces the expiry of an existing key.
+    fn set_expiry(&mut self, key: &str, expires_at: Option<Instant>) {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return;
+        };
+        if let Some(old) = std::mem::replace(&mut entry.expires_at, expires_at) {
+            self.expirations.remove(&(old, key.to_string()));
+        }
+        if let Some(expiry) = expires_at {
+            self.expirations.insert((expiry, key.to_string()));
+        }
+        self.touch(key);
+    }
+
+    /// The number of keys that have not expired, counting the expired ones
+    /// still stored from the front of the expiry index.
+    fn live_len(&self, now: Instant) -> usize {
+        let expired = self.expirations.range(..(now, String::new())).count();
+        self.entries.len() - expired
+    }
+
+    /// Tells anyone watching `key` that it was written, and records the
+    /// write if changes are being recorded.
+    fn touch(&mut self, key: &str) {
+        if let Some(signal) = self.watched.get(key) {
+            signal.notify();
+        }
+        if let Some(log) = &mut self.changes {
+            log.push(Some(key.to_string()));
+        }
+    }
+
+    /// Removes every key, returning them to be dropped by the caller.
+    fn flush(&mut self) -> Flushed {
+        let hasher = self.entries.hasher().clone();
+        let flushed = Flushed {
+            _entries: std::mem::replace(&mut self.entries, HashMap::with_hasher(hasher)),
+            _expirations: std::mem::take(&mut self.expirations),
+            _scan_order: std::mem::take(&mut self.scan_order),
+        };
+        self.replaced();
+        flushed
+    }
+
+    /// Tells everyone watching a key, and the change log, that every key
+    /// may have been replaced.
+    fn replaced(&mut self) {
+        for signal in self.watched.values() {
+            signal.notify();
+        }
+        if let Some(log) = &mut self.changes {
+            log.push(None);
+            for key in self.entries.keys() {
+                log.push(Some(key.clone()));
+            }
+        }
+    }
+}
+
+impl ChangeLog {
+    fn push(&mut self, key: Option<String>) {
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((self.next_offset, key));
+        self.next_offset += 1;
+        self.written.notify();
+    }
+}
+
+/// Adds `increment` to the number in `data`, where a missing value counts
+/// as 0, and formats the sum.
+fn add_float(data: Option<&[u8]>, increment: f64) -> Result<Bytes, IncrError> {
+    let current = match data {
+        None => 0.0,
+        Some(data) => float::parse(data).ok_or(IncrError::NotFloat)?,
+    };
+    let sum = current + increment;
+    if !sum.is_finite() {
+        return Err(IncrError::NotFinite);
+    }
+    Ok(Bytes::from(float::format(sum)))
+}
+
+fn sorted(members: impl IntoIterator<Item = Bytes>) -> Vec<Bytes> {
+    let mut members: Vec<_> = members.into_iter().collect();
+    members.sort_unstable();
+    members
+}
+
+/// Takes the first `count` of `items`, which are in scan order starting at
+/// the cursor, plus any sharing a position with the last of them, and
+/// returns the cursor that follows them.
+fn scan_batch<T>(items: impl Iterator<Item = (u64, T)>, count: usize) -> (u64, Vec<T>) {
+    let mut items = items.peekable();
+    let mut batch = Vec::new();
+    let mut last = None;
+    while let Some((hash, _)) = items.peek() {
+        if batch.len() >= count && last != Some(*hash) {
+            break;
+        }
+        let (hash, item) = items.next().unwrap();
+        batch.push(item);
+        last = Some(hash);
+    }
+    // Anything left has a later position, so `last + 1` cannot overflow.
+    let cursor = match (items.peek(), last) {
+        (Some(_), Some(last)) => last + 1,
+        _ => 0,
+    };
+    (cursor, batch)
+}
+
+/// One step of walking the elements of a container, which have no index
+/// in scan order and so are sorted into it on every call. Elements are
+/// keyed by the bytes `pattern` is matched against.
+fn scan_elements<'a, T>(
+    elements: impl Iterator<Item = (&'a [u8], T)>,
+    cursor: u64,
+    count: usize,
+    pattern: Option<&[u8]>,
+) -> (u64, Vec<(&'a [u8], T)>) {
+    let mut elements: Vec<_> = elements
+        .map(|(name, element)| (hasher::scan_hash(name), (name, element)))
+        .filter(|(hash, _)| *hash >= cursor)
+        .collect();
+    elements.sort_unstable_by_key(|(hash, _)| *hash);
+    let (cursor, mut batch) = scan_batch(elements.into_iter(), count);
+    if let Some(pattern) = pattern {
+        batch.retain(|(name, _)| glob::matches(pattern, name));
+    }
+    (cursor, batch)
+}
+
+impl KeyWatch {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Whether the key has been written since the watch started.
+    pub fn is_modified(&self) -> bool {
+        self.signal.epoch() != self.epoch
+    }
+
+    /// Completes once the key has been written since the watch started.
+    pub async fn modified(&self) {
+        self.signal.changed(self.epoch).await
+    }
+}
+
+impl Drop for KeyWatch {
+    fn drop(&mut self) {
+        let mut state = self.db.lock();
+        // One reference is the keyspace's own; watches are only started
+        // under the lock, so no other can appear meanwhile.
+        if Arc::strong_count(&self.signal) == 2 {
+            state.watched.remove(&self.key);
+        }
+    }
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expiry| expiry < now)
+    }
+}
+
+/// The longest string an entry holds inline, the limit Redis has for its
+/// `embstr` encoding too.
+const INLINE_LEN: usize = 44;
+
+/// How an entry holds its value. Strings of up to [`INLINE_LEN`] bytes,
+/// which most are, are copied into the entry instead of keeping an
+/// allocation of their own, and copied out again when read.
+#[derive(Debug)]
+enum Stored {
+    Inline { len: u8, data: [u8; INLINE_LEN] },
+    Value(Value),
+}
+
+impl Stored {
+    /// The string held, or `None` if the value is of another type.
+    fn bytes(&self) -> Option<Bytes> {
+        match self {
+            Stored::Inline { len, data } => Some(Bytes::copy_from_slice(&data[..*len as usize])),
+            Stored::Value(value) => value.as_bytes().cloned(),
+        }
+    }
+
+    fn value(&self) -> Cow<'_, Value> {
+        match self {
+            Stored::Inline { .. } => Cow::Owned(Value::String(self.bytes().unwrap())),
+            Stored::Value(value) => Cow::Borrowed(value),
+        }
+    }
+
+    /// The value held, unless it is an inline string. Strings are only
+    /// ever replaced whole, so this is for changing collections in place.
+    fn value_mut(&mut self) -> Option<&mut Value> {
+        match self {
+            Stored::Inline { .. } => None,
+            Stored::Value(value) => Some(value),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Stored::Inline { .. } => "string",
+            Stored::Value(value) => value.type_name(),
+        }
+    }
+
+    fn encoding(&self) -> &'static str {
+        match self {
+            Stored::Inline { .. } => "embstr",
+            Stored::Value(value) => value.encoding(),
+        }
+    }
+}
+
+impl From<Value> for Stored {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::String(data) if data.len() <= INLINE_LEN => {
+                let mut inline = [0; INLINE_LEN];
+                inline[..data.len()].copy_from_slice(&data);
+                Stored::Inline {
+                    len: data.len() as u8,
+                    data: inline,
+                }
+            }
+            value => Stored::Value(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::{thread, time};
+
+    /// Counts the allocations each thread currently holds, so tests can see
+    /// what the keyspace keeps on the heap.
+    struct CountingAlloc;
+
+    thread_local! {
+        static HELD: Cell<isize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            HELD.with(|held| held.set(held.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            HELD.with(|held| held.set(held.get() - 1));
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    /// How many more allocations this thread holds after running `f`.
+    fn allocations_held(f: impl FnOnce()) -> isize {
+        let before = HELD.with(Cell::get);
+        f();
+        HELD.with(Cell::get) - before
+    }
+
+    #[test]
+    fn test_create_new_db() {
+        let _ = Db::new();
+    }
+
+    #[test]
+    fn test_set_and_get_key_in_db_with_fx_hasher() {
+        let db = Db::with_key_hasher(KeyHasher::Fx);
+        let value = Bytes::from("Bar");
+        db.set("Foo".to_string(), value.clone(), None);
+
+        assert_eq!(value, db.get("Foo").unwrap());
+    }
+
+    #[test]
+    fn test_set_and_get_key_in_db() {
+        let db = Db::new();
+        let key: &str = "Foo";
+        let value = Bytes::from("Bar");
+        db.set(key.to_string(), value.clone(), None);
+
+        let value_got = db.get(key);
+
+        assert_eq!(value, value_got.unwrap());
+    }
+
+    #[test]
+    fn test_get_value_in_db() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        assert_eq!(db.get_value("Foo").unwrap(), Value::from("Bar"));
+        assert!(db.get_value("Baz").is_none());
+    }
+
+    #[test]
+    fn test_short_strings_are_held_inline() {
+        let db = Db::new();
+        let short = Bytes::from(vec![b'a'; INLINE_LEN]);
+        let long = Bytes::from(vec![b'b'; INLINE_LEN + 1]);
+        db.set("short".to_string(), short.clone(), None);
+        db.set("long".to_string(), long.clone(), None);
+        db.set("empty".to_string(), Bytes::new(), None);
+
+        assert_eq!(db.encoding("short"), Some("embstr"));
+        assert_eq!(db.encoding("long"), Some("raw"));
+        assert_eq!(db.get("short"), Some(short.clone()));
+        assert_eq!(db.get("long"), Some(long));
+        assert_eq!(db.get("empty"), Some(Bytes::new()));
+        assert_eq!(db.get_value("short"), Some(Value::String(short)));
+        assert_eq!(db.type_name("short"), Some("string"));
+        assert_eq!(db.sadd("short", vec![Bytes::from("m")]), Err(WrongType));
+
+        assert_eq!(db.set_range("short", INLINE_LEN, b"c"), Ok(INLINE_LEN + 1));
+        assert_eq!(db.encoding("short"), Some("raw"));
+    }
+
+    #[test]
+    fn test_short_strings_do_not_allocate() {
+        let held = |value: &[u8]| {
+            let db = Db::new();
+            let pairs: Vec<_> = (0..100)
+                .map(|n| (format!("key:{n}"), Bytes::copy_from_slice(value)))
+                .collect();
+            allocations_held(|| db.mset(pairs))
+        };
+
+        // The long values keep the allocations they came in, one each,
+        // while the short ones are copied into their entries and freed.
+        assert_eq!(held(&[b'x'; INLINE_LEN + 1]) - held(&[b'x'; 8]), 100);
+    }
+
+    #[test]
+    fn test_inline_strings_do_not_grow_entries() {
+        assert_eq!(std::mem::size_of::<Stored>(), std::mem::size_of::<Value>());
+    }
+
+    #[test]
+    fn test_type_and_encoding_of_key() {
+        let db = Db::new();
+        db.set("s".to_string(), Bytes::from("v"), None);
+        db.zadd("z", vec![(1.0, Bytes::from("m"))], AddOptions::default())
+            .unwrap();
+
+        assert_eq!(db.type_name("s"), Some("string"));
+        assert_eq!(db.type_name("z"), Some("zset"));
+        assert_eq!(db.encoding("z"), Some("btree"));
+        assert_eq!(db.type_name("missing"), None);
+        assert_eq!(db.encoding("missing"), None);
+    }
+
+    #[test]
+    fn test_get_missing_entry_in_db() {
+        let db = Db::new();
+        let key: &str = "Foo";
+
+        let value_got = db.get(key);
+
+        assert!(value_got.is_none());
+    }
+
+    #[test]
+    fn test_add_entry_to_db_with_expiry() {
+        let db = Db::new();
+        let key: &str = "Foo";
+        let value = Bytes::from("Bar");
+        let expiry = Duration::new(5, 0);
+
+        db.set(key.to_string(), value.clone(), Some(expiry));
+
+        let value_got = db.get(key);
+
+        assert_eq!(value, value_got.unwrap());
+    }
+
+    #[test]
+    fn test_get_entry_in_db_that_has_expired() {
+        let db = Db::new();
+        let key: &str = "Foo";
+        let value = Bytes::from("Bar");
+        let expiry = Duration::new(0, 10);
+
+        db.set(key.to_string(), value, Some(expiry));
+
+        let ten_millis = time::Duration::from_millis(10);
+        thread::sleep(ten_millis);
+
+        let value_got = db.get(key);
+
+        assert!(value_got.is_none());
+    }
+
+    #[test]
+    fn test_exists_in_db() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+        db.set(
+            "Gone".to_string(),
+            Bytes::from("Soon"),
+            Some(Duration::new(0, 10)),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert!(db.exists("Foo"));
+        assert!(!db.exists("Gone"));
+        assert!(!db.exists("Baz"));
+    }
+
+    #[test]
+    fn test_keys_matching_pattern() {
+        let db = Db::new();
+        for key in ["user:1", "user:2", "users", "order:1"] {
+            db.set(key.to_string(), Bytes::from("v"), None);
+        }
+        db.set("user:3".to_string(), Bytes::from("v"), Some(Duration::ZERO));
+        thread::sleep(time::Duration::from_millis(1));
+
+        assert_eq!(db.keys(b"user:*"), ["user:1", "user:2"]);
+        assert_eq!(db.keys(b"user?"), ["users"]);
+        assert_eq!(db.keys(b"*").len(), 4);
+        assert!(db.keys(b"nothing*").is_empty());
+    }
+
+    #[test]
+    fn test_scan_batches_stay_small_for_keys_built_to_collide() {
+        let db = Db::new();
+        // Runs of NUL bytes all hash to 0 under an unseeded FxHash.
+        for len in 1..=50 {
+            db.set("\0".repeat(len), Bytes::from("v"), None);
+        }
+
+        let (cursor, keys) = db.scan(0, 10, None, None);
+        assert_ne!(cursor, 0);
+        assert_eq!(keys.len(), 10);
+    }
+
+    #[test]
+    fn test_scan_returns_lasting_keys_once_while_keyspace_changes() {
+        let db = Db::new();
+        db.populate(200, "key", None);
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        let mut step = 0;
+        loop {
+            let (next, keys) = db.scan(cursor, 7, None, None);
+            seen.extend(keys);
+            // Churn that the walk should see through: new keys, and old
+            // ones removed, some of them before the walk reached them.
+            db.set(format!("new:{step}"), Bytes::from("v"), None);
+            db.del(&[format!("key:{}", 150 + step % 50)]);
+            step += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        for n in 0..150 {
+            let key = format!("key:{n}");
+            assert_eq!(seen.iter().filter(|k| **k == key).count(), 1, "{key}");
+        }
+        assert!(seen.len() < 200 + step);
+    }
+
+    #[test]
+    fn test_scan_filters() {
+        let db = Db::new();
+        db.populate(20, "key", None);
+        db.sadd("set", vec![Bytes::from("m")]).unwrap();
+        db.set("gone".to_string(), Bytes::from("v"), Some(Duration::ZERO));
+        thread::sleep(time::Duration::from_millis(1));
+
+        let (cursor, mut keys) = db.scan(0, 1000, Some(b"key:1*"), None);
+        keys.sort();
+        assert_eq!(cursor, 0);
+        assert_eq!(keys.len(), 11);
+        assert_eq!(
+            db.scan(0, 1000, None, Some("set")),
+            (0, vec!["set".to_string()])
+        );
+        assert_eq!(db.scan(0, 1000, None, None).1.len(), 21);
+        assert_eq!(Db::new().scan(0, 10, None, None), (0, vec![]));
+    }
+
+    #[test]
+    fn test_scan_containers() {
+        let db = Db::new();
+        let fields = (0..50).map(|n| (format!("f{n}"), Bytes::from(format!("v{n}"))));
+        db.hset("hash", fields.collect()).unwrap();
+        let members = (0..50).map(|n| Bytes::from(format!("m{n}")));
+        db.sadd("set", members.clone().collect()).unwrap();
+        let scored = members.enumerate().map(|(n, m)| (n as f64, m));
+        db.zadd("zset", scored.collect(), AddOptions::default())
+            .unwrap();
+
+        let mut fields = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, batch) = db.hscan("hash", cursor, 6, None).unwrap();
+            assert!(batch.len() <= 7);
+            fields.extend(batch);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        fields.sort();
+        assert_eq!(fields, {
+            let mut all = db.hgetall("hash").unwrap();
+            all.sort();
+            all
+        });
+
+        let (cursor, members) = db.sscan("set", 0, 100, Some(b"m1?")).unwrap();
+        assert_eq!((cursor, members.len()), (0, 10));
+        let (_, members) = db.zscan("zset", 0, 100, Some(b"m7")).unwrap();
+        assert_eq!(members, [(Bytes::from("m7"), 7.0)]);
+        assert_eq!(db.sscan("missing", 0, 10, None), Ok((0, vec![])));
+        assert_eq!(db.zscan("hash", 0, 10, None), Err(WrongType));
+    }
+
+    #[test]
+    fn test_watch_sees_writes_to_its_key() {
+        let db = Db::new();
+        db.set("a".to_string(), Bytes::from("1"), None);
+        db.sadd("s", vec![Bytes::from("m")]).unwrap();
+
+        let watch = db.watch("a");
+        db.get("a");
+        db.set("b".to_string(), Bytes::from("1"), None);
+        assert!(!watch.is_modified());
+        db.set_range("a", 0, b"2").unwrap();
+        assert!(watch.is_modified());
+
+        let watch = db.watch("s");
+        db.sadd("s", vec![Bytes::from("m")]).unwrap();
+        db.srem("s", &[Bytes::from("x")]).unwrap();
+        assert!(!watch.is_modified());
+        db.srem("s", &[Bytes::from("m")]).unwrap();
+        assert!(watch.is_modified());
+
+        let watch = db.watch("gone");
+        db.set("gone".to_string(), Bytes::from("v"), Some(Duration::ZERO));
+        let again = db.watch("gone");
+        thread::sleep(time::Duration::from_millis(1));
+        assert_eq!(db.purge_expired(10), 1);
+        assert!(watch.is_modified() && again.is_modified());
+
+        // Only the shadowed watches on `a` and `s` are left.
+        drop((watch, again));
+        let watched = db.shared.state.lock().unwrap().watched.len();
+        assert_eq!(watched, 2);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_watch_waits_for_write() {
+        let db = Db::new();
+        let watch = db.watch("a");
+        let writer = db.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(time::Duration::from_millis(10)).await;
+            writer
+                .push("a", ListEnd::Left, vec![Bytes::from("x")])
+                .unwrap();
+        });
+
+        tokio::time::timeout(time::Duration::from_secs(5), watch.modified())
+            .await
+            .unwrap();
+        assert_eq!(watch.key(), "a");
+    }
+
+    #[test]
+    fn test_record_changes() {
+        let db = Db::new();
+        db.set("before".to_string(), Bytes::from("1"), None);
+        assert_eq!(db.next_change_offset(), None);
+        assert_eq!(db.changes_since(0, 10), Ok(vec![]));
+
+        db.record_changes(3, 5);
+        db.set("a".to_string(), Bytes::from("1"), None);
+        db.set(
+            "a".to_string(),
+            Bytes::from("2"),
+            Some(Duration::from_secs(60)),
+        );
+        db.del(&["before".to_string()]);
+        assert_eq!(db.next_change_offset(), Some(8));
+
+        let changes = db.changes_since(5, 2).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            (changes[0].offset, changes[0].key.as_deref()),
+            (5, Some("a"))
+        );
+        assert_eq!(changes[0].value, Some(Value::String(Bytes::from("2"))));
+        assert!(changes[1].ttl.is_some());
+        let last = db.changes_since(7, 10).unwrap();
+        assert_eq!(
+            (last[0].key.as_deref(), &last[0].value),
+            (Some("before"), &None)
+        );
+        assert_eq!(db.changes_since(8, 10), Ok(vec![]));
+
+        db.sadd("s", vec![Bytes::from("m")]).unwrap();
+        assert_eq!(db.changes_since(5, 10), Err(ChangesLost { oldest: 7 }));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_wait_for_change() {
+        let db = Db::new();
+        db.record_changes(10, 0);
+        db.set("a".to_string(), Bytes::from("1"), None);
+        db.wait_for_change(0).await;
+
+        let writer = db.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(time::Duration::from_millis(10)).await;
+            writer.set("b".to_string(), Bytes::from("1"), None);
+        });
+        tokio::time::timeout(time::Duration::from_secs(5), db.wait_for_change(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            db.changes_since(1, 10).unwrap()[0].key.as_deref(),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn test_incr_by_float() {
+        let db = Db::new();
+        db.set(
+            "n".to_string(),
+            Bytes::from("10.50"),
+            Some(Duration::from_secs(60)),
+        );
+
+        assert_eq!(db.incr_by_float("n", 0.1), Ok(Bytes::from("10.6")));
+        assert_eq!(db.incr_by_float("n", -5.0e3), Ok(Bytes::from("-4989.4")));
+        assert!(db.key_snapshot("n").unwrap().ttl.is_some());
+        assert_eq!(db.incr_by_float("new", 3.0), Ok(Bytes::from("3")));
+        assert_eq!(
+            db.incr_by_float("n", f64::INFINITY),
+            Err(IncrError::NotFinite)
+        );
+        assert_eq!(db.get("n"), Some(Bytes::from("-4989.4")));
+
+        db.set("text".to_string(), Bytes::from(""), None);
+        assert_eq!(db.incr_by_float("text", 1.0), Err(IncrError::NotFloat));
+        db.sadd("s", vec![Bytes::from("m")]).unwrap();
+        assert_eq!(db.incr_by_float("s", 1.0), Err(IncrError::WrongType));
+    }
+
+    #[test]
+    fn test_hincr_by_float() {
+        let db = Db::new();
+
+        assert_eq!(db.hincr_by_float("h", "f", 1.5), Ok(Bytes::from("1.5")));
+        assert_eq!(db.hincr_by_float("h", "f", 1.5), Ok(Bytes::from("3")));
+        assert_eq!(db.hget("h", "f"), Ok(Some(Bytes::from("3"))));
+        assert_eq!(
+            db.hincr_by_float("missing", "f", f64::NEG_INFINITY),
+            Err(IncrError::NotFinite)
+        );
+        assert!(!db.exists("missing"));
+
+        db.hset("h", vec![("word".to_string(), Bytes::from("abc"))])
+            .unwrap();
+        assert_eq!(
+            db.hincr_by_float("h", "word", 1.0),
+            Err(IncrError::NotFloat)
+        );
+    }
+
+    #[test]
+    fn test_rename() {
+        let db = Db::new();
+        db.set(
+            "a".to_string(),
+            Bytes::from("1"),
+            Some(Duration::from_secs(60)),
+        );
+        db.set("b".to_string(), Bytes::from("2"), None);
+        db.set("c".to_string(), Bytes::from("3"), None);
+
+        assert_eq!(db.rename("a", "b", false), Ok(true));
+        assert_eq!(db.get("a"), None);
+        assert_eq!(db.get("b"), Some(Bytes::from("1")));
+        assert!(db.key_snapshot("b").unwrap().ttl.is_some());
+
+        assert_eq!(db.rename("b", "c", true), Ok(false));
+        assert_eq!(db.get("c"), Some(Bytes::from("3")));
+        assert_eq!(db.rename("c", "c", false), Ok(true));
+        assert_eq!(db.rename("c", "c", true), Ok(false));
+        assert_eq!(db.rename("missing", "d", false), Err(NoSuchKey));
+
+        // The expiry index follows the key, with nothing left under `a`.
+        db.rename("b", "d", true).unwrap();
+        db.expire("d", Instant::now(), ExpireCondition::default());
+        assert_eq!(db.purge_expired(10), 0);
+        assert!(db.snapshot().iter().all(|key| key.key == "c"));
+    }
+
+    #[test]
+    fn test_flush() {
+        let db = Db::new();
+        db.populate(3, "key", None);
+        db.set(
+            "ttl".to_string(),
+            Bytes::from("v"),
+            Some(Duration::from_secs(60)),
+        );
+        let watch = db.watch("key:0");
+        db.record_changes(10, 0);
+
+        let flushed = db.flush();
+        assert!(db.is_empty());
+        assert!(watch.is_modified());
+        assert_eq!(db.scan(0, 10, None, None), (0, vec![]));
+        assert_eq!(db.changes_since(0, 10).unwrap()[0].key, None);
+        drop(flushed);
+
+        db.set("a".to_string(), Bytes::from("1"), None);
+        assert_eq!(db.keys(b"*"), ["a"]);
+    }
+
+    #[test]
+    fn test_swap_databases() {
+        let databases = DbHandle::with_databases(3, KeyHasher::default()).databases();
+        databases[0].set("a".to_string(), Bytes::from("1"), Some(Duration::ZERO));
+        databases[2].set("b".to_string(), Bytes::from("2"), None);
+        let watch = databases[2].watch("b");
+
+        databases.swap(2, 0);
+        assert_eq!(databases[0].keys(b"*"), ["b"]);
+        assert!(watch.is_modified());
+        // The expiry index moved with the keys.
+        thread::sleep(time::Duration::from_millis(1));
+        assert_eq!(databases[0].purge_expired(10), 0);
+        assert_eq!(databases[2].purge_expired(10), 1);
+        assert!(databases[1].is_empty());
+    }
+
+    #[test]
+    fn test_move_key() {
+        let databases = DbHandle::with_databases(2, KeyHasher::default()).databases();
+        let (src, dst) = (&databases[0], &databases[1]);
+        src.set(
+            "a".to_string(),
+            Bytes::from("1"),
+            Some(Duration::from_secs(60)),
+        );
+        src.set("b".to_string(), Bytes::from("2"), None);
+        dst.set("b".to_string(), Bytes::from("3"), None);
+
+        assert!(databases.move_key("a", 0, 1));
+        assert!(src.get("a").is_none());
+        assert!(dst.key_snapshot("a").unwrap().ttl.is_some());
+        assert!(!databases.move_key("b", 0, 1));
+        assert_eq!(src.get("b"), Some(Bytes::from("2")));
+        assert!(!databases.move_key("missing", 0, 1));
+        assert!(databases.move_key("a", 1, 0));
+    }
+
+    #[test]
+    fn test_exclusive_holds_off_other_threads() {
+        let databases = DbHandle::with_databases(2, KeyHasher::default()).databases();
+        let other = databases.clone();
+        databases
+            .exclusive(|| {
+                let writer =
+                    thread::spawn(move || other[1].set("a".to_string(), Bytes::from("2"), None));
+                thread::sleep(time::Duration::from_millis(20));
+                // The caller's own writes, in any database, still go through.
+                databases[1].set("a".to_string(), Bytes::from("1"), None);
+                databases.swap(0, 1);
+                assert_eq!(databases[0].get("a"), Some(Bytes::from("1")));
+                assert!(!writer.is_finished());
+                writer
+            })
+            .join()
+            .unwrap();
+        assert_eq!(databases[1].get("a"), Some(Bytes::from("2")));
+    }
+
+    #[test]
+    fn test_len_and_random_key() {
+        let db = Db::new();
+        assert!(db.is_empty());
+        assert_eq!(db.random_key(), None);
+
+        db.populate(3, "key", None);
+        db.set("gone".to_string(), Bytes::from("v"), Some(Duration::ZERO));
+        thread::sleep(time::Duration::from_millis(1));
+        assert_eq!(db.len(), 3);
+
+        let mut seen = HashSet::new();
+        for _ in 0..200 {
+            seen.insert(db.random_key().unwrap());
+        }
+        assert_eq!(seen.len(), 3);
+        assert!(!seen.contains("gone"));
+    }
+
+    #[test]
+    fn test_expire_key() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        let changed = db.expire(
+            "Foo",
+            Instant::now() + Duration::new(0, 10),
+            ExpireCondition::default(),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert!(changed);
+        assert!(db.get("Foo").is_none());
+        assert!(!db.expire("Foo", Instant::now(), ExpireCondition::default()));
+    }
+
+    #[test]
+    fn test_expire_in_the_past_deletes_key() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        assert!(db.expire("Foo", Instant::now(), ExpireCondition::default()));
+        assert!(!db.exists("Foo"));
+    }
+
+    #[test]
+    fn test_expire_conditions() {
+        let db = Db::new();
+        let soon = Instant::now() + Duration::new(5, 0);
+        let later = soon + Duration::new(5, 0);
+        let nx = ExpireCondition {
+            nx: true,
+            ..Default::default()
+        };
+        let xx = ExpireCondition {
+            xx: true,
+            ..Default::default()
+        };
+        let gt = ExpireCondition {
+            gt: true,
+            ..Default::default()
+        };
+        let lt = ExpireCondition {
+            lt: true,
+            ..Default::default()
+        };
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        // Without a TTL the key counts as never expiring.
+        assert!(!db.expire("Foo", later, xx));
+        assert!(!db.expire("Foo", later, gt));
+        assert!(db.expire("Foo", later, nx));
+        assert!(!db.expire("Foo", soon, nx));
+        assert!(!db.expire("Foo", soon, gt));
+        assert!(db.expire("Foo", soon, lt));
+        assert!(!db.expire("Foo", later, lt));
+        assert!(db.expire("Foo", later, gt));
+        assert!(db.expire("Foo", soon, xx));
+    }
+
+    #[test]
+    fn test_persist_key() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::from_millis(50)),
+        );
+
+        assert!(db.persist("Foo"));
+        assert!(!db.persist("Foo"));
+        thread::sleep(time::Duration::from_millis(60));
+
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("Bar"));
+        assert!(!db.persist("Baz"));
+    }
+
+    #[test]
+    fn test_persist_expired_key() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(0, 10)),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert!(!db.persist("Foo"));
+        assert!(db.get("Foo").is_none());
+    }
+
+    #[test]
+    fn test_set_with_conditions() {
+        let db = Db::new();
+        let set = |value: &'static str, condition| {
+            db.set_with(
+                "Foo".to_string(),
+                Bytes::from(value),
+                condition,
+                SetExpiry::Clear,
+                false,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(set("Bar", SetCondition::Exists), (false, None));
+        assert_eq!(set("Bar", SetCondition::NotExists), (true, None));
+        assert_eq!(
+            set("Baz", SetCondition::NotExists),
+            (false, Some(Value::from("Bar")))
+        );
+        assert_eq!(
+            set("Baz", SetCondition::Exists),
+            (true, Some(Value::from("Bar")))
+        );
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("Baz"));
+    }
+
+    #[test]
+    fn test_set_with_keep_ttl() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(5, 0)),
+        );
+
+        db.set_with(
+            "Foo".to_string(),
+            Bytes::from("Baz"),
+            SetCondition::Always,
+            SetExpiry::Keep,
+            false,
+        )
+        .unwrap();
+        assert!(db.key_snapshot("Foo").unwrap().ttl.is_some());
+
+        db.set_with(
+            "Foo".to_string(),
+            Bytes::from("Qux"),
+            SetCondition::Always,
+            SetExpiry::Clear,
+            false,
+        )
+        .unwrap();
+        assert!(db.key_snapshot("Foo").unwrap().ttl.is_none());
+    }
+
+    #[test]
+    fn test_mset_and_mget() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Old"),
+            Some(Duration::new(0, 10)),
+        );
+
+        db.mset(vec![
+            ("Foo".to_string(), Bytes::from("Bar")),
+            ("Baz".to_string(), Bytes::from("Qux")),
+        ]);
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert_eq!(
+            db.mget(&["Foo".to_string(), "Nope".to_string(), "Baz".to_string()]),
+            [Some(Bytes::from("Bar")), None, Some(Bytes::from("Qux"))]
+        );
+    }
+
+    #[test]
+    fn test_set_range() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Hello World"),
+            Some(Duration::new(5, 0)),
+        );
+
+        assert_eq!(db.set_range("Foo", 6, b"Redis"), Ok(11));
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("Hello Redis"));
+        assert!(db.key_snapshot("Foo").unwrap().ttl.is_some());
+    }
+
+    #[test]
+    fn test_set_range_pads_with_zeros() {
+        let db = Db::new();
+
+        assert_eq!(db.set_range("Foo", 3, b"ab"), Ok(5));
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("\0\0\0ab"));
+    }
+
+    #[test]
+    fn test_set_range_empty_value_creates_nothing() {
+        let db = Db::new();
+
+        assert_eq!(db.set_range("Foo", 10, b""), Ok(0));
+        assert!(!db.exists("Foo"));
+    }
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, Bytes)> {
+        pairs
+            .iter()
+            .map(|(f, v)| (f.to_string(), Bytes::copy_from_slice(v.as_bytes())))
+            .collect()
+    }
+
+    #[test]
+    fn test_hset_and_hget() {
+        let db = Db::new();
+
+        assert_eq!(db.hset("Foo", fields(&[("a", "1"), ("b", "2")])), Ok(2));
+        assert_eq!(db.hset("Foo", fields(&[("b", "3"), ("c", "4")])), Ok(1));
+
+        assert_eq!(db.hget("Foo", "b"), Ok(Some(Bytes::from("3"))));
+        assert_eq!(db.hget("Foo", "z"), Ok(None));
+        assert_eq!(db.hget("Nope", "a"), Ok(None));
+        assert_eq!(db.hlen("Foo"), Ok(3));
+        assert_eq!(db.hexists("Foo", "c"), Ok(true));
+        assert_eq!(
+            db.hgetall("Foo"),
+            Ok(fields(&[("a", "1"), ("b", "3"), ("c", "4")]))
+        );
+        assert_eq!(db.get_value("Foo").unwrap().type_name(), "hash");
+    }
+
+    #[test]
+    fn test_hdel_removes_empty_hash() {
+        let db = Db::new();
+        db.hset("Foo", fields(&[("a", "1"), ("b", "2")])).unwrap();
+
+        assert_eq!(db.hdel("Foo", &["a".to_string(), "z".to_string()]), Ok(1));
+        assert!(db.exists("Foo"));
+        assert_eq!(db.hdel("Foo", &["b".to_string()]), Ok(1));
+        assert!(!db.exists("Foo"));
+        assert_eq!(db.hdel("Foo", &["b".to_string()]), Ok(0));
+    }
+
+    #[test]
+    fn test_push_and_pop() {
+        let db = Db::new();
+
+        assert_eq!(db.push("Foo", ListEnd::Right, members(&["b", "c"])), Ok(2));
+        assert_eq!(db.push("Foo", ListEnd::Left, members(&["a", "z"])), Ok(4));
+        assert_eq!(db.lrange("Foo", 0, -1), Ok(members(&["z", "a", "b", "c"])));
+        assert_eq!(db.lrange("Foo", -2, 10), Ok(members(&["b", "c"])));
+        assert_eq!(db.llen("Foo"), Ok(4));
+
+        assert_eq!(db.pop("Foo", ListEnd::Left, 1), Ok(Some(members(&["z"]))));
+        assert_eq!(
+            db.pop("Foo", ListEnd::Right, 2),
+            Ok(Some(members(&["c", "b"])))
+        );
+        assert_eq!(db.pop("Foo", ListEnd::Right, 5), Ok(Some(members(&["a"]))));
+        assert!(!db.exists("Foo"));
+        assert_eq!(db.pop("Foo", ListEnd::Left, 1), Ok(None));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_blocking_pop_waits_for_push() {
+        let db = Db::new();
+        let keys = vec!["Foo".to_string(), "Bar".to_string()];
+        db.set("Other".to_string(), Bytes::from("x"), None);
+
+        let waiter = {
+            let db = db.clone();
+            tokio::spawn(async move { db.blocking_pop(&keys, ListEnd::Left).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        db.push("Other2", ListEnd::Left, members(&["ignored"]))
+            .unwrap();
+        db.push("Bar", ListEnd::Left, members(&["a", "b"])).unwrap();
+
+        assert_eq!(
+            waiter.await.unwrap(),
+            Ok(("Bar".to_string(), Bytes::from("b")))
+        );
+        assert_eq!(db.lrange("Bar", 0, -1), Ok(members(&["a"])));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_blocking_pop_takes_existing_element() {
+        let db = Db::new();
+        db.push("Bar", ListEnd::Right, members(&["a", "b"]))
+            .unwrap();
+        db.set("Str".to_string(), Bytes::from("x"), None);
+
+        let keys = ["Foo".to_string(), "Bar".to_string()];
+
+        let popped = db.blocking_pop(&keys, ListEnd::Right).await;
+
+        assert_eq!(popped, Ok(("Bar".to_string(), Bytes::from("b"))));
+        assert_eq!(
+            db.blocking_pop(&["Str".to_string()], ListEnd::Left).await,
+            Err(WrongType)
+        );
+    }
+
+    fn members(members: &[&str]) -> Vec<Bytes> {
+        members
+            .iter()
+            .map(|m| Bytes::copy_from_slice(m.as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_sadd_and_srem() {
+        let db = Db::new();
+
+        assert_eq!(db.sadd("Foo", members(&["a", "b", "a"])), Ok(2));
+        assert_eq!(db.sadd("Foo", members(&["b", "c"])), Ok(1));
+        assert_eq!(db.smembers("Foo"), Ok(members(&["a", "b", "c"])));
+        assert_eq!(db.scard("Foo"), Ok(3));
+        assert_eq!(db.sismember("Foo", b"c"), Ok(true));
+        assert_eq!(db.sismember("Nope", b"c"), Ok(false));
+
+        assert_eq!(db.srem("Foo", &members(&["a", "z"])), Ok(1));
+        assert_eq!(db.srem("Foo", &members(&["b", "c"])), Ok(2));
+        assert!(!db.exists("Foo"));
+    }
+
+    #[test]
+    fn test_combine_sets() {
+        let db = Db::new();
+        db.sadd("A", members(&["a", "b", "c"])).unwrap();
+        db.sadd("B", members(&["b", "c", "d"])).unwrap();
+        let keys = |keys: &[&str]| -> Vec<String> { keys.iter().map(|k| k.to_string()).collect() };
+
+        assert_eq!(
+            db.combine_sets(SetOperation::Inter, &keys(&["A", "B"])),
+            Ok(members(&["b", "c"]))
+        );
+        assert_eq!(
+            db.combine_sets(SetOperation::Union, &keys(&["A", "B", "Nope"])),
+            Ok(members(&["a", "b", "c", "d"]))
+        );
+        assert_eq!(
+            db.combine_sets(SetOperation::Diff, &keys(&["A", "B"])),
+            Ok(members(&["a"]))
+        );
+        assert_eq!(
+            db.combine_sets(SetOperation::Inter, &keys(&["A", "Nope"])),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn test_store_combined_sets() {
+        let db = Db::new();
+        db.sadd("A", members(&["a", "b"])).unwrap();
+        db.set(
+            "Dest".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(5, 0)),
+        );
+
+        assert_eq!(
+            db.store_combined_sets(SetOperation::Union, "Dest", &["A".to_string()]),
+            Ok(2)
+        );
+        assert_eq!(db.smembers("Dest"), Ok(members(&["a", "b"])));
+        assert!(db.key_snapshot("Dest").unwrap().ttl.is_none());
+
+        assert_eq!(
+            db.store_combined_sets(
+                SetOperation::Diff,
+                "Dest",
+                &["A".to_string(), "A".to_string()]
+            ),
+            Ok(0)
+        );
+        assert!(!db.exists("Dest"));
+    }
+
+    #[test]
+    fn test_zadd_and_zrange() {
+        let db = Db::new();
+        let scored = |pairs: &[(f64, &str)]| -> Vec<(f64, Bytes)> {
+            pairs
+                .iter()
+                .map(|(s, m)| (*s, Bytes::copy_from_slice(m.as_bytes())))
+                .collect()
+        };
+
+        assert_eq!(
+            db.zadd(
+                "Foo",
+                scored(&[(2.0, "b"), (1.0, "a"), (3.0, "c")]),
+                AddOptions::default()
+            ),
+            Ok(3)
+        );
+        assert_eq!(db.zscore("Foo", b"b"), Ok(Some(2.0)));
+        assert_eq!(db.zscore("Foo", b"z"), Ok(None));
+        assert_eq!(
+            db.zrange("Foo", 0, 1),
+            Ok(vec![(Bytes::from("a"), 1.0), (Bytes::from("b"), 2.0)])
+        );
+        let all = ScoreBound {
+            score: f64::INFINITY,
+            exclusive: false,
+        };
+        let above_one = ScoreBound {
+            score: 1.0,
+            exclusive: true,
+        };
+        assert_eq!(
+            db.zrange_by_score("Foo", above_one, all, 1, Some(5)),
+            Ok(vec![(Bytes::from("c"), 3.0)])
+        );
+
+        assert_eq!(db.zrem("Foo", &members(&["a", "b", "c"])), Ok(3));
+        assert!(!db.exists("Foo"));
+    }
+
+    #[test]
+    fn test_zadd_xx_does_not_create_key() {
+        let db = Db::new();
+        let xx = AddOptions {
+            xx: true,
+            ..Default::default()
+        };
+
+        assert_eq!(db.zadd("Foo", vec![(1.0, Bytes::from("a"))], xx), Ok(0));
+        assert!(!db.exists("Foo"));
+    }
+
+    #[test]
+    fn test_zadd_only_touches_key_when_it_changes() {
+        let db = Db::new();
+        let member = || vec![(1.0, Bytes::from("a"))];
+        db.zadd("z", member(), AddOptions::default()).unwrap();
+        let nx = AddOptions {
+            nx: true,
+            ..Default::default()
+        };
+        let xx = AddOptions {
+            xx: true,
+            ..Default::default()
+        };
+
+        let watch = db.watch("z");
+        let missing = db.watch("missing");
+        db.zadd("z", member(), nx).unwrap();
+        db.zadd("z", member(), AddOptions::default()).unwrap();
+        db.zadd("missing", member(), xx).unwrap();
+        assert!(!watch.is_modified());
+        assert!(!missing.is_modified());
+        assert!(!db.exists("missing"));
+        db.zadd("z", vec![(2.0, Bytes::from("a"))], xx).unwrap();
+        assert!(watch.is_modified());
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let db = Db::new();
+        db.set("Str".to_string(), Bytes::from("Bar"), None);
+        db.hset("Hash", fields(&[("a", "1")])).unwrap();
+
+        assert_eq!(db.hget("Str", "a"), Err(WrongType));
+        assert_eq!(db.hset("Str", fields(&[("a", "1")])), Err(WrongType));
+        assert_eq!(db.hlen("Str"), Err(WrongType));
+        assert_eq!(db.sadd("Hash", members(&["a"])), Err(WrongType));
+        assert_eq!(db.zscore("Hash", b"a"), Err(WrongType));
+        assert_eq!(
+            db.combine_sets(
+                SetOperation::Union,
+                &["Nope".to_string(), "Str".to_string()]
+            ),
+            Err(WrongType)
+        );
+        assert_eq!(db.get("Hash"), None);
+        assert_eq!(db.mget(&["Hash".to_string()]), [None]);
+        assert_eq!(db.set_range("Hash", 0, b"x"), Err(WrongType));
+        assert_eq!(
+            db.compare_and_set("Hash", b"", Bytes::from("x"), None),
+            Err(WrongType)
+        );
+    }
+
+    #[test]
+    fn test_set_replaces_hash_unless_get() {
+        let db = Db::new();
+        db.hset("Foo", fields(&[("a", "1")])).unwrap();
+        let set = |get| {
+            db.set_with(
+                "Foo".to_string(),
+                Bytes::from("Bar"),
+                SetCondition::Always,
+                SetExpiry::Clear,
+                get,
+            )
+        };
+
+        assert_eq!(set(true), Err(WrongType));
+        assert_eq!(db.hlen("Foo"), Ok(1));
+        assert!(set(false).unwrap().0);
+        assert_eq!(db.get("Foo"), Some(Bytes::from("Bar")));
+    }
+
+    #[test]
+    fn test_del_keys() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+        db.set("Baz".to_string(), Bytes::from("Qux"), None);
+
+        let removed = db.del(&["Foo".to_string(), "Baz".to_string(), "Nope".to_string()]);
+
+        assert_eq!(removed, 2);
+        assert!(db.get("Foo").is_none());
+        assert!(db.get("Baz").is_none());
+    }
+
+    #[test]
+    fn test_del_counts_each_key_once() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        assert_eq!(db.del(&["Foo".to_string(), "Foo".to_string()]), 1);
+    }
+
+    #[test]
+    fn test_del_expired_key() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(0, 10)),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert_eq!(db.del(&["Foo".to_string()]), 0);
+    }
+
+    #[test]
+    fn test_expired_key_is_removed_on_access() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(0, 10)),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert!(db.get("Foo").is_none());
+        assert_eq!(db.purge_expired(10), 0);
+    }
+
+    #[test]
+    fn test_purge_expired_keys() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+        db.set(
+            "Baz".to_string(),
+            Bytes::from("Qux"),
+            Some(Duration::new(5, 0)),
+        );
+        for n in 0..3 {
+            db.set(
+                format!("Gone:{n}"),
+                Bytes::from("Soon"),
+                Some(Duration::new(0, 10)),
+            );
+        }
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert_eq!(db.purge_expired(2), 2);
+        assert_eq!(db.purge_expired(10), 1);
+        assert_eq!(db.purge_expired(10), 0);
+        assert!(db.exists("Foo"));
+        assert!(db.exists("Baz"));
+    }
+
+    #[test]
+    fn test_purge_skips_replaced_ttl() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(0, 10)),
+        );
+        db.set("Foo".to_string(), Bytes::from("Baz"), None);
+        db.set(
+            "Qux".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::from_millis(50)),
+        );
+        db.persist("Qux");
+        thread::sleep(time::Duration::from_millis(60));
+
+        assert_eq!(db.purge_expired(10), 0);
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("Baz"));
+        assert_eq!(db.get("Qux").unwrap(), Bytes::from("Bar"));
+    }
+
+    #[test]
+    fn test_compare_and_set_matching_value() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        let old = db
+            .compare_and_set("Foo", b"Bar", Bytes::from("Baz"), None)
+            .unwrap();
+
+        assert_eq!(old.unwrap(), Bytes::from("Bar"));
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("Baz"));
+    }
+
+    #[test]
+    fn test_compare_and_set_mismatched_value() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        let old = db
+            .compare_and_set("Foo", b"Qux", Bytes::from("Baz"), None)
+            .unwrap();
+
+        assert_eq!(old.unwrap(), Bytes::from("Bar"));
+        assert_eq!(db.get("Foo").unwrap(), Bytes::from("Bar"));
+    }
+
+    #[test]
+    fn test_compare_and_set_missing_key() {
+        let db = Db::new();
+
+        let old = db
+            .compare_and_set("Foo", b"", Bytes::from("Baz"), None)
+            .unwrap();
+
+        assert!(old.is_none());
+        assert!(db.get("Foo").is_none());
+    }
+
+    #[test]
+    fn test_compare_and_set_expired_key() {
+        let db = Db::new();
+        db.set(
+            "Foo".to_string(),
+            Bytes::from("Bar"),
+            Some(Duration::new(0, 10)),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        let old = db
+            .compare_and_set("Foo", b"Bar", Bytes::from("Baz"), None)
+            .unwrap();
+
+        assert!(old.is_none());
+        assert!(db.get("Foo").is_none());
+    }
+
+    #[test]
+    fn test_compare_and_set_with_expiry() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        db.compare_and_set(
+            "Foo",
+            b"Bar",
+            Bytes::from("Baz"),
+            Some(Duration::new(0, 10)),
+        )
+        .unwrap();
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert!(db.get("Foo").is_none());
+    }
+
+    #[test]
+    fn test_snapshot_db() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+        db.set(
+            "Baz".to_string(),
+            Bytes::from("Qux"),
+            Some(Duration::new(5, 0)),
+        );
+        db.set(
+            "Gone".to_string(),
+            Bytes::from("Soon"),
+            Some(Duration::new(0, 10)),
+        );
+        thread::sleep(time::Duration::from_millis(10));
+
+        let mut snapshot = db.snapshot();
+        snapshot.sort_by(|a, b| a.key.cmp(&b.key));
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].key, "Baz");
+        assert!(snapshot[0].ttl.unwrap() <= Duration::new(5, 0));
+        assert_eq!(snapshot[1].key, "Foo");
+        assert_eq!(snapshot[1].value, Value::from("Bar"));
+        assert!(snapshot[1].ttl.is_none());
+    }
+
+    #[test]
+    fn test_snapshot_copies_every_key_across_batches() {
+        let db = Db::new();
+        db.populate(SNAPSHOT_BATCH as u64 * 3 + 1, "key", None);
+
+        let mut keys: Vec<_> = db.snapshot().into_iter().map(|key| key.key).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), SNAPSHOT_BATCH * 3 + 1);
+    }
+
+    #[test]
+    fn test_key_snapshot() {
+        let db = Db::new();
+        db.set("Foo".to_string(), Bytes::from("Bar"), None);
+
+        let snapshot = db.key_snapshot("Foo").unwrap();
+
+        assert_eq!(snapshot.value, Value::from("Bar"));
+        assert!(snapshot.ttl.is_none());
+        assert!(db.key_snapshot("Baz").is_none());
+    }
+
+    #[test]
+    fn test_trace_keys() {
+        let db = Db::new();
+        let keys = ["Foo".to_string(), "Bar".to_string()];
+
+        db.trace_key("Foo", Some(Duration::new(5, 0)));
+        db.trace_key("Bar", Some(Duration::new(0, 10)));
+        thread::sleep(time::Duration::from_millis(10));
+
+        assert_eq!(db.traced_keys(&keys), ["Foo"]);
+        db.trace_key("Foo", None);
+        assert!(db.traced_keys(&keys).is_empty());
+    }
+
+    #[test]
+    fn test_populate_db() {
+        let db = Db::new();
+        db.set("key:1".to_string(), Bytes::from("Bar"), None);
+
+        let added = db.populate(3, "key", None);
+
+        assert_eq!(added, 2);
+        assert_eq!(db.get("key:0").unwrap(), Bytes::from("value:0"));
+        assert_eq!(db.get("key:1").unwrap(), Bytes::from("Bar"));
+        assert_eq!(db.get("key:2").unwrap(), Bytes::from("value:2"));
+    }
+
+    #[test]
+    fn test_populate_db_with_size() {
+        let db = Db::new();
+
+        db.populate(1, "big", Some(10));
+        db.populate(1, "small", Some(3));
+
+        assert_eq!(db.get("big:0").unwrap(), Bytes::from("value:0\0\0\0"));
+        assert_eq!(db.get("small:0").unwrap(), Bytes::from("val"));
     }
 }
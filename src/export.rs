@@ -0,0 +1,278 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::db::KeySnapshot;
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line: `{"key":..,"type":..,"ttl":..,"value":..}`.
+    Json,
+    /// A `key,type,ttl,value` header followed by one row per key.
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown export format '{s}'")),
+        }
+    }
+}
+
+/// Writes `entries` to `out` in the given format and returns the number of
+/// keys written. TTLs are in milliseconds, with -1 for keys that never
+/// expire. Values that are not valid UTF-8 are rendered lossily, hashes as a
+/// JSON object of their fields, lists and sets as a JSON array of their
+/// elements and sorted sets as a JSON object of members and their scores.
+pub fn write_entries<W: Write>(
+    mut out: W,
+    entries: &[KeySnapshot],
+    format: Format,
+) -> io::Result<usize> {
+    if format == Format::Csv {
+        out.write_all(b"key,type,ttl,value\n")?;
+    }
+
+    for entry in entries {
+        let ttl = entry.ttl.map_or(-1, |ttl| ttl.as_millis() as i64);
+        let kind = entry.value.type_name();
+        let line = match format {
+            Format::Json => format!(
+                "{{\"key\":{},\"type\":\"{kind}\",\"ttl\":{ttl},\"value\":{}}}\n",
+                json_string(&entry.key),
+                json_value(&entry.value)
+            ),
+            Format::Csv => format!(
+                "{},{kind},{ttl},{}\n",
+                csv_field(&entry.key),
+                csv_field(&render(&entry.value))
+            ),
+        };
+        out.write_all(line.as_bytes())?;
+    }
+
+    out.flush()?;
+    Ok(entries.len())
+}
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(data) => String::from_utf8_lossy(data).into_owned(),
+        Value::List(_) | Value::Hash(_) | Value::Set(_) | Value::SortedSet(_) => json_value(value),
+    }
+}
+
+pub(crate) fn json_value(value: &Value) -> String {
+    match value {
+        Value::String(_) => json_string(&render(value)),
+        Value::Hash(hash) => {
+            let mut fields: Vec<_> = hash.iter().collect();
+            fields.sort_unstable();
+            let fields: Vec<_> = fields
+                .into_iter()
+                .map(|(field, data)| {
+                    format!(
+                        "{}:{}",
+                        json_string(field),
+                        json_string(&String::from_utf8_lossy(data))
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::List(list) => {
+            let elements: Vec<_> = list
+                .iter()
+                .map(|element| json_string(&String::from_utf8_lossy(element)))
+                .collect();
+            format!("[{}]", elements.join(","))
+        }
+        Value::Set(set) => {
+            let mut members: Vec<_> = set.iter().collect();
+            members.sort_unstable();
+            let members: Vec<_> = members
+                .into_iter()
+                .map(|member| json_string(&String::from_utf8_lossy(member)))
+                .collect();
+            format!("[{}]", members.join(","))
+        }
+        Value::SortedSet(set) => {
+            let members: Vec<_> = set
+                .iter()
+                .map(|(member, score)| {
+                    // JSON has no infinity, so those scores are strings.
+                    let score = if score.is_finite() {
+                        score.to_string()
+                    } else {
+                        json_string(&score.to_string())
+                    };
+                    format!("{}:{score}", json_string(&String::from_utf8_lossy(member)))
+                })
+                .collect();
+            format!("{{{}}}", members.join(","))
+        }
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entries() -> Vec<KeySnapshot> {
+        vec![
+            KeySnapshot {
+                key: "Foo".to_string(),
+                value: Value::from("Bar"),
+                ttl: None,
+            },
+            KeySnapshot {
+                key: "a,b".to_string(),
+                value: Value::from("say \"hi\"\n"),
+                ttl: Some(Duration::from_millis(1500)),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("JSON".parse::<Format>().unwrap(), Format::Json);
+        assert_eq!("csv".parse::<Format>().unwrap(), Format::Csv);
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut out = Vec::new();
+
+        let written = write_entries(&mut out, &entries(), Format::Json).unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"key\":\"Foo\",\"type\":\"string\",\"ttl\":-1,\"value\":\"Bar\"}\n\
+             {\"key\":\"a,b\",\"type\":\"string\",\"ttl\":1500,\"value\":\"say \\\"hi\\\"\\n\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut out = Vec::new();
+
+        let written = write_entries(&mut out, &entries(), Format::Csv).unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "key,type,ttl,value\n\
+             Foo,string,-1,Bar\n\
+             \"a,b\",string,1500,\"say \"\"hi\"\"\n\"\n"
+        );
+    }
+
+    #[test]
+    fn test_write_hash() {
+        let hash = KeySnapshot {
+            key: "H".to_string(),
+            value: Value::Hash(
+                [("b", "2"), ("a", "1")]
+                    .into_iter()
+                    .map(|(f, v)| (f.to_string(), bytes::Bytes::from(v)))
+                    .collect(),
+            ),
+            ttl: None,
+        };
+        let mut json = Vec::new();
+        let mut csv = Vec::new();
+
+        write_entries(&mut json, std::slice::from_ref(&hash), Format::Json).unwrap();
+        write_entries(&mut csv, &[hash], Format::Csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            "{\"key\":\"H\",\"type\":\"hash\",\"ttl\":-1,\"value\":{\"a\":\"1\",\"b\":\"2\"}}\n"
+        );
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "key,type,ttl,value\nH,hash,-1,\"{\"\"a\"\":\"\"1\"\",\"\"b\"\":\"\"2\"\"}\"\n"
+        );
+    }
+
+    #[test]
+    fn test_write_set() {
+        let set = KeySnapshot {
+            key: "S".to_string(),
+            value: Value::Set(["b", "a"].into_iter().map(bytes::Bytes::from).collect()),
+            ttl: None,
+        };
+        let mut out = Vec::new();
+
+        write_entries(&mut out, &[set], Format::Json).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"key\":\"S\",\"type\":\"set\",\"ttl\":-1,\"value\":[\"a\",\"b\"]}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_sorted_set() {
+        let set = KeySnapshot {
+            key: "Z".to_string(),
+            value: Value::SortedSet(
+                [("b", 1.5), ("a", f64::INFINITY)]
+                    .into_iter()
+                    .map(|(m, s)| (bytes::Bytes::from(m), s))
+                    .collect(),
+            ),
+            ttl: None,
+        };
+        let mut out = Vec::new();
+
+        write_entries(&mut out, &[set], Format::Json).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"key\":\"Z\",\"type\":\"zset\",\"ttl\":-1,\"value\":{\"b\":1.5,\"a\":\"inf\"}}\n"
+        );
+    }
+
+    #[test]
+    fn test_json_escapes_control_characters() {
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+}
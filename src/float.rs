@@ -0,0 +1,66 @@
+//! Floating point numbers as clients send and receive them.
+
+/// Formats `value` the way Redis replies with it: the fewest digits that
+/// read back as the same number, at most 17, with no trailing zeros and no
+/// exponent unless the number is very large or very small. Infinities are
+/// `inf` and `-inf`.
+pub fn format(value: f64) -> String {
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    if value == 0.0 {
+        // Also turns -0 into 0.
+        return "0".to_string();
+    }
+
+    let scientific = format!("{value:e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if (-4..17).contains(&exponent) {
+        value.to_string()
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{mantissa}e{sign}{:02}", exponent.abs())
+    }
+}
+
+/// Parses a number sent by a client, which may be `inf`, `+inf` or `-inf`
+/// but not NaN.
+pub fn parse(data: &[u8]) -> Option<f64> {
+    std::str::from_utf8(data)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|f| !f.is_nan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(1.5), "1.5");
+        assert_eq!(format(3.0), "3");
+        assert_eq!(format(-0.0), "0");
+        assert_eq!(format(10.5 + 0.1), "10.6");
+        assert_eq!(format(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format(5.0e3), "5000");
+        assert_eq!(format(0.0001), "0.0001");
+        assert_eq!(format(1e-5), "1e-05");
+        assert_eq!(format(1.5e-7), "1.5e-07");
+        assert_eq!(format(1e17), "1e+17");
+        assert_eq!(format(12345678901234567.0), "12345678901234568");
+        assert_eq!(format(-2.5e300), "-2.5e+300");
+        assert_eq!(format(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(b"1.5"), Some(1.5));
+        assert_eq!(parse(b"-5e3"), Some(-5000.0));
+        assert_eq!(parse(b"+inf"), Some(f64::INFINITY));
+        assert_eq!(parse(b"nan"), None);
+        assert_eq!(parse(b" 1"), None);
+        assert_eq!(parse(b""), None);
+    }
+}
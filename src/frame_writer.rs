@@ -0,0 +1,81 @@
+use std::io;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::resp::Frame;
+
+/// Writes RESP frames to an `AsyncWrite` a piece at a time, so a large
+/// reply never has to be encoded into one buffer first: bulk strings are
+/// passed to the writer as they are, and nested arrays are walked in place.
+/// Every write waits for the underlying writer to accept the data, so a
+/// peer that reads slowly holds back the writer rather than growing a
+/// buffer.
+///
+/// Headers are written in small pieces, so `W` should be buffered.
+#[derive(Debug)]
+pub struct FrameWriter<W> {
+    inner: W,
+}
+
+impl<W: AsyncWrite + Unpin> FrameWriter<W> {
+    pub fn new(inner: W) -> FrameWriter<W> {
+        FrameWriter { inner }
+    }
+
+    /// Writes a whole frame, walking nested arrays in place.
+    pub async fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let mut stack = vec![std::slice::from_ref(frame).iter()];
+        while let Some(frames) = stack.last_mut() {
+            let Some(frame) = frames.next() else {
+                stack.pop();
+                continue;
+            };
+            match frame {
+                Frame::Array(frames) => {
+                    self.write_line(b'*', frames.len()).await?;
+                    stack.push(frames.iter());
+                }
+                Frame::BulkString(data) => self.write_bulk(data).await?,
+                frame => self.inner.write_all(&frame.to_bytes()).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a bulk string, passing `data` to the writer without copying
+    /// it.
+    async fn write_bulk(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write_line(b'$', data.len()).await?;
+        self.inner.write_all(data).await?;
+        self.inner.write_all(b"\r\n").await
+    }
+
+    async fn write_line(&mut self, prefix: u8, n: usize) -> io::Result<()> {
+        self.inner
+            .write_all(format!("{}{n}\r\n", prefix as char).as_bytes())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_write_frame_matches_encode() {
+        let frame = Frame::Array(vec![
+            Frame::BulkString(Bytes::from("key")),
+            Frame::Array(vec![Frame::Integer(1), Frame::Null, Frame::Array(vec![])]),
+            Frame::SimpleString("OK".into()),
+            Frame::Error("ERR no".into()),
+        ]);
+        let mut written = Vec::new();
+
+        FrameWriter::new(&mut written)
+            .write_frame(&frame)
+            .await
+            .unwrap();
+
+        assert_eq!(written, frame.to_bytes());
+    }
+}
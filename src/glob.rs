@@ -0,0 +1,111 @@
+//! Redis-style glob patterns, as used by PSUBSCRIBE and KEYS.
+
+/// Whether `text` matches `pattern`, where `*` matches any run of bytes,
+/// `?` any single byte, `[abc]`, `[a-z]` and `[^abc]` a byte from (or not
+/// from) a set, and `\` makes the next byte literal.
+pub fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where to resume if the bytes after the last `*` stop matching: the
+    // pattern just past it, and the next text position it could swallow.
+    let mut backtrack = None;
+
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(1),
+            Some(b'[') => match_class(&pattern[p..], text[t]),
+            Some(b'\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(2),
+            Some(&byte) => (byte == text[t]).then_some(1),
+            None => None,
+        };
+        match (step, backtrack) {
+            (Some(len), _) => {
+                p += len;
+                t += 1;
+            }
+            (None, Some((star_p, star_t))) => {
+                p = star_p;
+                t = star_t + 1;
+                backtrack = Some((star_p, star_t + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&byte| byte == b'*')
+}
+
+/// Matches `byte` against the class at the start of `pattern`, returning
+/// the length of the class if it matches. An unterminated class runs to the
+/// end of the pattern.
+fn match_class(pattern: &[u8], byte: u8) -> Option<usize> {
+    let mut i = 1;
+    let negated = pattern.get(i) == Some(&b'^');
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    while i < pattern.len() && pattern[i] != b']' {
+        if pattern[i] == b'\\' && i + 1 < pattern.len() {
+            matched |= pattern[i + 1] == byte;
+            i += 2;
+        } else if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' {
+            let (low, high) = (
+                pattern[i].min(pattern[i + 2]),
+                pattern[i].max(pattern[i + 2]),
+            );
+            matched |= (low..=high).contains(&byte);
+            i += 3;
+        } else {
+            matched |= pattern[i] == byte;
+            i += 1;
+        }
+    }
+    let len = (i + 1).min(pattern.len());
+    (matched != negated).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        matches(pattern.as_bytes(), text.as_bytes())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(glob("*", ""));
+        assert!(glob("h?llo", "hello"));
+        assert!(!glob("h?llo", "hllo"));
+        assert!(glob("h*llo", "hllo"));
+        assert!(glob("h*llo", "heeeello"));
+        assert!(glob("*a*b*", "xxaxxbxx"));
+        assert!(!glob("*a*b", "xxaxxbxx"));
+        assert!(glob("news.*", "news.tech"));
+        assert!(!glob("news.*", "weather"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(glob("h[ae]llo", "hallo"));
+        assert!(!glob("h[ae]llo", "hillo"));
+        assert!(glob("h[^e]llo", "hallo"));
+        assert!(!glob("h[^e]llo", "hello"));
+        assert!(glob("h[a-b]llo", "hbllo"));
+        assert!(glob("h[b-a]llo", "hallo"));
+        assert!(!glob("h[a-b]llo", "hcllo"));
+        assert!(glob("[a-]", "-"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(glob(r"a\*b", "a*b"));
+        assert!(!glob(r"a\*b", "axb"));
+        assert!(glob(r"[\]]", "]"));
+    }
+}
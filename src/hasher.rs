@@ -0,0 +1,174 @@
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Selects the hash function used for the keyspace.
+///
+/// The default is SipHash with a random seed drawn for every keyspace, so
+/// clients cannot precompute sets of colliding keys (HashDoS). FxHash is
+/// noticeably faster for short keys but has no seed, and should only be
+/// used when every client is trusted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyHasher {
+    #[default]
+    SipHash,
+    Fx,
+}
+
+impl FromStr for KeyHasher {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "siphash" => Ok(KeyHasher::SipHash),
+            "fx" | "fxhash" => Ok(KeyHasher::Fx),
+            _ => Err(format!("unknown key hasher '{s}'")),
+        }
+    }
+}
+
+impl KeyHasher {
+    /// The name it is selected by.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyHasher::SipHash => "siphash",
+            KeyHasher::Fx => "fx",
+        }
+    }
+
+    pub(crate) fn build(self) -> KeyHasherBuilder {
+        match self {
+            KeyHasher::SipHash => KeyHasherBuilder::SipHash(RandomState::new()),
+            KeyHasher::Fx => KeyHasherBuilder::Fx,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum KeyHasherBuilder {
+    SipHash(RandomState),
+    Fx,
+}
+
+impl BuildHasher for KeyHasherBuilder {
+    type Hasher = KeyHasherState;
+
+    fn build_hasher(&self) -> KeyHasherState {
+        match self {
+            KeyHasherBuilder::SipHash(state) => KeyHasherState::SipHash(state.build_hasher()),
+            KeyHasherBuilder::Fx => KeyHasherState::Fx(FxHasher::default()),
+        }
+    }
+}
+
+pub(crate) enum KeyHasherState {
+    SipHash(DefaultHasher),
+    Fx(FxHasher),
+}
+
+impl Hasher for KeyHasherState {
+    fn finish(&self) -> u64 {
+        match self {
+            KeyHasherState::SipHash(h) => h.finish(),
+            KeyHasherState::Fx(h) => h.finish(),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            KeyHasherState::SipHash(h) => h.write(bytes),
+            KeyHasherState::Fx(h) => h.write(bytes),
+        }
+    }
+}
+
+/// The position of `data` in the order SCAN cursors walk. The hash is
+/// seeded once per process, which keeps cursors valid for as long as the
+/// server runs, while clients cannot pick many keys with one position
+/// that SCAN would then have to return in a single batch.
+pub(crate) fn scan_hash(data: &[u8]) -> u64 {
+    static SEED: OnceLock<RandomState> = OnceLock::new();
+    let mut hasher = SEED.get_or_init(RandomState::new).build_hasher();
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// A random number, from the same source as the SipHash seeds.
+pub(crate) fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// The rustc "Fx" hash: a multiply and rotate per word of input.
+#[derive(Default)]
+pub(crate) struct FxHasher {
+    hash: u64,
+}
+
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add_to_hash(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for &byte in chunks.remainder() {
+            self.add_to_hash(u64::from(byte));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(builder: &KeyHasherBuilder, key: &str) -> u64 {
+        builder.hash_one(key)
+    }
+
+    #[test]
+    fn test_parse_key_hasher() {
+        assert_eq!("SipHash".parse::<KeyHasher>().unwrap(), KeyHasher::SipHash);
+        assert_eq!("fx".parse::<KeyHasher>().unwrap(), KeyHasher::Fx);
+        assert!("md5".parse::<KeyHasher>().is_err());
+    }
+
+    #[test]
+    fn test_siphash_seed_differs_per_keyspace() {
+        let a = KeyHasher::SipHash.build();
+        let b = KeyHasher::SipHash.build();
+
+        assert_eq!(hash(&a, "Foo"), hash(&a, "Foo"));
+        assert_ne!(hash(&a, "Foo"), hash(&b, "Foo"));
+    }
+
+    #[test]
+    fn test_fx_is_deterministic() {
+        let a = KeyHasher::Fx.build();
+        let b = KeyHasher::Fx.build();
+
+        assert_eq!(hash(&a, "Foo"), hash(&b, "Foo"));
+        assert_ne!(hash(&a, "Foo"), hash(&a, "Bar"));
+    }
+
+    #[test]
+    fn test_fx_hashes_every_byte() {
+        let builder = KeyHasher::Fx.build();
+
+        assert_ne!(
+            hash(&builder, "key:12345678a"),
+            hash(&builder, "key:12345678b")
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Per-command latency histograms.
+//!
+//! Samples are counted in log-linear buckets, as HDR histograms do: every
+//! power of two is split into 32 equal buckets, so a percentile is reported
+//! within about 3% of the true value however long the tail is, and memory
+//! use does not grow with the number of samples.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// log2 of the number of buckets per power of two.
+const SUB_BUCKET_BITS: u32 = 5;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+/// Enough buckets for any `u64` value.
+const BUCKETS: usize = ((64 - SUB_BUCKET_BITS as usize) + 1) * SUB_BUCKETS as usize;
+
+/// Latency samples in microseconds.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            counts: vec![0; BUCKETS],
+            total: 0,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn record(&mut self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        self.counts[bucket(micros)] += 1;
+        self.total += 1;
+    }
+
+    /// The number of samples recorded.
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// The latency in microseconds that `percentile` percent of samples
+    /// did not exceed, rounded up to the end of its bucket, or 0 if there
+    /// are no samples.
+    pub fn percentile(&self, percentile: f64) -> u64 {
+        if self.total == 0 {
+            return 0;
+        }
+        let wanted = ((percentile / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= wanted {
+                return bucket_end(index);
+            }
+        }
+        bucket_end(BUCKETS - 1)
+    }
+}
+
+fn bucket(value: u64) -> usize {
+    if value < 2 * SUB_BUCKETS {
+        return value as usize;
+    }
+    let shift = (63 - value.leading_zeros()) - SUB_BUCKET_BITS;
+    ((shift as u64 + 1) * SUB_BUCKETS + (value >> shift) - SUB_BUCKETS) as usize
+}
+
+/// The largest value that falls in bucket `index`.
+fn bucket_end(index: usize) -> u64 {
+    let index = index as u64;
+    if index < 2 * SUB_BUCKETS {
+        return index;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let mantissa = index % SUB_BUCKETS + SUB_BUCKETS;
+    ((mantissa + 1) << shift).wrapping_sub(1)
+}
+
+/// A histogram per command, shared by every connection.
+#[derive(Debug, Default)]
+pub struct Latencies {
+    commands: Mutex<HashMap<&'static str, Histogram>>,
+}
+
+impl Latencies {
+    pub fn record(&self, command: &'static str, latency: Duration) {
+        let mut commands = self.commands.lock().unwrap();
+        commands.entry(command).or_default().record(latency);
+    }
+
+    /// Copies of the histograms of the named commands, or of every command
+    /// that has been recorded if `names` is empty, sorted by name.
+    pub fn histograms(&self, names: &[String]) -> Vec<(&'static str, Histogram)> {
+        let commands = self.commands.lock().unwrap();
+        let mut found: Vec<_> = commands
+            .iter()
+            .filter(|(name, _)| names.is_empty() || names.iter().any(|n| n == *name))
+            .map(|(name, histogram)| (*name, histogram.clone()))
+            .collect();
+        found.sort_by_key(|(name, _)| *name);
+        found
+    }
+
+    /// Forgets the samples of the named commands, or of every command if
+    /// `names` is empty. Returns how many histograms were cleared.
+    pub fn reset(&self, names: &[String]) -> usize {
+        let mut commands = self.commands.lock().unwrap();
+        let before = commands.len();
+        commands.retain(|name, _| !names.is_empty() && !names.iter().any(|n| n == name));
+        before - commands.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_are_contiguous() {
+        for value in 0..100_000 {
+            let index = bucket(value);
+            assert!(bucket_end(index) >= value, "{value}");
+            if index > 0 {
+                assert!(bucket_end(index - 1) < value, "{value}");
+            }
+        }
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_end(BUCKETS - 1), u64::MAX);
+    }
+
+    #[test]
+    fn test_bucket_precision() {
+        for value in [100, 1_000, 123_456, 10_000_000] {
+            let end = bucket_end(bucket(value));
+            assert!((end - value) as f64 / value as f64 <= 1.0 / SUB_BUCKETS as f64);
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = Histogram::default();
+        for micros in 1..=1000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+
+        assert_eq!(histogram.len(), 1000);
+        assert_eq!(histogram.percentile(0.0), 1);
+        assert!((500..=516).contains(&histogram.percentile(50.0)));
+        assert!((990..=1023).contains(&histogram.percentile(99.0)));
+        assert!(histogram.percentile(100.0) >= 1000);
+        assert_eq!(Histogram::default().percentile(99.0), 0);
+    }
+
+    #[test]
+    fn test_latencies_per_command() {
+        let latencies = Latencies::default();
+        latencies.record("get", Duration::from_micros(10));
+        latencies.record("get", Duration::from_micros(20));
+        latencies.record("set", Duration::from_micros(30));
+
+        let all = latencies.histograms(&[]);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].0, "get");
+        assert_eq!(all[0].1.len(), 2);
+        assert_eq!(latencies.histograms(&["set".into()]).len(), 1);
+
+        assert_eq!(latencies.reset(&["get".into()]), 1);
+        assert_eq!(latencies.histograms(&[])[0].0, "set");
+        assert_eq!(latencies.reset(&[]), 1);
+        assert!(latencies.histograms(&[]).is_empty());
+    }
+}
@@ -1 +1,37 @@
+pub mod aof;
+pub mod cdc;
+#[cfg(feature = "server")]
+pub mod client;
+#[cfg(feature = "server")]
+pub mod cmd;
+pub mod config;
+#[cfg(feature = "server")]
+pub mod connection;
 pub mod db;
+pub mod export;
+pub mod float;
+#[cfg(feature = "server")]
+pub mod frame_writer;
+pub mod glob;
+pub mod hasher;
+pub mod latency;
+#[cfg(test)]
+mod model;
+pub mod net;
+#[cfg(feature = "server")]
+pub mod probe;
+#[cfg(feature = "server")]
+pub mod pubsub;
+pub mod range;
+pub mod resp;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "server")]
+pub mod shared;
+pub mod signal;
+pub mod stats;
+pub mod time;
+pub mod value;
+pub mod zset;
+
+pub use value::Value;
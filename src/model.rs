@@ -0,0 +1,463 @@
+//! Randomised tests running the same operations against [`Db`] and against
+//! a deliberately simple model of it, comparing every result and then the
+//! whole visible state after each step.
+
+use bytes::Bytes;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::db::{Db, ExpireCondition, ListEnd, SetCondition, SetExpiry, WrongType};
+use crate::value::Value;
+
+const STEPS: usize = 300;
+const SEEDS: u64 = 40;
+
+/// xorshift64*, which is plenty for picking operations.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn chance(&mut self, one_in: u64) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn bytes(&mut self) -> Vec<u8> {
+        (0..self.below(4))
+            .map(|_| b'a' + self.below(3) as u8)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ModelValue {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Hash(BTreeMap<String, Vec<u8>>),
+}
+
+/// The keyspace as a plain map. TTLs are whole seconds relative to the
+/// start of the test: negative ones have already passed, positive ones are
+/// at least an hour away, so no key expires while the test runs.
+#[derive(Debug, Default)]
+struct Model {
+    entries: BTreeMap<String, (ModelValue, Option<i64>)>,
+}
+
+impl Model {
+    fn live(&self, key: &str) -> Option<&(ModelValue, Option<i64>)> {
+        self.entries
+            .get(key)
+            .filter(|(_, ttl)| !ttl.is_some_and(|ttl| ttl < 0))
+    }
+
+    fn live_mut(&mut self, key: &str) -> Option<&mut (ModelValue, Option<i64>)> {
+        if self.live(key).is_none() {
+            self.entries.remove(key);
+        }
+        self.entries.get_mut(key)
+    }
+
+    fn remove_if_empty(&mut self, key: &str) {
+        let empty = match self.entries.get(key) {
+            Some((ModelValue::List(list), _)) => list.is_empty(),
+            Some((ModelValue::Hash(hash), _)) => hash.is_empty(),
+            _ => false,
+        };
+        if empty {
+            self.entries.remove(key);
+        }
+    }
+}
+
+/// Runs operations on `keys` against both, with instants derived from
+/// `base`, panicking on the first difference.
+struct Harness<'a> {
+    db: &'a Db,
+    model: Model,
+    keys: Vec<String>,
+    base: Instant,
+    rng: Rng,
+}
+
+impl Harness<'_> {
+    fn instant(&self, ttl: i64) -> Instant {
+        if ttl < 0 {
+            self.base
+                .checked_sub(Duration::from_secs(1))
+                .unwrap_or(self.base)
+        } else {
+            self.base + Duration::from_secs(ttl as u64)
+        }
+    }
+
+    fn ttl(&mut self) -> i64 {
+        if self.rng.chance(3) {
+            -1
+        } else {
+            3600 + self.rng.below(100) as i64
+        }
+    }
+
+    fn key(&mut self) -> String {
+        self.keys[self.rng.below(self.keys.len() as u64) as usize].clone()
+    }
+
+    fn step(&mut self) -> String {
+        let key = self.key();
+        match self.rng.below(12) {
+            0 => {
+                let value = self.rng.bytes();
+                let ttl = if self.rng.chance(2) {
+                    None
+                } else {
+                    Some(self.ttl())
+                };
+                let expiry = ttl.map_or(SetExpiry::Clear, |ttl| SetExpiry::At(self.instant(ttl)));
+                let written = self
+                    .db
+                    .set_with(
+                        key.clone(),
+                        value.clone().into(),
+                        SetCondition::Always,
+                        expiry,
+                        false,
+                    )
+                    .unwrap()
+                    .0;
+                assert!(written);
+                self.model
+                    .entries
+                    .insert(key.clone(), (ModelValue::String(value), ttl));
+                format!("set {key} {ttl:?}")
+            }
+            1 => {
+                let expected = match self.model.live(&key) {
+                    Some((ModelValue::String(data), _)) => Some(Bytes::from(data.clone())),
+                    _ => None,
+                };
+                assert_eq!(self.db.get(&key), expected, "get {key}");
+                format!("get {key}")
+            }
+            2 => {
+                let other = self.key();
+                let keys = vec![key.clone(), other.clone()];
+                let mut expected = 0;
+                for key in [&key, &other] {
+                    if self.model.live(key).is_some() {
+                        expected += 1;
+                    }
+                    self.model.entries.remove(key.as_str());
+                }
+                assert_eq!(self.db.del(&keys), expected, "del {key} {other}");
+                format!("del {key} {other}")
+            }
+            3 => {
+                assert_eq!(
+                    self.db.exists(&key),
+                    self.model.live(&key).is_some(),
+                    "exists {key}"
+                );
+                format!("exists {key}")
+            }
+            4 => {
+                let ttl = self.ttl();
+                let condition = match self.rng.below(5) {
+                    0 => ExpireCondition::default(),
+                    1 => ExpireCondition {
+                        nx: true,
+                        ..Default::default()
+                    },
+                    2 => ExpireCondition {
+                        xx: true,
+                        ..Default::default()
+                    },
+                    3 => ExpireCondition {
+                        gt: true,
+                        ..Default::default()
+                    },
+                    _ => ExpireCondition {
+                        lt: true,
+                        ..Default::default()
+                    },
+                };
+                let expected = match self.model.live_mut(&key) {
+                    None => false,
+                    Some((_, current)) => {
+                        let allowed = match *current {
+                            None => !condition.xx && !condition.gt,
+                            Some(current) => {
+                                !condition.nx
+                                    && (!condition.gt || ttl > current)
+                                    && (!condition.lt || ttl < current)
+                            }
+                        };
+                        if allowed {
+                            *current = Some(ttl);
+                        }
+                        allowed
+                    }
+                };
+                if expected && ttl < 0 {
+                    self.model.entries.remove(&key);
+                }
+                let changed = self.db.expire(&key, self.instant(ttl), condition);
+                assert_eq!(changed, expected, "expire {key} {ttl} {condition:?}");
+                format!("expire {key} {ttl} {condition:?}")
+            }
+            5 => {
+                let expected = match self.model.live_mut(&key) {
+                    Some((_, ttl)) => ttl.take().is_some(),
+                    None => false,
+                };
+                assert_eq!(self.db.persist(&key), expected, "persist {key}");
+                format!("persist {key}")
+            }
+            6 => {
+                let offset = self.rng.below(6) as usize;
+                let data = self.rng.bytes();
+                let expected = match self.model.live_mut(&key) {
+                    None if data.is_empty() => Ok(0),
+                    None => {
+                        let mut value = vec![0; offset];
+                        value.extend_from_slice(&data);
+                        let len = value.len();
+                        self.model
+                            .entries
+                            .insert(key.clone(), (ModelValue::String(value), None));
+                        Ok(len)
+                    }
+                    Some((ModelValue::String(value), _)) => {
+                        if !data.is_empty() {
+                            if value.len() < offset + data.len() {
+                                value.resize(offset + data.len(), 0);
+                            }
+                            value[offset..offset + data.len()].copy_from_slice(&data);
+                        }
+                        Ok(value.len())
+                    }
+                    Some(_) => Err(WrongType),
+                };
+                assert_eq!(
+                    self.db.set_range(&key, offset, &data),
+                    expected,
+                    "setrange {key}"
+                );
+                format!("setrange {key} {offset} {data:?}")
+            }
+            7 => {
+                let end = if self.rng.chance(2) {
+                    ListEnd::Left
+                } else {
+                    ListEnd::Right
+                };
+                let values: Vec<_> = (0..1 + self.rng.below(3))
+                    .map(|_| self.rng.bytes())
+                    .collect();
+                let expected = match self.model.live_mut(&key) {
+                    None => {
+                        self.model
+                            .entries
+                            .insert(key.clone(), (ModelValue::List(VecDeque::new()), None));
+                        self.model.entries.get_mut(&key)
+                    }
+                    entry => entry,
+                };
+                let expected = match expected {
+                    Some((ModelValue::List(list), _)) => {
+                        for value in &values {
+                            match end {
+                                ListEnd::Left => list.push_front(value.clone()),
+                                ListEnd::Right => list.push_back(value.clone()),
+                            }
+                        }
+                        Ok(list.len())
+                    }
+                    _ => Err(WrongType),
+                };
+                let values = values.into_iter().map(Bytes::from).collect();
+                assert_eq!(self.db.push(&key, end, values), expected, "push {key}");
+                format!("push {key} {end:?}")
+            }
+            8 => {
+                let end = if self.rng.chance(2) {
+                    ListEnd::Left
+                } else {
+                    ListEnd::Right
+                };
+                let count = self.rng.below(3) as usize;
+                let expected = match self.model.live_mut(&key) {
+                    None => Ok(None),
+                    Some((ModelValue::List(list), _)) => {
+                        let popped: Vec<_> = (0..count)
+                            .map_while(|_| match end {
+                                ListEnd::Left => list.pop_front(),
+                                ListEnd::Right => list.pop_back(),
+                            })
+                            .map(Bytes::from)
+                            .collect();
+                        Ok(Some(popped))
+                    }
+                    Some(_) => Err(WrongType),
+                };
+                self.model.remove_if_empty(&key);
+                assert_eq!(self.db.pop(&key, end, count), expected, "pop {key} {count}");
+                format!("pop {key} {end:?} {count}")
+            }
+            9 => {
+                let field = String::from_utf8(self.rng.bytes()).unwrap();
+                let value = self.rng.bytes();
+                let expected = match self.model.live_mut(&key) {
+                    None => {
+                        self.model
+                            .entries
+                            .insert(key.clone(), (ModelValue::Hash(BTreeMap::new()), None));
+                        self.model.entries.get_mut(&key)
+                    }
+                    entry => entry,
+                };
+                let expected = match expected {
+                    Some((ModelValue::Hash(hash), _)) => {
+                        Ok(hash.insert(field.clone(), value.clone()).is_none() as u64)
+                    }
+                    _ => Err(WrongType),
+                };
+                let fields = vec![(field.clone(), Bytes::from(value))];
+                assert_eq!(self.db.hset(&key, fields), expected, "hset {key} {field}");
+                format!("hset {key} {field}")
+            }
+            10 => {
+                let field = String::from_utf8(self.rng.bytes()).unwrap();
+                let expected = match self.model.live_mut(&key) {
+                    None => Ok(0),
+                    Some((ModelValue::Hash(hash), _)) => Ok(hash.remove(&field).is_some() as u64),
+                    Some(_) => Err(WrongType),
+                };
+                self.model.remove_if_empty(&key);
+                assert_eq!(
+                    self.db.hdel(&key, std::slice::from_ref(&field)),
+                    expected,
+                    "hdel {key} {field}"
+                );
+                format!("hdel {key} {field}")
+            }
+            _ => {
+                self.db.purge_expired(usize::MAX);
+                "purge".to_string()
+            }
+        }
+    }
+
+    /// Compares every key the harness uses, as [`Db::key_snapshot`] sees it.
+    fn check_state(&self, history: &[String]) {
+        for key in &self.keys {
+            let actual = self
+                .db
+                .key_snapshot(key)
+                .map(|snapshot| (snapshot.value, snapshot.ttl.is_some()));
+            let expected = self
+                .model
+                .live(key)
+                .map(|(value, ttl)| (to_value(value), ttl.is_some()));
+            assert_eq!(actual, expected, "state of {key} after {history:#?}");
+        }
+    }
+
+    fn run(&mut self, steps: usize) {
+        let mut history = Vec::new();
+        for _ in 0..steps {
+            history.push(self.step());
+            self.check_state(&history);
+        }
+    }
+}
+
+fn to_value(value: &ModelValue) -> Value {
+    match value {
+        ModelValue::String(data) => Value::String(data.clone().into()),
+        ModelValue::List(list) => Value::List(list.iter().cloned().map(Bytes::from).collect()),
+        ModelValue::Hash(hash) => Value::Hash(
+            hash.iter()
+                .map(|(field, value)| (field.clone(), Bytes::from(value.clone())))
+                .collect(),
+        ),
+    }
+}
+
+fn harness<'a>(db: &'a Db, prefix: &str, seed: u64) -> Harness<'a> {
+    Harness {
+        db,
+        model: Model::default(),
+        keys: ["a", "b", "c"]
+            .iter()
+            .map(|k| format!("{prefix}{k}"))
+            .collect(),
+        base: Instant::now(),
+        rng: Rng::new(seed),
+    }
+}
+
+#[test]
+fn test_db_matches_model() {
+    for seed in 0..SEEDS {
+        let db = Db::new();
+        harness(&db, "", seed).run(STEPS);
+    }
+}
+
+#[test]
+fn test_db_matches_model_across_threads() {
+    // Each thread has keys of its own, so its model stays exact while the
+    // threads contend for the same lock.
+    let db = Db::new();
+    let threads: Vec<_> = (0..4)
+        .map(|n| {
+            let db = db.clone();
+            thread::spawn(move || harness(&db, &format!("t{n}:"), 1000 + n).run(STEPS))
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn test_concurrent_pushes_are_not_lost_or_reordered() {
+    let db = Arc::new(Db::new());
+    let threads: Vec<_> = (0..4u8)
+        .map(|n| {
+            let db = db.clone();
+            thread::spawn(move || {
+                for i in 0..100u8 {
+                    db.push("List", ListEnd::Right, vec![Bytes::from(vec![n, i])])
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let list = db.lrange("List", 0, -1).unwrap();
+    assert_eq!(list.len(), 400);
+    for n in 0..4u8 {
+        let pushed: Vec<_> = list.iter().filter(|e| e[0] == n).map(|e| e[1]).collect();
+        assert_eq!(pushed, (0..100).collect::<Vec<_>>());
+    }
+}
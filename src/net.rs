@@ -0,0 +1,40 @@
+use std::net::{IpAddr, SocketAddr};
+
+/// Converts IPv4-mapped IPv6 peer addresses, as reported by dual-stack
+/// listeners (e.g. `[::ffff:10.0.0.1]:5000`), back to their IPv4 form so
+/// clients are shown the same way whichever listener they arrived on.
+pub fn normalize_peer_addr(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => SocketAddr::new(IpAddr::V4(v4), addr.port()),
+            None => addr,
+        },
+        IpAddr::V4(_) => addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_ipv4_mapped_addr() {
+        let addr: SocketAddr = "[::ffff:10.0.0.1]:5000".parse().unwrap();
+
+        assert_eq!(normalize_peer_addr(addr).to_string(), "10.0.0.1:5000");
+    }
+
+    #[test]
+    fn test_normalize_keeps_ipv6_addr() {
+        let addr: SocketAddr = "[2001:db8::1]:5000".parse().unwrap();
+
+        assert_eq!(normalize_peer_addr(addr).to_string(), "[2001:db8::1]:5000");
+    }
+
+    #[test]
+    fn test_normalize_keeps_ipv4_addr() {
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+
+        assert_eq!(normalize_peer_addr(addr), addr);
+    }
+}
@@ -0,0 +1,97 @@
+//! A health check listener for orchestrators such as Kubernetes, kept apart
+//! from the client listeners so that probes are answered straight away
+//! however busy the server is.
+//!
+//! A probe connection may send an HTTP request, answered `200 OK` for any
+//! path except `/ready` and `/readyz`, which answer `503 Service
+//! Unavailable` once the server has stopped taking new work, or a RESP or
+//! inline `PING`, answered `+PONG`. The connection is closed after one reply.
+
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// Longest a probe may take to send its request.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Serves probes from `listener` until the task is dropped. The server is
+/// reported ready while `shutdown` is `false`.
+pub async fn serve(listener: TcpListener, shutdown: watch::Receiver<bool>) {
+    loop {
+        match listener.accept().await {
+            Ok((socket, _)) => {
+                tokio::spawn(answer(socket, shutdown.clone()));
+            }
+            Err(err) => {
+                println!("error: {err}");
+            }
+        }
+    }
+}
+
+async fn answer(mut socket: TcpStream, shutdown: watch::Receiver<bool>) -> io::Result<()> {
+    let mut buf = [0; 1024];
+    let len = tokio::time::timeout(PROBE_TIMEOUT, socket.read(&mut buf))
+        .await
+        .map_err(|_| io::ErrorKind::TimedOut)??;
+
+    let ready = !*shutdown.borrow();
+    socket.write_all(reply(&buf[..len], ready)).await?;
+    socket.shutdown().await
+}
+
+fn reply(request: &[u8], ready: bool) -> &'static [u8] {
+    let Some(target) = request.strip_prefix(b"GET ") else {
+        return b"+PONG\r\n";
+    };
+    let path = target.split(|&b| b == b' ').next().unwrap_or_default();
+    if matches!(path, b"/ready" | b"/readyz") && !ready {
+        b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    } else {
+        b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_probe() {
+        assert_eq!(reply(b"*1\r\n$4\r\nPING\r\n", true), b"+PONG\r\n");
+        assert_eq!(reply(b"PING\r\n", false), b"+PONG\r\n");
+    }
+
+    #[test]
+    fn test_http_probes() {
+        let live = b"GET /healthz HTTP/1.1\r\nHost: x\r\n\r\n";
+        let ready = b"GET /readyz HTTP/1.1\r\nHost: x\r\n\r\n";
+
+        assert!(reply(live, false).starts_with(b"HTTP/1.1 200"));
+        assert!(reply(ready, true).starts_with(b"HTTP/1.1 200"));
+        assert!(reply(ready, false).starts_with(b"HTTP/1.1 503"));
+    }
+
+    #[tokio::test]
+    async fn test_serve_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (notify, shutdown) = watch::channel(false);
+        tokio::spawn(serve(listener, shutdown));
+
+        let probe = |request: &'static [u8]| async move {
+            let mut socket = TcpStream::connect(addr).await.unwrap();
+            socket.write_all(request).await.unwrap();
+            let mut reply = Vec::new();
+            socket.read_to_end(&mut reply).await.unwrap();
+            reply
+        };
+
+        assert_eq!(probe(b"PING\r\n").await, b"+PONG\r\n");
+        notify.send(true).unwrap();
+        let reply = probe(b"GET /readyz HTTP/1.1\r\n\r\n").await;
+        assert!(reply.starts_with(b"HTTP/1.1 503"));
+    }
+}
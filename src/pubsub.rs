@@ -0,0 +1,335 @@
+//! Publish/subscribe: channels and channel patterns that clients subscribe
+//! to, and the messages published on them.
+
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use crate::glob;
+use crate::resp::Frame;
+
+/// Messages a channel holds for its slowest subscriber before that
+/// subscriber is considered lost.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Messages waiting to be written to one subscribed client.
+const DELIVERY_CAPACITY: usize = 128;
+
+/// Every channel and pattern somebody is subscribed to.
+#[derive(Debug, Default)]
+pub struct PubSub {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    channels: HashMap<Bytes, Channel>,
+    patterns: HashMap<Bytes, Channel>,
+}
+
+impl Inner {
+    fn subscriptions(&mut self, kind: Kind) -> &mut HashMap<Bytes, Channel> {
+        match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+        }
+    }
+}
+
+/// Whether a subscription is to a channel name or a glob pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Channel,
+    Pattern,
+}
+
+#[derive(Debug)]
+struct Channel {
+    sender: broadcast::Sender<Published>,
+    /// Kept separately from `sender.receiver_count()`, which only drops once
+    /// an unsubscribed client's forwarding task has actually finished.
+    subscribers: usize,
+}
+
+/// What is broadcast to subscribers: pattern subscribers need to know which
+/// channel the message was published on.
+#[derive(Debug, Clone)]
+struct Published {
+    channel: Bytes,
+    payload: Bytes,
+}
+
+impl PubSub {
+    /// Sends `message` to everyone subscribed to `channel` or to a pattern
+    /// matching it, returning how many subscriptions received it.
+    pub fn publish(&self, channel: &[u8], message: Bytes) -> usize {
+        let inner = self.inner.lock().unwrap();
+        let published = Published {
+            channel: Bytes::copy_from_slice(channel),
+            payload: message,
+        };
+        let patterns = inner
+            .patterns
+            .iter()
+            .filter(|(pattern, _)| glob::matches(pattern, channel))
+            .map(|(_, subscribers)| subscribers);
+        let mut received = 0;
+        for subscribers in inner.channels.get(channel).into_iter().chain(patterns) {
+            let _ = subscribers.sender.send(published.clone());
+            received += subscribers.subscribers;
+        }
+        received
+    }
+
+    fn subscribe(&self, kind: Kind, name: Bytes) -> broadcast::Receiver<Published> {
+        let mut inner = self.inner.lock().unwrap();
+        let channel = inner
+            .subscriptions(kind)
+            .entry(name)
+            .or_insert_with(|| Channel {
+                sender: broadcast::channel(CHANNEL_CAPACITY).0,
+                subscribers: 0,
+            });
+        channel.subscribers += 1;
+        channel.sender.subscribe()
+    }
+
+    fn unsubscribe(&self, kind: Kind, name: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        let subscriptions = inner.subscriptions(kind);
+        if let Some(channel) = subscriptions.get_mut(name) {
+            channel.subscribers -= 1;
+            if channel.subscribers == 0 {
+                subscriptions.remove(name);
+            }
+        }
+    }
+}
+
+/// A message published on a channel, as delivered to a subscriber.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// The pattern it was received through, for pattern subscriptions.
+    pub pattern: Option<Bytes>,
+    pub channel: Bytes,
+    pub payload: Bytes,
+}
+
+impl Message {
+    /// The push frame sent to the subscriber, a `message` or, for pattern
+    /// subscriptions, a `pmessage`.
+    pub fn to_frame(&self) -> Frame {
+        let mut frame = match &self.pattern {
+            None => vec![Frame::BulkString(Bytes::from_static(b"message"))],
+            Some(pattern) => vec![
+                Frame::BulkString(Bytes::from_static(b"pmessage")),
+                Frame::BulkString(pattern.clone()),
+            ],
+        };
+        frame.push(Frame::BulkString(self.channel.clone()));
+        frame.push(Frame::BulkString(self.payload.clone()));
+        Frame::Array(frame)
+    }
+}
+
+/// Returned when a subscriber fell so far behind that messages were lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lagged;
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("subscriber could not keep up with published messages")
+    }
+}
+
+/// The channels and patterns one client is subscribed to. Each has a task
+/// forwarding its messages into a single queue that the connection reads
+/// from, and every subscription is dropped along with this.
+#[derive(Debug)]
+pub struct Subscriptions {
+    pubsub: Arc<PubSub>,
+    channels: HashMap<Bytes, JoinHandle<()>>,
+    patterns: HashMap<Bytes, JoinHandle<()>>,
+    sender: mpsc::Sender<Result<Message, Lagged>>,
+    receiver: mpsc::Receiver<Result<Message, Lagged>>,
+}
+
+impl Subscriptions {
+    pub fn new(pubsub: Arc<PubSub>) -> Subscriptions {
+        let (sender, receiver) = mpsc::channel(DELIVERY_CAPACITY);
+        Subscriptions {
+            pubsub,
+            channels: HashMap::new(),
+            patterns: HashMap::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// The number of channels and patterns subscribed to. While it is not
+    /// zero the client may only run pub/sub commands.
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// The number of channels or patterns subscribed to.
+    pub fn len(&self, kind: Kind) -> usize {
+        self.tasks(kind).len()
+    }
+
+    /// The subscribed channels or patterns, in no particular order.
+    pub fn names(&self, kind: Kind) -> Vec<Bytes> {
+        self.tasks(kind).keys().cloned().collect()
+    }
+
+    /// Subscribes to a channel or pattern unless already subscribed,
+    /// returning the number of subscriptions afterwards.
+    pub fn subscribe(&mut self, kind: Kind, name: Bytes) -> usize {
+        if !self.tasks(kind).contains_key(&name) {
+            let receiver = self.pubsub.subscribe(kind, name.clone());
+            let pattern = (kind == Kind::Pattern).then(|| name.clone());
+            let task = tokio::spawn(forward(pattern, receiver, self.sender.clone()));
+            self.tasks_mut(kind).insert(name, task);
+        }
+        self.count()
+    }
+
+    /// Unsubscribes from a channel or pattern if subscribed, returning the
+    /// number of subscriptions afterwards.
+    pub fn unsubscribe(&mut self, kind: Kind, name: &[u8]) -> usize {
+        if let Some(task) = self.tasks_mut(kind).remove(name) {
+            task.abort();
+            self.pubsub.unsubscribe(kind, name);
+        }
+        self.count()
+    }
+
+    /// Waits for the next message on any subscription. Never completes
+    /// while there are none.
+    pub async fn recv(&mut self) -> Result<Message, Lagged> {
+        // The queue cannot close, as it holds a sender itself.
+        self.receiver.recv().await.unwrap_or(Err(Lagged))
+    }
+
+    fn tasks(&self, kind: Kind) -> &HashMap<Bytes, JoinHandle<()>> {
+        match kind {
+            Kind::Channel => &self.channels,
+            Kind::Pattern => &self.patterns,
+        }
+    }
+
+    fn tasks_mut(&mut self, kind: Kind) -> &mut HashMap<Bytes, JoinHandle<()>> {
+        match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+        }
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        for (kind, tasks) in [
+            (Kind::Channel, &mut self.channels),
+            (Kind::Pattern, &mut self.patterns),
+        ] {
+            for (name, task) in tasks.drain() {
+                task.abort();
+                self.pubsub.unsubscribe(kind, &name);
+            }
+        }
+    }
+}
+
+async fn forward(
+    pattern: Option<Bytes>,
+    mut receiver: broadcast::Receiver<Published>,
+    sender: mpsc::Sender<Result<Message, Lagged>>,
+) {
+    loop {
+        let delivery = match receiver.recv().await {
+            Ok(published) => Ok(Message {
+                pattern: pattern.clone(),
+                channel: published.channel,
+                payload: published.payload,
+            }),
+            Err(broadcast::error::RecvError::Lagged(_)) => Err(Lagged),
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if sender.send(delivery).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscribers() {
+        let pubsub = Arc::new(PubSub::default());
+        let mut first = Subscriptions::new(pubsub.clone());
+        let mut second = Subscriptions::new(pubsub.clone());
+        first.subscribe(Kind::Channel, Bytes::from("news"));
+        second.subscribe(Kind::Channel, Bytes::from("news"));
+        second.subscribe(Kind::Channel, Bytes::from("weather"));
+
+        assert_eq!(pubsub.publish(b"news", Bytes::from("hello")), 2);
+        assert_eq!(pubsub.publish(b"sport", Bytes::from("ignored")), 0);
+
+        let expected = Message {
+            pattern: None,
+            channel: Bytes::from("news"),
+            payload: Bytes::from("hello"),
+        };
+        assert_eq!(first.recv().await, Ok(expected.clone()));
+        assert_eq!(second.recv().await, Ok(expected));
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_and_drop_forget_channels() {
+        let pubsub = Arc::new(PubSub::default());
+        let mut subs = Subscriptions::new(pubsub.clone());
+        assert_eq!(subs.subscribe(Kind::Channel, Bytes::from("a")), 1);
+        assert_eq!(subs.subscribe(Kind::Channel, Bytes::from("a")), 1);
+        assert_eq!(subs.subscribe(Kind::Channel, Bytes::from("b")), 2);
+
+        assert_eq!(subs.unsubscribe(Kind::Channel, b"a"), 1);
+        assert_eq!(subs.unsubscribe(Kind::Channel, b"a"), 1);
+        assert_eq!(pubsub.publish(b"a", Bytes::from("m")), 0);
+
+        drop(subs);
+        assert_eq!(pubsub.publish(b"b", Bytes::from("m")), 0);
+        assert!(pubsub.inner.lock().unwrap().channels.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pattern_subscriptions() {
+        let pubsub = Arc::new(PubSub::default());
+        let mut subs = Subscriptions::new(pubsub.clone());
+        subs.subscribe(Kind::Channel, Bytes::from("news.tech"));
+        subs.subscribe(Kind::Pattern, Bytes::from("news.*"));
+        assert_eq!(subs.subscribe(Kind::Pattern, Bytes::from("*")), 3);
+
+        // Each matching subscription gets its own copy.
+        assert_eq!(pubsub.publish(b"news.tech", Bytes::from("m")), 3);
+        assert_eq!(pubsub.publish(b"weather", Bytes::from("m")), 1);
+
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            frames.push(subs.recv().await.unwrap().to_frame());
+        }
+        let pmessage = Frame::Array(
+            ["pmessage", "news.*", "news.tech", "m"]
+                .map(|s| Frame::BulkString(Bytes::from(s)))
+                .to_vec(),
+        );
+        assert!(frames.contains(&pmessage));
+
+        assert_eq!(subs.unsubscribe(Kind::Pattern, b"*"), 2);
+        assert_eq!(pubsub.publish(b"weather", Bytes::from("m")), 0);
+    }
+}
@@ -0,0 +1,92 @@
+/// Normalizes a Redis-style inclusive `start..=end` index pair against a
+/// sequence of length `len`.
+///
+/// Negative indexes count from the end, so `-1` is the last element. Both
+/// ends are clamped to the sequence, and `None` is returned when the
+/// resulting range is empty.
+pub fn normalize(start: i64, end: i64, len: usize) -> Option<(usize, usize)> {
+    let len = i64::try_from(len).unwrap_or(i64::MAX);
+
+    let start = if start < 0 { start + len } else { start };
+    let end = if end < 0 { end + len } else { end };
+    let start = start.max(0);
+
+    if start > end || start >= len {
+        return None;
+    }
+
+    let end = end.min(len - 1);
+    Some((start as usize, end as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_full_range() {
+        assert_eq!(normalize(0, -1, 5), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_normalize_positive_indexes() {
+        assert_eq!(normalize(1, 3, 5), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_normalize_negative_indexes() {
+        assert_eq!(normalize(-3, -2, 5), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_normalize_single_element() {
+        assert_eq!(normalize(2, 2, 5), Some((2, 2)));
+        assert_eq!(normalize(-1, -1, 5), Some((4, 4)));
+    }
+
+    #[test]
+    fn test_normalize_clamps_end_past_length() {
+        assert_eq!(normalize(0, 100, 5), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_normalize_clamps_start_before_beginning() {
+        assert_eq!(normalize(-100, 2, 5), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_normalize_clamps_both_ends() {
+        assert_eq!(normalize(-100, 100, 5), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_normalize_start_after_end_is_empty() {
+        assert_eq!(normalize(3, 1, 5), None);
+        assert_eq!(normalize(-1, -3, 5), None);
+    }
+
+    #[test]
+    fn test_normalize_start_past_length_is_empty() {
+        assert_eq!(normalize(5, 10, 5), None);
+    }
+
+    #[test]
+    fn test_normalize_end_before_beginning_is_empty() {
+        assert_eq!(normalize(0, -100, 5), None);
+        assert_eq!(normalize(-100, -50, 5), None);
+    }
+
+    #[test]
+    fn test_normalize_empty_sequence() {
+        assert_eq!(normalize(0, -1, 0), None);
+        assert_eq!(normalize(0, 0, 0), None);
+        assert_eq!(normalize(-1, -1, 0), None);
+    }
+
+    #[test]
+    fn test_normalize_extreme_indexes() {
+        assert_eq!(normalize(i64::MIN, i64::MAX, 5), Some((0, 4)));
+        assert_eq!(normalize(i64::MAX, i64::MAX, 5), None);
+        assert_eq!(normalize(i64::MIN, i64::MIN, 5), None);
+    }
+}
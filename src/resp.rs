@@ -0,0 +1,471 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use std::fmt;
+use std::str;
+
+/// Largest bulk string accepted from a client, matching Redis'
+/// proto-max-bulk-len default.
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// How deeply arrays may nest, so that parsing a hostile frame cannot run
+/// out of stack.
+const MAX_DEPTH: usize = 128;
+
+/// A single RESP (REdis Serialization Protocol) value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    SimpleString(String),
+    Error(String),
+    Integer(i64),
+    BulkString(Bytes),
+    Array(Vec<Frame>),
+    Null,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The buffer holds the start of a frame but not all of it yet.
+    Incomplete,
+    /// The buffer does not contain valid RESP.
+    Invalid(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Incomplete => f.write_str("incomplete frame"),
+            ParseError::Invalid(msg) => write!(f, "Protocol error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Follows a frame as it arrives in pieces, so that each read only looks
+/// at the bytes it added. Only prefixes and lengths are checked; the frame
+/// is parsed with [`Frame::parse`] once all of it is there.
+#[derive(Debug, Default)]
+pub struct Scanner {
+    /// Where the next element starts.
+    pos: usize,
+    /// How many elements are still to come in each array open at `pos`.
+    open: Vec<usize>,
+    /// Up to where the current line has been searched for its CRLF.
+    searched: usize,
+}
+
+impl Scanner {
+    /// Returns the length of the frame at the start of `buf` once it has
+    /// been received in full. Each call must be given the bytes the last
+    /// one was, with any more that came since appended.
+    pub fn scan(&mut self, buf: &[u8]) -> Result<Option<usize>, ParseError> {
+        loop {
+            if self.open.is_empty() && self.pos > 0 {
+                return Ok((buf.len() >= self.pos).then_some(self.pos));
+            }
+            let Some(&prefix) = buf.get(self.pos) else {
+                return Ok(None);
+            };
+            if !b"+-:$*".contains(&prefix) {
+                return Err(unexpected(prefix));
+            }
+            let start = self.pos + 1;
+            let from = self.searched.max(start);
+            let Some(end) = buf[from..].windows(2).position(|w| w == b"\r\n") else {
+                // A CR at the very end may be followed by its LF.
+                self.searched = buf.len().saturating_sub(1).max(start);
+                return Ok(None);
+            };
+            let next = from + end + 2;
+            let mut parser = Parser {
+                buf: &buf[..next],
+                pos: start,
+                depth: self.open.len(),
+            };
+            self.pos = next;
+            match prefix {
+                b'$' => match parser.length()? {
+                    Some(len) if len > MAX_BULK_LEN => {
+                        return Err(ParseError::Invalid("invalid bulk length".into()));
+                    }
+                    Some(len) => self.pos += len + 2,
+                    None => {}
+                },
+                b'*' => match parser.length()? {
+                    Some(_) if self.open.len() == MAX_DEPTH => {
+                        return Err(ParseError::Invalid("too many nested arrays".into()));
+                    }
+                    Some(count) if count > 0 => {
+                        self.open.push(count);
+                        continue;
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+            // The element is done, and with it any arrays it was the last
+            // element of.
+            while let Some(left) = self.open.last_mut() {
+                *left -= 1;
+                if *left > 0 {
+                    break;
+                }
+                self.open.pop();
+            }
+        }
+    }
+}
+
+impl Frame {
+    /// Parses one frame from the start of `buf`, returning it together with
+    /// the number of bytes it occupied. Returns `ParseError::Incomplete` if
+    /// more data is needed, in which case the caller should read more and
+    /// try again with the same starting point.
+    pub fn parse(buf: &[u8]) -> Result<(Frame, usize), ParseError> {
+        let mut parser = Parser {
+            buf,
+            pos: 0,
+            depth: 0,
+        };
+        let frame = parser.frame()?;
+        Ok((frame, parser.pos))
+    }
+
+    /// Appends the RESP encoding of this frame to `dst`.
+    pub fn encode(&self, dst: &mut BytesMut) {
+        match self {
+            Frame::SimpleString(s) => {
+                dst.put_u8(b'+');
+                dst.put_slice(s.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Error(msg) => {
+                dst.put_u8(b'-');
+                dst.put_slice(msg.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Integer(n) => {
+                dst.put_slice(format!(":{n}\r\n").as_bytes());
+            }
+            Frame::BulkString(data) => {
+                dst.put_slice(format!("${}\r\n", data.len()).as_bytes());
+                dst.put_slice(data);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Array(frames) => {
+                dst.put_slice(format!("*{}\r\n", frames.len()).as_bytes());
+                for frame in frames {
+                    frame.encode(dst);
+                }
+            }
+            Frame::Null => dst.put_slice(b"$-1\r\n"),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        let mut dst = BytesMut::new();
+        self.encode(&mut dst);
+        dst.freeze()
+    }
+}
+
+struct Parser<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Arrays open around the current position.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn frame(&mut self) -> Result<Frame, ParseError> {
+        let prefix = *self.buf.get(self.pos).ok_or(ParseError::Incomplete)?;
+        self.pos += 1;
+
+        match prefix {
+            b'+' => Ok(Frame::SimpleString(self.line_str()?.to_string())),
+            b'-' => Ok(Frame::Error(self.line_str()?.to_string())),
+            b':' => Ok(Frame::Integer(self.integer()?)),
+            b'$' => match self.length()? {
+                None => Ok(Frame::Null),
+                Some(len) if len > MAX_BULK_LEN => {
+                    Err(ParseError::Invalid("invalid bulk length".into()))
+                }
+                Some(len) => {
+                    let end = self.pos + len;
+                    if self.buf.len() < end + 2 {
+                        return Err(ParseError::Incomplete);
+                    }
+                    if &self.buf[end..end + 2] != b"\r\n" {
+                        return Err(ParseError::Invalid("bulk string not terminated".into()));
+                    }
+                    let data = Bytes::copy_from_slice(&self.buf[self.pos..end]);
+                    self.pos = end + 2;
+                    Ok(Frame::BulkString(data))
+                }
+            },
+            b'*' => match self.length()? {
+                None => Ok(Frame::Null),
+                Some(_) if self.depth == MAX_DEPTH => {
+                    Err(ParseError::Invalid("too many nested arrays".into()))
+                }
+                Some(count) => {
+                    // Don't trust the count for preallocation, each element
+                    // needs at least three bytes.
+                    let mut frames = Vec::with_capacity(count.min(self.buf.len() / 3));
+                    self.depth += 1;
+                    for _ in 0..count {
+                        frames.push(self.frame()?);
+                    }
+                    self.depth -= 1;
+                    Ok(Frame::Array(frames))
+                }
+            },
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Returns the bytes up to the next CRLF and moves past it.
+    fn line(&mut self) -> Result<&[u8], ParseError> {
+        let rest = &self.buf[self.pos..];
+        let end = rest
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or(ParseError::Incomplete)?;
+        self.pos += end + 2;
+        Ok(&rest[..end])
+    }
+
+    fn line_str(&mut self) -> Result<&str, ParseError> {
+        str::from_utf8(self.line()?).map_err(|_| ParseError::Invalid("invalid UTF-8".into()))
+    }
+
+    fn integer(&mut self) -> Result<i64, ParseError> {
+        let line = self.line_str()?;
+        line.parse()
+            .map_err(|_| ParseError::Invalid(format!("invalid integer '{line}'")))
+    }
+
+    /// Parses a bulk string or array length, where -1 means null.
+    fn length(&mut self) -> Result<Option<usize>, ParseError> {
+        match self.integer()? {
+            -1 => Ok(None),
+            n if n < 0 => Err(ParseError::Invalid(format!("invalid length {n}"))),
+            n => Ok(Some(n as usize)),
+        }
+    }
+}
+
+fn unexpected(prefix: u8) -> ParseError {
+    ParseError::Invalid(format!(
+        "expected '+', '-', ':', '$' or '*', got '{}'",
+        prefix.escape_ascii()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(buf: &[u8]) -> Result<Frame, ParseError> {
+        Frame::parse(buf).map(|(frame, _)| frame)
+    }
+
+    fn bulk(s: &str) -> Frame {
+        Frame::BulkString(Bytes::copy_from_slice(s.as_bytes()))
+    }
+
+    #[test]
+    fn test_parse_simple_string() {
+        assert_eq!(parse(b"+OK\r\n"), Ok(Frame::SimpleString("OK".into())));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            parse(b"-ERR unknown\r\n"),
+            Ok(Frame::Error("ERR unknown".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_integer() {
+        assert_eq!(parse(b":1000\r\n"), Ok(Frame::Integer(1000)));
+        assert_eq!(parse(b":-42\r\n"), Ok(Frame::Integer(-42)));
+    }
+
+    #[test]
+    fn test_parse_bulk_string() {
+        assert_eq!(parse(b"$11\r\nhello world\r\n"), Ok(bulk("hello world")));
+        assert_eq!(parse(b"$0\r\n\r\n"), Ok(bulk("")));
+    }
+
+    #[test]
+    fn test_parse_binary_bulk_string() {
+        assert_eq!(
+            parse(b"$4\r\na\r\nb\r\n"),
+            Ok(Frame::BulkString(Bytes::from_static(b"a\r\nb")))
+        );
+    }
+
+    #[test]
+    fn test_parse_null() {
+        assert_eq!(parse(b"$-1\r\n"), Ok(Frame::Null));
+        assert_eq!(parse(b"*-1\r\n"), Ok(Frame::Null));
+    }
+
+    #[test]
+    fn test_parse_array() {
+        assert_eq!(
+            parse(b"*2\r\n$4\r\necho\r\n$11\r\nhello world\r\n"),
+            Ok(Frame::Array(vec![bulk("echo"), bulk("hello world")]))
+        );
+        assert_eq!(parse(b"*0\r\n"), Ok(Frame::Array(vec![])));
+    }
+
+    #[test]
+    fn test_parse_nested_array() {
+        assert_eq!(
+            parse(b"*2\r\n*1\r\n:1\r\n+OK\r\n"),
+            Ok(Frame::Array(vec![
+                Frame::Array(vec![Frame::Integer(1)]),
+                Frame::SimpleString("OK".into()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_consumed_length() {
+        let buf = b"*1\r\n$4\r\nping\r\n*1\r\n$4\r\nping\r\n";
+
+        let (_, len) = Frame::parse(buf).unwrap();
+
+        assert_eq!(len, 14);
+    }
+
+    #[test]
+    fn test_parse_incomplete_frames() {
+        let buf = b"*3\r\n$3\r\nset\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+
+        for end in 0..buf.len() {
+            assert_eq!(parse(&buf[..end]), Err(ParseError::Incomplete), "{end}");
+        }
+        assert!(parse(buf).is_ok());
+    }
+
+    #[test]
+    fn test_scan_finds_end_of_frame_received_in_pieces() {
+        let buf = b"*3\r\n$3\r\nset\r\n*2\r\n:1\r\n*0\r\n$-1\r\n+OK\r\n";
+        let (_, len) = Frame::parse(buf).unwrap();
+
+        let mut scanner = Scanner::default();
+        for end in 0..len {
+            assert_eq!(scanner.scan(&buf[..end]), Ok(None), "{end}");
+        }
+        assert_eq!(scanner.scan(&buf[..len]), Ok(Some(len)));
+        assert_eq!(scanner.scan(buf), Ok(Some(len)));
+    }
+
+    #[test]
+    fn test_scan_rejects_what_parse_does() {
+        let scan = |buf: &[u8]| Scanner::default().scan(buf);
+        let nested = [b"*1\r\n".repeat(MAX_DEPTH + 1), b":1\r\n".to_vec()].concat();
+
+        for buf in [
+            &b"?foo"[..],
+            b"$-2\r\n",
+            b"*1.5\r\n",
+            b"$9999999999\r\n",
+            &nested,
+        ] {
+            assert!(matches!(scan(buf), Err(ParseError::Invalid(_))), "{buf:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_unknown_prefix() {
+        assert!(matches!(parse(b"?foo\r\n"), Err(ParseError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_parse_invalid_integer() {
+        assert!(matches!(parse(b":abc\r\n"), Err(ParseError::Invalid(_))));
+        assert!(matches!(
+            parse(b"$x\r\nfoo\r\n"),
+            Err(ParseError::Invalid(_))
+        ));
+        assert!(matches!(parse(b"*1.5\r\n"), Err(ParseError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_parse_invalid_length() {
+        assert!(matches!(parse(b"$-2\r\n"), Err(ParseError::Invalid(_))));
+        assert!(matches!(parse(b"*-5\r\n"), Err(ParseError::Invalid(_))));
+        assert!(matches!(
+            parse(b"$9999999999\r\n"),
+            Err(ParseError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_bulk_string_length_mismatch() {
+        assert!(matches!(
+            parse(b"$3\r\nhello\r\n"),
+            Err(ParseError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_invalid_element_in_array() {
+        assert!(matches!(
+            parse(b"*2\r\n$4\r\nping\r\n!\r\n"),
+            Err(ParseError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_limits_nesting() {
+        let nested = |depth: usize| [b"*1\r\n".repeat(depth), b":1\r\n".to_vec()].concat();
+
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            parse(&nested(MAX_DEPTH + 1)),
+            Err(ParseError::Invalid(_))
+        ));
+        // Deep enough to overflow the stack if every level recursed.
+        assert!(matches!(
+            parse(&nested(1_000_000)),
+            Err(ParseError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_invalid_utf8_simple_string() {
+        assert!(matches!(parse(b"+\xff\r\n"), Err(ParseError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_encode_frames() {
+        assert_eq!(Frame::SimpleString("OK".into()).to_bytes(), "+OK\r\n");
+        assert_eq!(Frame::Error("ERR bad".into()).to_bytes(), "-ERR bad\r\n");
+        assert_eq!(Frame::Integer(-3).to_bytes(), ":-3\r\n");
+        assert_eq!(bulk("Bar").to_bytes(), "$3\r\nBar\r\n");
+        assert_eq!(Frame::Null.to_bytes(), "$-1\r\n");
+        assert_eq!(
+            Frame::Array(vec![bulk("a"), Frame::Integer(1)]).to_bytes(),
+            "*2\r\n$1\r\na\r\n:1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_parse_round_trip() {
+        let frame = Frame::Array(vec![
+            Frame::SimpleString("OK".into()),
+            Frame::Error("ERR".into()),
+            Frame::Integer(7),
+            bulk("line\r\nbreak"),
+            Frame::Array(vec![bulk("")]),
+        ]);
+        let encoded = frame.to_bytes();
+
+        assert_eq!(Frame::parse(&encoded), Ok((frame, encoded.len())));
+    }
+}
@@ -0,0 +1,1120 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+use crate::aof::{self, Aof, Fsync};
+use crate::cdc::{self, Target};
+use crate::client::Client;
+use crate::cmd::{Command, CommandError};
+use crate::config::Config;
+use crate::connection::Connection;
+use crate::db::{Databases, Db, DbHandle};
+use crate::net::normalize_peer_addr;
+use crate::probe;
+use crate::pubsub::Subscriptions;
+use crate::resp::Frame;
+use crate::shared::Shared;
+use crate::stats::Offence;
+
+/// How often expired keys are reclaimed, matching Redis' default `hz` of 10.
+const ACTIVE_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Expired keys removed per lock acquisition, so a burst of expiries does
+/// not stall clients.
+const ACTIVE_EXPIRE_BATCH: usize = 20;
+
+/// How long one round of active expiry may keep going while it finds a full
+/// batch of expired keys every time, a quarter of the interval as in Redis.
+const ACTIVE_EXPIRE_BUDGET: Duration = Duration::from_millis(25);
+
+/// How often the append-only file is flushed to disk with the `everysec`
+/// policy.
+const AOF_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A running server: the keyspace and the listeners accepting clients for it.
+#[derive(Debug)]
+pub struct Server {
+    config: Config,
+    /// Keeps the keyspace alive for as long as the server runs.
+    _db: DbHandle,
+    listeners: Listeners,
+    expire_task: JoinHandle<()>,
+    /// Flushes the append-only file to disk every second, when that is its
+    /// fsync policy.
+    aof_sync: Option<JoinHandle<()>>,
+    probes: Vec<JoinHandle<()>>,
+    /// Streams writes to the configured change data capture sink until
+    /// told to stop.
+    cdc: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+    /// Set to `true` once shutdown has started.
+    notify_shutdown: watch::Sender<bool>,
+    /// Set to `true` once the shutdown timeout has passed, which closes
+    /// the clients still connected.
+    close_clients: watch::Sender<bool>,
+    /// Closes once every client task, each holding a sender, has finished.
+    shutdown_complete: mpsc::Receiver<()>,
+}
+
+impl Server {
+    /// Resolves the configured addresses and starts listening on them,
+    /// failing if none could be bound.
+    pub async fn bind(config: Config) -> io::Result<Server> {
+        let db = DbHandle::with_databases(config.databases, config.key_hasher);
+        let mut shared = Shared::new(config.clone());
+        if let Some(path) = &config.aof_file {
+            let replayed = aof::replay(path, &db.databases())?;
+            println!("replayed {replayed} commands from {}", path.display());
+            shared = shared.with_aof(Aof::open(path, config.aof_fsync)?);
+        }
+        // Before any client can connect, so that every write is streamed.
+        let cdc_offset = match &config.cdc_sink {
+            Some(target) => Some(record_changes(&db.db(), target).await),
+            None => None,
+        };
+        let shared = Arc::new(shared);
+        let (notify_shutdown, _) = watch::channel(false);
+        let (close_clients, _) = watch::channel(false);
+        let (shutdown_complete_tx, shutdown_complete) = mpsc::channel(1);
+
+        let mut listeners = Listeners {
+            clients: Clients {
+                databases: db.databases(),
+                shared: shared.clone(),
+                shutdown: notify_shutdown.subscribe(),
+                close: close_clients.subscribe(),
+                _complete: shutdown_complete_tx,
+            },
+            active: HashMap::new(),
+        };
+        listeners.rebind(&resolve(&config).await?);
+        if listeners.active.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("could not bind to any address for {:?}", config.bind),
+            ));
+        }
+
+        let expire_task = tokio::spawn(expire_keys(db.databases()));
+        let aof_sync = match &shared.aof {
+            Some(aof) if aof.fsync() == Fsync::EverySec => {
+                Some(tokio::spawn(sync_aof(shared.clone())))
+            }
+            _ => None,
+        };
+        let cdc = match (&config.cdc_sink, cdc_offset) {
+            (Some(target), Some(first_offset)) => {
+                Some(start_cdc(db.db(), target.clone(), first_offset))
+            }
+            _ => None,
+        };
+        let mut probes = Vec::new();
+        if let Some(port) = config.probe_port {
+            let addrs: Vec<_> = resolve(&config)
+                .await?
+                .into_iter()
+                .map(|addr| SocketAddr::new(addr.ip(), port))
+                .collect();
+            for addr in &addrs {
+                let listener = bind(*addr, &addrs)?;
+                println!("serving health probes on {}", listener.local_addr()?);
+                probes.push(tokio::spawn(probe::serve(
+                    listener,
+                    notify_shutdown.subscribe(),
+                )));
+            }
+        }
+
+        Ok(Server {
+            config,
+            _db: db,
+            listeners,
+            expire_task,
+            aof_sync,
+            probes,
+            cdc,
+            notify_shutdown,
+            close_clients,
+            shutdown_complete,
+        })
+    }
+
+    /// The addresses being listened on, with any port 0 replaced by the one
+    /// the system picked.
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.listeners
+            .active
+            .values()
+            .map(|listener| listener.local_addr)
+            .collect()
+    }
+
+    /// Serves clients until `shutdown` completes, then drains them.
+    ///
+    /// Draining stops the listeners, lets commands already executing finish
+    /// and send their replies, answers requests that were already received
+    /// with a `SHUTDOWN in progress` error and closes each connection. Once
+    /// `shutdown_timeout` has passed any remaining clients are dropped.
+    pub async fn run(mut self, shutdown: impl Future<Output = ()>) -> io::Result<()> {
+        tokio::pin!(shutdown);
+
+        #[cfg(unix)]
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+
+        loop {
+            #[cfg(unix)]
+            let rebind = hangup.recv();
+            #[cfg(not(unix))]
+            let rebind = std::future::pending::<Option<()>>();
+
+            tokio::select! {
+                _ = &mut shutdown => break,
+                // Re-resolve the bind addresses on SIGHUP, keeping the
+                // current listeners if the names no longer resolve.
+                _ = rebind => match resolve(&self.config).await {
+                    Ok(addrs) => self.listeners.rebind(&addrs),
+                    Err(err) => println!("error: {err}"),
+                },
+            }
+        }
+
+        println!("shutting down");
+        // CONFIG SET may have changed the timeout since startup.
+        let shared = self.listeners.clients.shared.clone();
+        let timeout = shared.config.get().shutdown_timeout;
+        self.listeners.rebind(&[]);
+        let _ = self.notify_shutdown.send(true);
+        drop(self.listeners);
+
+        if tokio::time::timeout(timeout, self.shutdown_complete.recv())
+            .await
+            .is_err()
+        {
+            println!("shutdown timeout of {timeout:?} reached, closing remaining clients");
+            let _ = self.close_clients.send(true);
+            // Each stops at its next await, without finishing its command.
+            self.shutdown_complete.recv().await;
+        }
+        // Clients are done writing, so whatever the sink has not been sent
+        // yet is all there will be.
+        if let Some((stop, task)) = self.cdc {
+            let _ = stop.send(());
+            if tokio::time::timeout(timeout, task).await.is_err() {
+                println!("shutdown timeout of {timeout:?} reached, dropping unsent changes");
+            }
+        }
+        if let Some(task) = self.aof_sync {
+            task.abort();
+        }
+        if let Some(aof) = &shared.aof {
+            if let Err(err) = aof.sync() {
+                println!("error: syncing the append-only file: {err}");
+            }
+        }
+        self.expire_task.abort();
+        for probe in self.probes {
+            probe.abort();
+        }
+        Ok(())
+    }
+}
+
+/// Reclaims the memory of expired keys in the background. Lookups already
+/// remove the expired keys they find; this catches those nobody asks for.
+async fn expire_keys(databases: Databases) {
+    let mut interval = tokio::time::interval(ACTIVE_EXPIRE_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+
+        // The budget is shared, so many databases don't lengthen a round.
+        let started = tokio::time::Instant::now();
+        for db in databases.iter() {
+            while db.purge_expired(ACTIVE_EXPIRE_BATCH) == ACTIVE_EXPIRE_BATCH
+                && started.elapsed() < ACTIVE_EXPIRE_BUDGET
+            {
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+}
+
+/// Flushes the append-only file to disk once a second, off the runtime's
+/// threads.
+async fn sync_aof(shared: Arc<Shared>) {
+    let mut interval = tokio::time::interval(AOF_SYNC_INTERVAL);
+    loop {
+        interval.tick().await;
+        let shared = shared.clone();
+        let synced = tokio::task::spawn_blocking(move || shared.aof.as_ref().unwrap().sync());
+        if let Ok(Err(err)) = synced.await {
+            println!("error: syncing the append-only file: {err}");
+        }
+    }
+}
+
+/// Starts recording writes for `target`, returning the offset of the
+/// first. A file that already holds changes is appended to, numbering on
+/// from its last one.
+async fn record_changes(db: &Db, target: &Target) -> u64 {
+    let first_offset = match target {
+        Target::File(path) => match tokio::fs::read(path).await {
+            Ok(data) => cdc::last_offset(&String::from_utf8_lossy(&data)).map_or(0, |o| o + 1),
+            Err(_) => 0,
+        },
+        Target::Tcp(_) => 0,
+    };
+    db.record_changes(cdc::BACKLOG, first_offset);
+    first_offset
+}
+
+/// Streams the changes recorded since `first_offset` to `target`.
+fn start_cdc(db: Db, target: Target, first_offset: u64) -> (oneshot::Sender<()>, JoinHandle<()>) {
+    println!("streaming changes to {target} from offset {first_offset}");
+    let (stop, stopped) = oneshot::channel();
+    let task = tokio::spawn(cdc::stream(db, target, first_offset, async {
+        let _ = stopped.await;
+    }));
+    (stop, task)
+}
+
+async fn resolve(config: &Config) -> io::Result<Vec<SocketAddr>> {
+    let mut addrs = Vec::new();
+    for host in &config.bind {
+        let resolved = lookup_host((host.as_str(), config.port))
+            .await
+            .map_err(|err| {
+                io::Error::new(err.kind(), format!("could not resolve '{host}': {err}"))
+            })?;
+        for addr in resolved {
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+    }
+    Ok(addrs)
+}
+
+/// What every client task needs, cloned into each as it is spawned.
+#[derive(Debug, Clone)]
+struct Clients {
+    databases: Databases,
+    shared: Arc<Shared>,
+    shutdown: watch::Receiver<bool>,
+    /// Set once clients that have not finished by the shutdown timeout
+    /// are to be closed.
+    close: watch::Receiver<bool>,
+    _complete: mpsc::Sender<()>,
+}
+
+#[derive(Debug)]
+struct Listener {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct Listeners {
+    clients: Clients,
+    active: HashMap<SocketAddr, Listener>,
+}
+
+impl Listeners {
+    /// Starts listening on any new addresses and stops listening on those that
+    /// are no longer wanted. Connected clients are unaffected.
+    fn rebind(&mut self, addrs: &[SocketAddr]) {
+        self.active.retain(|addr, listener| {
+            let keep = addrs.contains(addr);
+            if !keep {
+                listener.task.abort();
+                println!("stopped listening on {addr}");
+            }
+            keep
+        });
+
+        for addr in addrs {
+            if self.active.contains_key(addr) {
+                continue;
+            }
+            match bind(*addr, addrs).and_then(|l| Ok((l.local_addr()?, l))) {
+                Ok((local_addr, listener)) => {
+                    println!("listening on {local_addr}");
+                    let task = tokio::spawn(accept_clients(listener, self.clients.clone()));
+                    self.active.insert(*addr, Listener { local_addr, task });
+                }
+                Err(err) => {
+                    println!("error: could not bind to {addr}: {err}");
+                }
+            }
+        }
+    }
+}
+
+/// Binds a listener for `addr`. An IPv6 wildcard (`::`) listens dual-stack
+/// unless the IPv4 wildcard on the same port is also wanted, in which case
+/// each gets its own socket.
+fn bind(addr: SocketAddr, wanted: &[SocketAddr]) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if let IpAddr::V6(ip) = addr.ip() {
+        let v4_wildcard = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), addr.port());
+        let dual_stack = ip.is_unspecified() && !wanted.contains(&v4_wildcard);
+        socket.set_only_v6(!dual_stack)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    TcpListener::from_std(socket.into())
+}
+
+async fn accept_clients(listener: TcpListener, clients: Clients) {
+    loop {
+        match listener.accept().await {
+            Ok((socket, addr)) => {
+                let addr = normalize_peer_addr(addr);
+                if clients.shared.stats.bad_clients.is_banned(addr.ip()) {
+                    tokio::spawn(refuse_client(socket));
+                    continue;
+                }
+                let conn = Connection::new(socket);
+                tokio::spawn(handle_client(conn, addr, clients.clone()));
+            }
+            Err(err) => {
+                println!("error: {err}");
+            }
+        };
+    }
+}
+
+/// Tells a banned client why it is being disconnected.
+async fn refuse_client(socket: TcpStream) {
+    let mut conn = Connection::new(socket);
+    let banned = Frame::Error("ERR client banned for too many bad requests".into());
+    let _ = conn.write_frame(&banned).await;
+}
+
+/// Serves one client, listing it in `CLIENT LIST` while it is connected.
+async fn handle_client(conn: Connection, addr: SocketAddr, clients: Clients) {
+    let id = clients.shared.stats.clients.register(addr);
+    let mut client = Client::new(id, conn, Subscriptions::new(clients.shared.pubsub.clone()));
+    client.shutdown = Some(clients.shutdown.clone());
+    let shared = clients.shared.clone();
+    let mut close = clients.close.clone();
+    tokio::select! {
+        _ = serve_client(client, addr.ip(), clients) => {}
+        // Dropping the client closes its connection.
+        Ok(_) = close.wait_for(|&close| close) => {}
+    }
+    shared.stats.clients.remove(id);
+}
+
+async fn serve_client(mut client: Client, ip: IpAddr, mut clients: Clients) {
+    let bad_clients = &clients.shared.stats.bad_clients;
+    while !*clients.shutdown.borrow() {
+        // Only waiting for a request is interrupted by shutdown; a command
+        // that has started always gets to write its whole reply. Messages
+        // for subscribed channels are written out while waiting.
+        let frame = tokio::select! {
+            res = client.conn.read_frame() => res,
+            message = client.subscriptions.recv() => {
+                let written = match message {
+                    Ok(message) => client.conn.write_frame(&message.to_frame()).await,
+                    Err(lagged) => {
+                        println!("disconnecting {ip}: {lagged}");
+                        return;
+                    }
+                };
+                if written.is_err() {
+                    return;
+                }
+                continue;
+            }
+            _ = clients.shutdown.changed() => break,
+        };
+        let frame = match frame {
+            Ok(None) => return,
+            Ok(Some(Frame::Array(args))) if args.is_empty() => continue,
+            Ok(Some(frame)) => frame,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                // The stream can't be resynchronised, so report and hang up.
+                bad_clients.record(ip, Offence::Protocol);
+                let _ = client
+                    .conn
+                    .write_frame(&Frame::Error(format!("ERR {err}")))
+                    .await;
+                return;
+            }
+            Err(_) => return,
+        };
+
+        let mut banned = false;
+        let request = clients.shared.aof.is_some().then(|| frame.clone());
+        let result = match Command::from_frame(frame) {
+            Ok(cmd) => {
+                if let Command::Unknown(_) = cmd {
+                    banned = bad_clients.record(ip, Offence::UnknownCommand);
+                }
+                cmd.apply(&clients.databases, &clients.shared, &mut client, request)
+                    .await
+            }
+            Err(err) => {
+                if let CommandError::Protocol(_) = err {
+                    banned = bad_clients.record(ip, Offence::Protocol);
+                }
+                client.abort_transaction();
+                client
+                    .conn
+                    .write_frame(&Frame::Error(err.to_string()))
+                    .await
+            }
+        };
+        // The request that got the client banned is answered, then it goes.
+        if result.is_err() || banned {
+            return;
+        }
+    }
+
+    // Refuse whatever the client had already sent before hanging up.
+    while let Ok(Some(_)) = client.conn.buffered_frame() {
+        let shutting_down = Frame::Error("SHUTDOWN in progress".into());
+        if client.conn.write_frame(&shutting_down).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_SHUTDOWN_TIMEOUT;
+    use bytes::BytesMut;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::oneshot;
+
+    fn config(shutdown_timeout: Duration) -> Config {
+        Config {
+            port: 0,
+            shutdown_timeout,
+            ..Config::default()
+        }
+    }
+
+    async fn start(
+        shutdown_timeout: Duration,
+    ) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<io::Result<()>>) {
+        let server = Server::bind(config(shutdown_timeout)).await.unwrap();
+        let addr = server.local_addrs()[0];
+        let (trigger, shutdown) = oneshot::channel();
+        let task = tokio::spawn(server.run(async {
+            let _ = shutdown.await;
+        }));
+        (addr, trigger, task)
+    }
+
+    /// What the listeners share, for serving clients without a server. The
+    /// returned sender starts shutdown, and must be kept until then.
+    fn clients() -> (Clients, watch::Sender<bool>) {
+        clients_sharing(Shared::new(Config::default()))
+    }
+
+    fn clients_sharing(shared: Shared) -> (Clients, watch::Sender<bool>) {
+        let config = Config::default();
+        let (notify_shutdown, shutdown) = watch::channel(false);
+        let clients = Clients {
+            databases: DbHandle::with_databases(config.databases, config.key_hasher).databases(),
+            shared: Arc::new(shared),
+            shutdown,
+            close: watch::channel(false).1,
+            _complete: mpsc::channel(1).0,
+        };
+        (clients, notify_shutdown)
+    }
+
+    /// Connects a client over an in-memory pipe, running the same
+    /// parse, execute and reply loop as for a socket.
+    fn connect(clients: &Clients) -> tokio::io::DuplexStream {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let addr = "127.0.0.1:5000".parse().unwrap();
+        tokio::spawn(handle_client(
+            Connection::new(server),
+            addr,
+            clients.clone(),
+        ));
+        client
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_commands_answered_in_order() {
+        let (clients, _shutdown) = clients();
+        let mut client = connect(&clients);
+
+        client
+            .write_all(
+                b"*3\r\n$3\r\nset\r\n$3\r\nFoo\r\n$3\r\nBar\r\n\
+                  *2\r\n$3\r\nget\r\n$3\r\nFoo\r\n\
+                  *1\r\n$4\r\nping\r\n\
+                  *1\r\n$3\r\nget\r\n\
+                  *2\r\n$4\r\necho\r\n$2\r\nhi\r\n",
+            )
+            .await
+            .unwrap();
+
+        let expected: &[u8] = b"+OK\r\n$3\r\nBar\r\n+PONG\r\n\
+            -ERR wrong number of arguments for 'get' command\r\n$2\r\nhi\r\n";
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_select_is_per_connection() {
+        let (clients, _shutdown) = clients();
+        let mut first = connect(&clients);
+        let mut second = connect(&clients);
+
+        first
+            .write_all(
+                b"*2\r\n$6\r\nselect\r\n$1\r\n3\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n",
+            )
+            .await
+            .unwrap();
+        let mut buf = vec![0; 10];
+        first.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, b"+OK\r\n+OK\r\n");
+
+        second
+            .write_all(
+                b"*2\r\n$3\r\nget\r\n$1\r\na\r\n\
+                  *2\r\n$6\r\nselect\r\n$2\r\n16\r\n",
+            )
+            .await
+            .unwrap();
+        let expected: &[u8] = b"$-1\r\n-ERR DB index is out of range\r\n";
+        let mut buf = vec![0; expected.len()];
+        second.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_exec_runs_queued_commands() {
+        let (clients, _shutdown) = clients();
+        let mut client = connect(&clients);
+
+        client
+            .write_all(
+                b"*1\r\n$5\r\nmulti\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n\
+                  *2\r\n$3\r\nget\r\n$1\r\na\r\n\
+                  *1\r\n$4\r\nexec\r\n\
+                  *1\r\n$4\r\nexec\r\n",
+            )
+            .await
+            .unwrap();
+        let expected: &[u8] = b"+OK\r\n+QUEUED\r\n+QUEUED\r\n*2\r\n+OK\r\n$1\r\n1\r\n\
+            -ERR EXEC without MULTI\r\n";
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_exec_refuses_after_rejected_command() {
+        let (clients, _shutdown) = clients();
+        let mut client = connect(&clients);
+
+        client
+            .write_all(
+                b"*1\r\n$5\r\nmulti\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n\
+                  *1\r\n$3\r\nget\r\n\
+                  *1\r\n$4\r\nexec\r\n\
+                  *1\r\n$5\r\nmulti\r\n\
+                  *1\r\n$7\r\ndiscard\r\n\
+                  *2\r\n$6\r\nexists\r\n$1\r\na\r\n",
+            )
+            .await
+            .unwrap();
+        let expected: &[u8] = b"+OK\r\n+QUEUED\r\n\
+            -ERR wrong number of arguments for 'get' command\r\n\
+            -EXECABORT Transaction discarded because of previous errors.\r\n\
+            +OK\r\n+OK\r\n:0\r\n";
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_exec_fails_once_a_watched_key_is_written() {
+        let (clients, _shutdown) = clients();
+        let mut watcher = connect(&clients);
+        let mut writer = connect(&clients);
+
+        watcher
+            .write_all(b"*2\r\n$5\r\nwatch\r\n$1\r\na\r\n")
+            .await
+            .unwrap();
+        let mut buf = vec![0; 5];
+        watcher.read_exact(&mut buf).await.unwrap();
+        writer
+            .write_all(b"*3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n")
+            .await
+            .unwrap();
+        writer.read_exact(&mut buf).await.unwrap();
+
+        watcher
+            .write_all(
+                b"*1\r\n$5\r\nmulti\r\n\
+                  *2\r\n$5\r\nwatch\r\n$1\r\nb\r\n\
+                  *2\r\n$3\r\nget\r\n$1\r\na\r\n\
+                  *1\r\n$4\r\nexec\r\n\
+                  *1\r\n$5\r\nmulti\r\n\
+                  *2\r\n$3\r\nget\r\n$1\r\na\r\n\
+                  *1\r\n$4\r\nexec\r\n",
+            )
+            .await
+            .unwrap();
+        // EXEC unwatched the key, so the second transaction runs.
+        let expected: &[u8] =
+            b"+OK\r\n-ERR WATCH inside MULTI is not allowed\r\n+QUEUED\r\n$-1\r\n\
+            +OK\r\n+QUEUED\r\n*1\r\n$1\r\n1\r\n";
+        let mut buf = vec![0; expected.len()];
+        watcher.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_logged_for_replay() {
+        let path = std::env::temp_dir().join(format!("redis-lite-log-{}.aof", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let aof = Aof::open(&path, Fsync::Always).unwrap();
+        let (clients, _shutdown) = clients_sharing(Shared::new(Config::default()).with_aof(aof));
+        let mut client = connect(&clients);
+
+        client
+            .write_all(
+                b"*5\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n$2\r\nex\r\n$3\r\n100\r\n\
+                  *2\r\n$6\r\nselect\r\n$1\r\n2\r\n\
+                  *1\r\n$5\r\nmulti\r\n\
+                  *4\r\n$5\r\nrpush\r\n$1\r\nl\r\n$1\r\nx\r\n$1\r\ny\r\n\
+                  *2\r\n$3\r\nget\r\n$1\r\nl\r\n\
+                  *1\r\n$4\r\nexec\r\n\
+                  *3\r\n$5\r\nblpop\r\n$1\r\nl\r\n$1\r\n0\r\n",
+            )
+            .await
+            .unwrap();
+        let expected: &[u8] = b"+OK\r\n+OK\r\n+OK\r\n+QUEUED\r\n+QUEUED\r\n\
+            *2\r\n:2\r\n-WRONGTYPE Operation against a key holding the wrong kind of value\r\n\
+            *2\r\n$1\r\nl\r\n$1\r\nx\r\n";
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+
+        let databases = DbHandle::with_databases(3, Default::default()).databases();
+        let replayed = aof::replay(&path, &databases).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // SET with its TTL pinned, then the push and the pop into database 2.
+        assert_eq!(replayed, 6);
+        assert!(databases[0].expires_at("a").is_some());
+        assert_eq!(
+            databases[2].lrange("l", 0, -1).unwrap(),
+            [bytes::Bytes::from("y")]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_writes_are_refused_once_logging_fails() {
+        let aof = Aof::open(std::path::Path::new("/dev/full"), Fsync::No).unwrap();
+        let (clients, _shutdown) = clients_sharing(Shared::new(Config::default()).with_aof(aof));
+        let mut client = connect(&clients);
+
+        client
+            .write_all(
+                b"*3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n\
+                  *3\r\n$5\r\nblpop\r\n$1\r\nl\r\n$1\r\n0\r\n\
+                  *1\r\n$5\r\nmulti\r\n\
+                  *2\r\n$3\r\nget\r\n$1\r\na\r\n\
+                  *1\r\n$4\r\nexec\r\n",
+            )
+            .await
+            .unwrap();
+        let misconf: &[u8] = b"-MISCONF Errors writing to the AOF file: No space left on device";
+        let mut expected = Vec::new();
+        for _ in 0..2 {
+            expected.extend_from_slice(misconf);
+            expected.extend_from_slice(b" (os error 28)\r\n");
+        }
+        expected.extend_from_slice(b"+OK\r\n+QUEUED\r\n*1\r\n$1\r\n1\r\n");
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&buf),
+            String::from_utf8_lossy(&expected)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_replay_matches_concurrent_blocking_pops_and_pushes() {
+        let path = std::env::temp_dir().join(format!("redis-lite-bpop-{}.aof", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let aof = Aof::open(&path, Fsync::No).unwrap();
+        let (clients, _shutdown) = clients_sharing(Shared::new(Config::default()).with_aof(aof));
+
+        let mut poppers = Vec::new();
+        for _ in 0..3 {
+            let mut popper = connect(&clients);
+            poppers.push(tokio::spawn(async move {
+                for _ in 0..50 {
+                    popper
+                        .write_all(b"*3\r\n$5\r\nblpop\r\n$1\r\nq\r\n$1\r\n0\r\n")
+                        .await
+                        .unwrap();
+                    // *2, $1 q, $4 and a four byte element.
+                    popper.read_exact(&mut [0; 21]).await.unwrap();
+                }
+            }));
+        }
+        let mut pushers = Vec::new();
+        for side in ["lpush", "rpush"] {
+            let mut pusher = connect(&clients);
+            pushers.push(tokio::spawn(async move {
+                for n in 0..100 {
+                    let push = aof::command(&[
+                        side.as_bytes(),
+                        b"q",
+                        format!("{side:.1}{n:03}").as_bytes(),
+                    ]);
+                    pusher.write_all(&push.to_bytes()).await.unwrap();
+                    let mut reply = [0; 1];
+                    while reply != *b"\n" {
+                        pusher.read_exact(&mut reply).await.unwrap();
+                    }
+                }
+            }));
+        }
+        for task in poppers.into_iter().chain(pushers) {
+            task.await.unwrap();
+        }
+
+        let databases = DbHandle::with_databases(1, Default::default()).databases();
+        aof::replay(&path, &databases).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let live = clients.databases[0].lrange("q", 0, -1).unwrap();
+        assert_eq!(live.len(), 50);
+        assert_eq!(databases[0].lrange("q", 0, -1).unwrap(), live);
+    }
+
+    #[tokio::test]
+    async fn test_writes_over_limits_are_refused() {
+        let (clients, _shutdown) = clients();
+        let mut client = connect(&clients);
+
+        client
+            .write_all(
+                b"*6\r\n$6\r\nconfig\r\n$3\r\nset\r\n$14\r\nmax-key-length\r\n$1\r\n3\r\n$8\r\nmax-keys\r\n$1\r\n2\r\n\
+                  *3\r\n$3\r\nset\r\n$4\r\nlong\r\n$1\r\n1\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n1\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\nb\r\n$1\r\n1\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\nc\r\n$1\r\n1\r\n\
+                  *3\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\n2\r\n",
+            )
+            .await
+            .unwrap();
+        let expected: &[u8] = b"+OK\r\n-ERR key is longer than max-key-length (3 bytes)\r\n\
+            +OK\r\n+OK\r\n-ERR max-keys reached, at most 2 keys may be stored\r\n+OK\r\n";
+        let mut buf = vec![0; expected.len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_messages() {
+        let (clients, _shutdown) = clients();
+        let mut subscriber = connect(&clients);
+        let mut publisher = connect(&clients);
+
+        subscriber
+            .write_all(b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n$1\r\nb\r\n")
+            .await
+            .unwrap();
+        let expected: &[u8] = b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n\
+            *3\r\n$9\r\nsubscribe\r\n$1\r\nb\r\n:2\r\n";
+        let mut buf = vec![0; expected.len()];
+        subscriber.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+
+        subscriber
+            .write_all(b"*2\r\n$3\r\nget\r\n$1\r\na\r\n*1\r\n$4\r\nping\r\n")
+            .await
+            .unwrap();
+        let expected: &[u8] = b"-ERR Can't execute 'get': only (P)SUBSCRIBE / (P)UNSUBSCRIBE \
+            / PING are allowed in this context\r\n*2\r\n$4\r\npong\r\n$0\r\n\r\n";
+        let mut buf = vec![0; expected.len()];
+        subscriber.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+
+        publisher
+            .write_all(b"*3\r\n$7\r\npublish\r\n$1\r\nb\r\n$2\r\nhi\r\n")
+            .await
+            .unwrap();
+        let mut buf = [0; 4];
+        publisher.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b":1\r\n");
+
+        let expected: &[u8] = b"*3\r\n$7\r\nmessage\r\n$1\r\nb\r\n$2\r\nhi\r\n";
+        let mut buf = vec![0; expected.len()];
+        subscriber.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+
+        // Once the client drops its subscriptions it is back to normal.
+        subscriber
+            .write_all(
+                b"*2\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n\
+                *2\r\n$11\r\nunsubscribe\r\n$1\r\nb\r\n*1\r\n$4\r\nping\r\n",
+            )
+            .await
+            .unwrap();
+        let expected: &[u8] = b"*3\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n:1\r\n\
+            *3\r\n$11\r\nunsubscribe\r\n$1\r\nb\r\n:0\r\n+PONG\r\n";
+        let mut buf = vec![0; expected.len()];
+        subscriber.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[tokio::test]
+    async fn test_client_list_shows_blocked_and_subscribed_clients() {
+        let (clients, _shutdown) = clients();
+        let mut blocked = connect(&clients);
+        let mut subscriber = connect(&clients);
+        let mut admin = connect(&clients);
+
+        blocked
+            .write_all(b"*3\r\n$5\r\nblpop\r\n$4\r\nList\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+        subscriber
+            .write_all(b"*2\r\n$9\r\nsubscribe\r\n$1\r\na\r\n")
+            .await
+            .unwrap();
+        subscriber.read_exact(&mut [0; 30]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        admin
+            .write_all(b"*2\r\n$6\r\nclient\r\n$4\r\nlist\r\n")
+            .await
+            .unwrap();
+        let mut reply = vec![0; 4096];
+        let len = admin.read(&mut reply).await.unwrap();
+        let reply = String::from_utf8_lossy(&reply[..len]).into_owned();
+        let lines: Vec<_> = reply.lines().skip(1).filter(|l| !l.is_empty()).collect();
+
+        assert_eq!(lines.len(), 3, "{reply}");
+        assert!(lines[0].contains("flags=b ") && lines[0].ends_with("cmd=blpop blocked-on=List"));
+        assert!(lines[1].contains("flags=S db=0 sub=1 psub=0 "));
+        assert!(lines[2].contains("flags=N ") && lines[2].ends_with("cmd=client"));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_pop_gives_up_when_client_disconnects() {
+        let (clients, _shutdown) = clients();
+        let mut blocked = connect(&clients);
+        let mut pusher = connect(&clients);
+
+        blocked
+            .write_all(b"*3\r\n$5\r\nblpop\r\n$1\r\nq\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(blocked);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let mut buf = vec![0; 4];
+        pusher
+            .write_all(b"*3\r\n$5\r\nrpush\r\n$1\r\nq\r\n$3\r\njob\r\n")
+            .await
+            .unwrap();
+        pusher.read_exact(&mut buf).await.unwrap();
+        // Give a pop woken by the push the chance to run.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        pusher
+            .write_all(b"*2\r\n$4\r\nllen\r\n$1\r\nq\r\n")
+            .await
+            .unwrap();
+        pusher.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, b":1\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_expired_keys_are_reclaimed() {
+        let handle = DbHandle::new();
+        let db = handle.databases()[3].clone();
+        for n in 0..100 {
+            db.set(format!("key:{n}"), "value".into(), Some(Duration::ZERO));
+        }
+        let task = tokio::spawn(expire_keys(handle.databases()));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        task.abort();
+
+        assert_eq!(db.purge_expired(usize::MAX), 0);
+    }
+
+    #[tokio::test]
+    async fn test_writes_made_right_after_bind_are_streamed() {
+        let path =
+            std::env::temp_dir().join(format!("redis-lite-cdc-{}.jsonl", std::process::id()));
+        // A long history makes finding the offset to carry on from slow.
+        let history: String = (0..200_000)
+            .map(|offset| format!("{{\"offset\":{offset},\"op\":\"del\",\"key\":\"old\"}}\n"))
+            .collect();
+        std::fs::write(&path, history).unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let writer = tokio::spawn(async move {
+            let mut client = loop {
+                if let Ok(client) = TcpStream::connect(("127.0.0.1", port)).await {
+                    break client;
+                }
+                tokio::task::yield_now().await;
+            };
+            client
+                .write_all(b"*3\r\n$3\r\nset\r\n$5\r\nearly\r\n$1\r\n1\r\n")
+                .await
+                .unwrap();
+            client.read_exact(&mut [0; 5]).await.unwrap();
+        });
+        let server = Server::bind(Config {
+            port,
+            cdc_sink: Some(Target::File(path.clone())),
+            ..config(DEFAULT_SHUTDOWN_TIMEOUT)
+        })
+        .await
+        .unwrap();
+        writer.await.unwrap();
+        server.run(async {}).await.unwrap();
+
+        let streamed = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(streamed.contains("{\"offset\":200000,\"op\":\"set\",\"key\":\"early\""));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_finishes_in_flight_commands() {
+        let (addr, trigger, task) = start(DEFAULT_SHUTDOWN_TIMEOUT).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+
+        // The sleep is in flight when shutdown starts, and the PING queued
+        // behind it must be refused rather than executed.
+        client
+            .write_all(
+                b"*3\r\n$5\r\ndebug\r\n$5\r\nsleep\r\n$3\r\n0.2\r\n\
+                  *1\r\n$4\r\nping\r\n",
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        trigger.send(()).unwrap();
+
+        let mut replies = Vec::new();
+        client.read_to_end(&mut replies).await.unwrap();
+        assert_eq!(replies, b"+OK\r\n-SHUTDOWN in progress\r\n");
+
+        // Idle clients are disconnected and nobody new can connect.
+        assert_eq!(idle.read(&mut [0; 1]).await.unwrap(), 0);
+        task.await.unwrap().unwrap();
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_does_not_truncate_replies() {
+        let (addr, trigger, task) = start(DEFAULT_SHUTDOWN_TIMEOUT).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let value = vec![b'x'; 8 * 1024 * 1024];
+        let mut set = BytesMut::new();
+        Frame::Array(vec![
+            Frame::BulkString("set".into()),
+            Frame::BulkString("big".into()),
+            Frame::BulkString(value.clone().into()),
+        ])
+        .encode(&mut set);
+        client.write_all(&set).await.unwrap();
+        let mut ok = [0; 5];
+        client.read_exact(&mut ok).await.unwrap();
+
+        // Nobody reads the reply until shutdown has started, so the server
+        // is blocked part way through writing it.
+        client
+            .write_all(b"*2\r\n$3\r\nget\r\n$3\r\nbig\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        trigger.send(()).unwrap();
+
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await.unwrap();
+        assert_eq!(reply, Frame::BulkString(value.into()).to_bytes());
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_wakes_blocked_pops() {
+        let (addr, trigger, task) = start(DEFAULT_SHUTDOWN_TIMEOUT).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"*3\r\n$5\r\nblpop\r\n$1\r\nk\r\n$1\r\n0\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        trigger.send(()).unwrap();
+
+        let expected = b"-SHUTDOWN in progress\r\n";
+        let mut buf = [0; 23];
+        tokio::time::timeout(Duration::from_secs(1), client.read_exact(&mut buf))
+            .await
+            .expect("the blocked pop should be told about the shutdown")
+            .unwrap();
+        assert_eq!(&buf, expected);
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .expect("shutdown should not wait for the blocked pop")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_timeout_drops_slow_clients() {
+        let (addr, trigger, task) = start(Duration::from_millis(100)).await;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"*3\r\n$5\r\ndebug\r\n$5\r\nsleep\r\n$2\r\n30\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        trigger.send(()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("shutdown should not wait for the sleeping client")
+            .unwrap()
+            .unwrap();
+        let read = tokio::time::timeout(Duration::from_secs(1), client.read(&mut [0; 16]))
+            .await
+            .expect("the sleeping client should have been closed");
+        assert!(matches!(read, Ok(0) | Err(_)), "{read:?}");
+    }
+}
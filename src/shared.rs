@@ -0,0 +1,55 @@
+//! What every client connection shares, apart from the keyspace.
+
+use std::sync::{Arc, RwLock};
+
+use crate::aof::Aof;
+use crate::config::{Config, Limits, LiveConfig};
+use crate::pubsub::PubSub;
+use crate::stats::{BadClients, Stats};
+
+#[derive(Debug)]
+pub struct Shared {
+    pub stats: Stats,
+    pub pubsub: Arc<PubSub>,
+    pub config: LiveConfig,
+    /// Where writes are logged, if they are.
+    pub aof: Option<Aof>,
+    limits: RwLock<Limits>,
+}
+
+impl Shared {
+    pub fn new(config: Config) -> Shared {
+        Shared {
+            stats: Stats {
+                bad_clients: BadClients::new(config.ban_threshold, config.ban_cooldown),
+                ..Stats::default()
+            },
+            pubsub: Arc::default(),
+            aof: None,
+            limits: RwLock::new(config.limits()),
+            config: LiveConfig::new(config),
+        }
+    }
+
+    /// Logs every write to `aof` from now on.
+    pub fn with_aof(self, aof: Aof) -> Shared {
+        Shared {
+            aof: Some(aof),
+            ..self
+        }
+    }
+
+    /// Puts a changed configuration into effect for the settings that are
+    /// not read afresh each time they are used.
+    pub fn reconfigure(&self, config: &Config) {
+        self.stats
+            .bad_clients
+            .set_limits(config.ban_threshold, config.ban_cooldown);
+        *self.limits.write().unwrap() = config.limits();
+    }
+
+    /// The limits writes are checked against.
+    pub fn limits(&self) -> Limits {
+        *self.limits.read().unwrap()
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+/// Wakes async tasks waiting for something to happen, without depending on
+/// a particular runtime.
+///
+/// A waiter takes the current [`epoch`](Signal::epoch) while it can still
+/// see that what it wants has not happened, then awaits
+/// [`changed`](Signal::changed). Every [`notify`](Signal::notify) advances
+/// the epoch, so a notification sent in between is not missed.
+#[derive(Debug, Default)]
+pub struct Signal {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    epoch: u64,
+    next_id: u64,
+    wakers: HashMap<u64, Waker>,
+}
+
+impl Signal {
+    pub fn epoch(&self) -> u64 {
+        self.inner.lock().unwrap().epoch
+    }
+
+    /// Wakes everything waiting on the signal.
+    pub fn notify(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.epoch += 1;
+        for (_, waker) in inner.wakers.drain() {
+            waker.wake();
+        }
+    }
+
+    /// Completes once the signal has been notified since `epoch`.
+    pub fn changed(&self, epoch: u64) -> Changed<'_> {
+        Changed {
+            signal: self,
+            epoch,
+            id: None,
+        }
+    }
+}
+
+/// The future returned by [`Signal::changed`].
+#[derive(Debug)]
+pub struct Changed<'a> {
+    signal: &'a Signal,
+    epoch: u64,
+    /// Where this future's waker is registered, once it has been polled.
+    id: Option<u64>,
+}
+
+impl Future for Changed<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.signal.inner.lock().unwrap();
+        if inner.epoch != self.epoch {
+            return Poll::Ready(());
+        }
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                inner.next_id += 1;
+                inner.next_id
+            }
+        };
+        inner.wakers.insert(id, cx.waker().clone());
+        drop(inner);
+        self.id = Some(id);
+        Poll::Pending
+    }
+}
+
+impl Drop for Changed<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.signal.inner.lock().unwrap().wakers.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct Flag(Mutex<bool>);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            *self.0.lock().unwrap() = true;
+        }
+    }
+
+    fn poll(changed: &mut Changed<'_>, flag: &Arc<Flag>) -> Poll<()> {
+        let waker = Waker::from(flag.clone());
+        Pin::new(changed).poll(&mut Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn test_notify_wakes_waiter() {
+        let signal = Signal::default();
+        let flag = Arc::new(Flag(Mutex::new(false)));
+        let mut changed = signal.changed(signal.epoch());
+
+        assert_eq!(poll(&mut changed, &flag), Poll::Pending);
+        signal.notify();
+
+        assert!(*flag.0.lock().unwrap());
+        assert_eq!(poll(&mut changed, &flag), Poll::Ready(()));
+    }
+
+    #[test]
+    fn test_notify_before_poll_is_not_missed() {
+        let signal = Signal::default();
+        let flag = Arc::new(Flag(Mutex::new(false)));
+        let mut changed = signal.changed(signal.epoch());
+
+        signal.notify();
+
+        assert_eq!(poll(&mut changed, &flag), Poll::Ready(()));
+    }
+
+    #[test]
+    fn test_dropped_waiter_is_forgotten() {
+        let signal = Signal::default();
+        let flag = Arc::new(Flag(Mutex::new(false)));
+        let mut changed = signal.changed(signal.epoch());
+        let _ = poll(&mut changed, &flag);
+
+        drop(changed);
+
+        assert!(signal.inner.lock().unwrap().wakers.is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+//! Server-wide statistics shared by every connection.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::latency::Latencies;
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub latencies: Latencies,
+    pub bad_clients: BadClients,
+    pub clients: ClientList,
+}
+
+/// Kinds of misbehaviour counted against a client's IP address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offence {
+    /// A request that was not valid RESP, or not an array of bulk strings.
+    Protocol,
+    UnknownCommand,
+}
+
+/// The offences recorded for one IP address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BadClient {
+    pub protocol_errors: u64,
+    pub unknown_commands: u64,
+    /// Offences since the address was last banned.
+    strikes: u64,
+    /// When a ban on the address runs out.
+    banned_until: Option<Instant>,
+}
+
+impl BadClient {
+    /// Time left on the address's ban, if it is banned.
+    pub fn banned_for(&self, now: Instant) -> Option<Duration> {
+        self.banned_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+}
+
+/// Counts offences per client IP address and, if a threshold is set, bans
+/// addresses that reach it for a cooldown period.
+#[derive(Debug, Default)]
+pub struct BadClients {
+    limits: Mutex<BanLimits>,
+    clients: Mutex<HashMap<IpAddr, BadClient>>,
+}
+
+#[derive(Debug, Default)]
+struct BanLimits {
+    /// Offences after which an address is banned, `None` to never ban.
+    threshold: Option<u64>,
+    cooldown: Duration,
+}
+
+impl BadClients {
+    pub fn new(threshold: Option<u64>, cooldown: Duration) -> BadClients {
+        BadClients {
+            limits: Mutex::new(BanLimits {
+                threshold,
+                cooldown,
+            }),
+            clients: Mutex::default(),
+        }
+    }
+
+    /// Changes the threshold and cooldown for offences from now on.
+    pub fn set_limits(&self, threshold: Option<u64>, cooldown: Duration) {
+        *self.limits.lock().unwrap() = BanLimits {
+            threshold,
+            cooldown,
+        };
+    }
+
+    /// Counts an offence against `ip`, returning whether that got it banned.
+    pub fn record(&self, ip: IpAddr, offence: Offence) -> bool {
+        let mut clients = self.clients.lock().unwrap();
+        let client = clients.entry(ip).or_default();
+        match offence {
+            Offence::Protocol => client.protocol_errors += 1,
+            Offence::UnknownCommand => client.unknown_commands += 1,
+        }
+        client.strikes += 1;
+
+        let limits = self.limits.lock().unwrap();
+        if limits
+            .threshold
+            .is_some_and(|threshold| client.strikes >= threshold)
+        {
+            client.strikes = 0;
+            client.banned_until = Instant::now().checked_add(limits.cooldown);
+            println!("banning {ip} for {:?}", limits.cooldown);
+            return true;
+        }
+        false
+    }
+
+    pub fn is_banned(&self, ip: IpAddr) -> bool {
+        let clients = self.clients.lock().unwrap();
+        clients
+            .get(&ip)
+            .is_some_and(|client| client.banned_for(Instant::now()).is_some())
+    }
+
+    /// Every address with an offence recorded, worst first.
+    pub fn report(&self) -> Vec<(IpAddr, BadClient)> {
+        let clients = self.clients.lock().unwrap();
+        let mut report: Vec<_> = clients.iter().map(|(ip, c)| (*ip, c.clone())).collect();
+        report.sort_by_key(|(ip, c)| {
+            (
+                std::cmp::Reverse(c.protocol_errors + c.unknown_commands),
+                *ip,
+            )
+        });
+        report
+    }
+}
+
+/// What `CLIENT LIST` shows about a connected client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub addr: SocketAddr,
+    pub connected_at: Instant,
+    /// When the client last finished a command.
+    pub last_active: Instant,
+    /// The last command the client ran, or is still running.
+    pub last_command: &'static str,
+    /// The database the client has selected.
+    pub db: usize,
+    pub channels: usize,
+    pub patterns: usize,
+    /// The keys a blocking command is waiting on, empty if not blocked.
+    pub blocked_on: Vec<String>,
+    /// Bytes received but not yet executed.
+    pub query_buffer: usize,
+    /// Bytes of replies not yet written to the socket.
+    pub output_buffer: usize,
+}
+
+impl ClientInfo {
+    /// The flags as Redis shows them: `S` for a subscriber, `b` for a
+    /// blocked client, `N` for neither.
+    pub fn flags(&self) -> String {
+        let mut flags = String::new();
+        if self.channels + self.patterns > 0 {
+            flags.push('S');
+        }
+        if !self.blocked_on.is_empty() {
+            flags.push('b');
+        }
+        if flags.is_empty() {
+            flags.push('N');
+        }
+        flags
+    }
+
+    /// The client's line in `CLIENT LIST`.
+    pub fn line(&self, now: Instant) -> String {
+        let mut line = String::new();
+        let _ = write!(
+            line,
+            "id={} addr={} age={} idle={} flags={} db={} sub={} psub={} qbuf={} obl={} cmd={}",
+            self.id,
+            self.addr,
+            now.saturating_duration_since(self.connected_at).as_secs(),
+            now.saturating_duration_since(self.last_active).as_secs(),
+            self.flags(),
+            self.db,
+            self.channels,
+            self.patterns,
+            self.query_buffer,
+            self.output_buffer,
+            self.last_command,
+        );
+        if !self.blocked_on.is_empty() {
+            let _ = write!(line, " blocked-on={}", self.blocked_on.join(","));
+        }
+        line
+    }
+}
+
+/// Every connected client, by id.
+#[derive(Debug, Default)]
+pub struct ClientList {
+    next_id: AtomicU64,
+    clients: Mutex<BTreeMap<u64, ClientInfo>>,
+}
+
+impl ClientList {
+    /// Adds a newly connected client and returns its id.
+    pub fn register(&self, addr: SocketAddr) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let now = Instant::now();
+        let info = ClientInfo {
+            id,
+            addr,
+            connected_at: now,
+            last_active: now,
+            last_command: "NULL",
+            db: 0,
+            channels: 0,
+            patterns: 0,
+            blocked_on: Vec::new(),
+            query_buffer: 0,
+            output_buffer: 0,
+        };
+        self.clients.lock().unwrap().insert(id, info);
+        id
+    }
+
+    pub fn update(&self, id: u64, f: impl FnOnce(&mut ClientInfo)) {
+        if let Some(info) = self.clients.lock().unwrap().get_mut(&id) {
+            f(info);
+        }
+    }
+
+    pub fn remove(&self, id: u64) {
+        self.clients.lock().unwrap().remove(&id);
+    }
+
+    /// Every connected client, oldest first.
+    pub fn list(&self) -> Vec<ClientInfo> {
+        self.clients.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_report_worst_first() {
+        let bad_clients = BadClients::default();
+        bad_clients.record(ip("10.0.0.1"), Offence::UnknownCommand);
+        bad_clients.record(ip("10.0.0.2"), Offence::Protocol);
+        bad_clients.record(ip("10.0.0.2"), Offence::UnknownCommand);
+
+        let report = bad_clients.report();
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].0, ip("10.0.0.2"));
+        assert_eq!(report[0].1.protocol_errors, 1);
+        assert_eq!(report[0].1.unknown_commands, 1);
+        assert_eq!(report[1].0, ip("10.0.0.1"));
+        assert!(!bad_clients.is_banned(ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn test_ban_after_threshold() {
+        let bad_clients = BadClients::new(Some(2), Duration::from_millis(20));
+        let client = ip("10.0.0.1");
+
+        assert!(!bad_clients.record(client, Offence::Protocol));
+        assert!(!bad_clients.is_banned(client));
+        assert!(bad_clients.record(client, Offence::Protocol));
+        assert!(bad_clients.is_banned(client));
+        assert!(!bad_clients.is_banned(ip("10.0.0.2")));
+
+        thread::sleep(Duration::from_millis(30));
+        assert!(!bad_clients.is_banned(client));
+    }
+
+    #[test]
+    fn test_client_list() {
+        let clients = ClientList::default();
+        let first = clients.register("127.0.0.1:5000".parse().unwrap());
+        let second = clients.register("127.0.0.1:5001".parse().unwrap());
+        clients.update(second, |info| {
+            info.channels = 1;
+            info.blocked_on = vec!["a".into(), "b".into()];
+            info.last_command = "blpop";
+        });
+        clients.remove(first);
+
+        let list = clients.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].flags(), "Sb");
+        assert_eq!(
+            list[0].line(list[0].connected_at),
+            "id=2 addr=127.0.0.1:5001 age=0 idle=0 flags=Sb db=0 sub=1 psub=0 qbuf=0 \
+             obl=0 cmd=blpop blocked-on=a,b"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Clock helpers. Expiry deadlines are kept as monotonic `Instant`s so they
+//! are unaffected by the wall clock being changed, while anything a client
+//! sees or that outlives the process is in UNIX milliseconds. These convert
+//! between the two.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Time since the UNIX epoch, or zero if the clock is set before it.
+pub fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Milliseconds since the UNIX epoch.
+pub fn unix_millis() -> i64 {
+    unix_now().as_millis() as i64
+}
+
+/// The instant at which the wall clock will read `millis` since the UNIX
+/// epoch. Times in the past map to instants in the past, clamped to the
+/// earliest the monotonic clock can represent. Returns `None` if the time is
+/// too far in the future.
+pub fn instant_from_unix_millis(millis: i64) -> Option<Instant> {
+    instant_after_millis(millis.saturating_sub(unix_millis()))
+}
+
+/// The instant `millis` from now, which may be negative. Returns `None` if
+/// it is too far in the future.
+pub fn instant_after_millis(millis: i64) -> Option<Instant> {
+    let now = Instant::now();
+    let offset = Duration::from_millis(millis.unsigned_abs());
+    if millis >= 0 {
+        now.checked_add(offset)
+    } else {
+        Some(now.checked_sub(offset).unwrap_or(now))
+    }
+}
+
+/// The wall-clock time, in milliseconds since the UNIX epoch, at which
+/// `instant` falls.
+pub fn unix_millis_from_instant(instant: Instant) -> i64 {
+    let now = Instant::now();
+    let unix_now = unix_millis();
+    if instant >= now {
+        unix_now.saturating_add((instant - now).as_millis() as i64)
+    } else {
+        unix_now.saturating_sub((now - instant).as_millis() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unix_millis_round_trip() {
+        let at = unix_millis() + 60_000;
+
+        let instant = instant_from_unix_millis(at).unwrap();
+
+        assert!((unix_millis_from_instant(instant) - at).abs() <= 1);
+    }
+
+    #[test]
+    fn test_instant_from_past_unix_millis() {
+        let instant = instant_from_unix_millis(0).unwrap();
+
+        assert!(instant <= Instant::now());
+    }
+
+    #[test]
+    fn test_instant_after_millis() {
+        let now = Instant::now();
+
+        assert!(instant_after_millis(1000).unwrap() >= now + Duration::from_secs(1));
+        assert!(instant_after_millis(-1000).unwrap() <= Instant::now());
+    }
+}
@@ -0,0 +1,260 @@
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::zset::SortedSet;
+
+/// A value stored under a key.
+///
+/// This is the representation the keyspace uses internally, exposed so
+/// embedders can read values without going through RESP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Value {
+    String(Bytes),
+    /// Elements in order, as stored by LPUSH and RPUSH.
+    List(VecDeque<Bytes>),
+    /// Fields and their values, as stored by HSET.
+    Hash(HashMap<String, Bytes>),
+    /// Unordered unique members, as stored by SADD.
+    Set(HashSet<Bytes>),
+    /// Members ordered by score, as stored by ZADD.
+    SortedSet(SortedSet),
+}
+
+impl Value {
+    /// The type name reported to clients, e.g. by TYPE.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+        }
+    }
+
+    /// The name of the representation, reported by OBJECT ENCODING. These
+    /// are RedisLite's own, except where the structure is the one Redis
+    /// uses under that name.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(_) => "raw",
+            Value::List(_) => "deque",
+            Value::Hash(_) | Value::Set(_) => "hashtable",
+            Value::SortedSet(_) => "btree",
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&Bytes> {
+        match self {
+            Value::String(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+impl From<Bytes> for Value {
+    fn from(data: Bytes) -> Self {
+        Value::String(data)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(data: Vec<u8>) -> Self {
+        Value::String(data.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(Bytes::copy_from_slice(s.as_bytes()))
+    }
+}
+
+impl TryFrom<Value> for Bytes {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(data) => Ok(data),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<u8> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Bytes::try_from(value).map(Vec::from)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Value;
+
+    /// Fails if the value is not a string or is not valid UTF-8, handing
+    /// the value back unchanged.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let data = Bytes::try_from(value)?;
+        String::from_utf8(data.to_vec()).map_err(|_| Value::String(data))
+    }
+}
+
+impl From<HashMap<String, Bytes>> for Value {
+    fn from(hash: HashMap<String, Bytes>) -> Self {
+        Value::Hash(hash)
+    }
+}
+
+impl TryFrom<HashMap<Bytes, Bytes>> for Value {
+    type Error = HashMap<Bytes, Bytes>;
+
+    /// Fails if a field is not valid UTF-8, handing the map back
+    /// unchanged.
+    fn try_from(hash: HashMap<Bytes, Bytes>) -> Result<Self, Self::Error> {
+        if hash.keys().any(|field| std::str::from_utf8(field).is_err()) {
+            return Err(hash);
+        }
+        let hash = hash
+            .into_iter()
+            .map(|(field, value)| (String::from_utf8(field.into()).unwrap(), value))
+            .collect();
+        Ok(Value::Hash(hash))
+    }
+}
+
+impl TryFrom<Value> for HashMap<String, Bytes> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Hash(hash) => Ok(hash),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Value> for HashMap<Bytes, Bytes> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let hash = HashMap::<String, Bytes>::try_from(value)?;
+        Ok(hash
+            .into_iter()
+            .map(|(field, value)| (Bytes::from(field), value))
+            .collect())
+    }
+}
+
+/// Members and their scores.
+impl From<BTreeMap<Bytes, f64>> for Value {
+    fn from(members: BTreeMap<Bytes, f64>) -> Self {
+        Value::SortedSet(members.into_iter().collect())
+    }
+}
+
+/// Members and their scores, ordered by member rather than by score.
+impl TryFrom<Value> for BTreeMap<Bytes, f64> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::SortedSet(set) => Ok(set
+                .iter()
+                .map(|(member, score)| (member.clone(), score))
+                .collect()),
+            other => Err(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_conversions() {
+        let value = Value::from("Bar");
+
+        assert_eq!(value, Value::from("Bar".to_string()));
+        assert_eq!(value, Value::from(b"Bar".to_vec()));
+        assert_eq!(value, Value::from(Bytes::from("Bar")));
+        assert_eq!(String::try_from(value.clone()).unwrap(), "Bar");
+        assert_eq!(Vec::<u8>::try_from(value.clone()).unwrap(), b"Bar");
+        assert_eq!(Bytes::try_from(value).unwrap(), Bytes::from("Bar"));
+    }
+
+    #[test]
+    fn test_invalid_utf8_to_string() {
+        let value = Value::from(vec![0xff, 0xfe]);
+
+        assert_eq!(String::try_from(value.clone()), Err(value));
+    }
+
+    #[test]
+    fn test_hash_conversions() {
+        let fields = HashMap::from([("f".to_string(), Bytes::from("v"))]);
+        let raw = HashMap::from([(Bytes::from("f"), Bytes::from("v"))]);
+        let value = Value::from(fields.clone());
+
+        assert_eq!(Value::try_from(raw.clone()), Ok(value.clone()));
+        assert_eq!(
+            HashMap::<String, Bytes>::try_from(value.clone()),
+            Ok(fields)
+        );
+        assert_eq!(HashMap::<Bytes, Bytes>::try_from(value), Ok(raw));
+
+        let invalid = HashMap::from([(Bytes::from(vec![0xff]), Bytes::from("v"))]);
+        assert_eq!(Value::try_from(invalid.clone()), Err(invalid));
+        let list = Value::List(VecDeque::new());
+        assert_eq!(HashMap::<Bytes, Bytes>::try_from(list.clone()), Err(list));
+    }
+
+    #[test]
+    fn test_sorted_set_conversions() {
+        let members = BTreeMap::from([(Bytes::from("a"), 2.0), (Bytes::from("b"), 1.0)]);
+        let value = Value::from(members.clone());
+
+        let Value::SortedSet(set) = &value else {
+            panic!("not a sorted set: {value:?}");
+        };
+        assert_eq!(set.score(b"a"), Some(2.0));
+        assert_eq!(set.len(), 2);
+        assert_eq!(BTreeMap::try_from(value), Ok(members));
+        assert_eq!(
+            BTreeMap::<Bytes, f64>::try_from(Value::from("Bar")),
+            Err(Value::from("Bar"))
+        );
+    }
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(Value::from("Bar").type_name(), "string");
+        assert_eq!(Value::List(VecDeque::new()).type_name(), "list");
+        assert_eq!(Value::Hash(HashMap::new()).type_name(), "hash");
+        assert_eq!(Value::Set(HashSet::new()).type_name(), "set");
+        assert_eq!(Value::SortedSet(SortedSet::new()).type_name(), "zset");
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(Value::from("12").encoding(), "raw");
+        assert_eq!(Value::List(VecDeque::new()).encoding(), "deque");
+        assert_eq!(Value::Set(HashSet::new()).encoding(), "hashtable");
+    }
+
+    #[test]
+    fn test_hash_is_not_a_string() {
+        let value = Value::Hash(HashMap::from([("f".to_string(), Bytes::from("v"))]));
+
+        assert_eq!(value.as_bytes(), None);
+        assert_eq!(Bytes::try_from(value.clone()), Err(value));
+    }
+}
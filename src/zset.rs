@@ -0,0 +1,300 @@
+use bytes::Bytes;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::range;
+
+/// A sorted set: unique members, each with a score, ordered by score and
+/// then by member.
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: BTreeSet<(Score, Bytes)>,
+}
+
+/// An `f64` ordered with `total_cmp`. Scores are never NaN, and `-0.0` is
+/// stored as `0.0` so the two compare equal.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// The `NX`, `XX`, `GT`, `LT` and `CH` options of ZADD.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AddOptions {
+    /// Only add new members.
+    pub nx: bool,
+    /// Only update existing members.
+    pub xx: bool,
+    /// Only update a score if the new one is greater.
+    pub gt: bool,
+    /// Only update a score if the new one is less.
+    pub lt: bool,
+    /// Count updated members as well as added ones.
+    pub ch: bool,
+}
+
+/// What [`SortedSet::add`] did to a member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    /// Already there, and its score changed.
+    Updated,
+}
+
+/// One end of a score range, as in `ZRANGEBYSCORE key (1 +inf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub score: f64,
+    pub exclusive: bool,
+}
+
+impl SortedSet {
+    pub fn new() -> SortedSet {
+        SortedSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds `member` or updates its score as `options` allow. Returns
+    /// what changed, `None` if nothing did. `options.ch` is left to the
+    /// caller.
+    pub fn add(&mut self, member: Bytes, score: f64, options: AddOptions) -> Option<Change> {
+        let score = if score == 0.0 { 0.0 } else { score };
+        match self.scores.get(&member).copied() {
+            None if options.xx => None,
+            None => {
+                self.ordered.insert((Score(score), member.clone()));
+                self.scores.insert(member, score);
+                Some(Change::Added)
+            }
+            Some(_) if options.nx => None,
+            Some(old) if Score(old) == Score(score) => None,
+            Some(old) if (options.gt && score < old) || (options.lt && score > old) => None,
+            Some(old) => {
+                self.ordered.remove(&(Score(old), member.clone()));
+                self.ordered.insert((Score(score), member.clone()));
+                self.scores.insert(member, score);
+                Some(Change::Updated)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove_entry(member) {
+            Some((member, score)) => self.ordered.remove(&(Score(score), member)),
+            None => false,
+        }
+    }
+
+    /// Members in score order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Members ranked `start..=stop` in score order, with Redis semantics
+    /// for negative and out of range indexes.
+    pub fn range(&self, start: i64, stop: i64) -> Vec<(Bytes, f64)> {
+        let Some((from, to)) = range::normalize(start, stop, self.len()) else {
+            return Vec::new();
+        };
+        self.iter()
+            .skip(from)
+            .take(to - from + 1)
+            .map(|(member, score)| (member.clone(), score))
+            .collect()
+    }
+
+    /// Members with a score between `min` and `max`, in score order.
+    pub fn range_by_score(
+        &self,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> impl Iterator<Item = (&Bytes, f64)> {
+        self.ordered
+            .range((Score(min.score), Bytes::new())..)
+            .map(|(score, member)| (member, score.0))
+            .skip_while(move |(_, score)| min.exclusive && *score == min.score)
+            .take_while(move |(_, score)| {
+                if max.exclusive {
+                    *score < max.score
+                } else {
+                    *score <= max.score
+                }
+            })
+    }
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.ordered == other.ordered
+    }
+}
+
+impl Eq for SortedSet {}
+
+impl FromIterator<(Bytes, f64)> for SortedSet {
+    fn from_iter<I: IntoIterator<Item = (Bytes, f64)>>(iter: I) -> Self {
+        let mut set = SortedSet::new();
+        for (member, score) in iter {
+            set.add(member, score, AddOptions::default());
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(members: &[(&str, f64)]) -> SortedSet {
+        members
+            .iter()
+            .map(|(m, s)| (Bytes::copy_from_slice(m.as_bytes()), *s))
+            .collect()
+    }
+
+    fn members<'a>(iter: impl Iterator<Item = (&'a Bytes, f64)>) -> Vec<&'a [u8]> {
+        iter.map(|(member, _)| member.as_ref()).collect()
+    }
+
+    fn inclusive(score: f64) -> ScoreBound {
+        ScoreBound {
+            score,
+            exclusive: false,
+        }
+    }
+
+    fn exclusive(score: f64) -> ScoreBound {
+        ScoreBound {
+            score,
+            exclusive: true,
+        }
+    }
+
+    #[test]
+    fn test_ordered_by_score_then_member() {
+        let set = set(&[("c", 2.0), ("b", 1.0), ("a", 2.0)]);
+
+        assert_eq!(members(set.iter()), [b"b", b"a", b"c"]);
+        assert_eq!(set.score(b"a"), Some(2.0));
+        assert_eq!(set.score(b"z"), None);
+    }
+
+    #[test]
+    fn test_add_updates_score() {
+        let mut set = set(&[("a", 1.0), ("b", 2.0)]);
+
+        assert_eq!(
+            set.add(Bytes::from("a"), 3.0, AddOptions::default()),
+            Some(Change::Updated)
+        );
+        assert_eq!(members(set.iter()), [b"b", b"a"]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_add_options() {
+        let mut set = set(&[("a", 1.0)]);
+        let nx = AddOptions {
+            nx: true,
+            ..Default::default()
+        };
+        let xx = AddOptions {
+            xx: true,
+            ..Default::default()
+        };
+        let gt = AddOptions {
+            gt: true,
+            ..Default::default()
+        };
+
+        assert_eq!(set.add(Bytes::from("a"), 5.0, nx), None);
+        assert_eq!(set.add(Bytes::from("b"), 5.0, xx), None);
+        assert_eq!(set.add(Bytes::from("a"), 5.0, xx), Some(Change::Updated));
+        assert_eq!(set.add(Bytes::from("a"), 5.0, xx), None);
+        assert_eq!(set.add(Bytes::from("a"), 4.0, gt), None);
+        assert_eq!(set.add(Bytes::from("c"), 4.0, gt), Some(Change::Added));
+        assert_eq!(set.score(b"a"), Some(5.0));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set = set(&[("a", 1.0), ("b", 2.0)]);
+
+        assert!(set.remove(b"a"));
+        assert!(!set.remove(b"a"));
+        assert_eq!(members(set.iter()), [b"b"]);
+    }
+
+    #[test]
+    fn test_range_by_rank() {
+        let set = set(&[("a", 1.0), ("b", 2.0), ("c", 3.0)]);
+
+        assert_eq!(
+            set.range(-2, -1),
+            [(Bytes::from("b"), 2.0), (Bytes::from("c"), 3.0)]
+        );
+        assert!(set.range(5, 10).is_empty());
+    }
+
+    #[test]
+    fn test_range_by_score() {
+        let set = set(&[("a", 1.0), ("b", 2.0), ("c", 2.0), ("d", 3.0)]);
+
+        assert_eq!(
+            members(set.range_by_score(inclusive(2.0), inclusive(3.0))),
+            [b"b", b"c", b"d"]
+        );
+        assert_eq!(
+            members(set.range_by_score(exclusive(1.0), exclusive(3.0))),
+            [b"b", b"c"]
+        );
+        assert_eq!(
+            members(set.range_by_score(inclusive(f64::NEG_INFINITY), inclusive(f64::INFINITY)))
+                .len(),
+            4
+        );
+        assert!(members(set.range_by_score(exclusive(2.0), exclusive(2.0))).is_empty());
+    }
+
+    #[test]
+    fn test_negative_zero_equals_zero() {
+        let mut set = set(&[("a", 0.0)]);
+
+        assert_eq!(set.add(Bytes::from("a"), -0.0, AddOptions::default()), None);
+        assert_eq!(
+            set.range_by_score(inclusive(-0.0), inclusive(0.0)).count(),
+            1
+        );
+    }
+}